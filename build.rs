@@ -0,0 +1,53 @@
+// Compiles the GLSL shaders in `shaders/` to SPIR-V embedded in the binary, see
+// `graphic_engine::shaders`.
+
+use std::{env, fs, path::Path};
+
+fn main() {
+    let out_dir = env::var("OUT_DIR").unwrap();
+    // a path that doesn't exist would rerun the script on every build
+    if Path::new("shaders").is_dir() {
+        println!("cargo:rerun-if-changed=shaders");
+    }
+
+    let mut paths = match fs::read_dir("shaders") {
+        Ok(dir) => dir.map(|entry| entry.unwrap().path()).collect(),
+        Err(_) => Vec::new(),
+    };
+    // stable output, read_dir order is unspecified
+    paths.sort();
+
+    let compiler = shaderc::Compiler::new().unwrap();
+    let mut entries = String::new();
+    for path in paths {
+        let kind = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("vert") => shaderc::ShaderKind::Vertex,
+            Some("frag") => shaderc::ShaderKind::Fragment,
+            Some("comp") => shaderc::ShaderKind::Compute,
+            _ => continue,
+        };
+        let name = path.file_name().unwrap().to_str().unwrap();
+        let source = fs::read_to_string(&path).unwrap();
+
+        let artifact = match compiler.compile_into_spirv(&source, kind, name, "main", None) {
+            Ok(artifact) => artifact,
+            Err(e) => panic!("Failed to compile {}: {}", path.display(), e),
+        };
+        let spirv_path = Path::new(&out_dir).join(format!("{}.spv", name));
+        fs::write(&spirv_path, artifact.as_binary_u8()).unwrap();
+
+        entries.push_str(&format!(
+            "    ({:?}, include_bytes!({:?})),\n",
+            name, spirv_path
+        ));
+    }
+
+    fs::write(
+        Path::new(&out_dir).join("embedded_shaders.rs"),
+        format!(
+            "const EMBEDDED_SHADERS: &[(&str, &[u8])] = &[\n{}];\n",
+            entries
+        ),
+    )
+    .unwrap();
+}
@@ -0,0 +1,118 @@
+#ifndef VULKAN_TUTORIAL_H
+#define VULKAN_TUTORIAL_H
+
+/* Generated by cbindgen from src/capi.rs, don't edit by hand. */
+
+#define VE_OK 0
+
+/**
+ * A pointer argument was null or a string wasn't valid UTF-8.
+ */
+#define VE_ERROR_INVALID_ARGUMENT -1
+
+/**
+ * The device or surface doesn't support the requested feature or setting.
+ */
+#define VE_ERROR_UNSUPPORTED -2
+
+#define VE_ERROR_OUT_OF_MEMORY -3
+
+/**
+ * Any other engine error, with the details printed to stdout.
+ */
+#define VE_ERROR_OTHER -4
+
+typedef struct VeApp VeApp;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates the window and the engine, and writes the app to `app`.
+ *
+ * # Safety
+ *
+ * `app` must be valid for writes.
+ */
+int ve_app_new(VeApp **app);
+
+/**
+ * Destroys an app that wasn't run. Does nothing if `app` is null.
+ *
+ * # Safety
+ *
+ * `app` must come from `ve_app_new` and not be used afterwards.
+ */
+void ve_app_free(VeApp *app);
+
+/**
+ * Runs the event loop, taking ownership of `app`. Doesn't return: the process exits when the
+ * window is closed.
+ *
+ * # Safety
+ *
+ * `app` must come from `ve_app_new` and not be used afterwards.
+ */
+int ve_app_run(VeApp *app);
+
+/**
+ * Sets the color the frame is cleared to, linear for sRGB swapchains and stored as is otherwise.
+ *
+ * # Safety
+ *
+ * `app` must come from `ve_app_new`.
+ */
+int ve_set_clear_color(VeApp *app, float r, float g, float b, float a);
+
+/**
+ * Sets the clear color from sRGB encoded components, for sRGB swapchains.
+ *
+ * # Safety
+ *
+ * `app` must come from `ve_app_new`.
+ */
+int ve_set_clear_color_srgb(VeApp *app, float r, float g, float b, float a);
+
+/**
+ * Sets the vertical field of view, in radians.
+ *
+ * # Safety
+ *
+ * `app` must come from `ve_app_new`.
+ */
+int ve_set_fov(VeApp *app, float fov_y);
+
+/**
+ * Sets the internal resolution relative to the window, see `Graphicengine::set_render_scale`.
+ * Returns `VE_ERROR_UNSUPPORTED` when the swapchain can't be rendered to at another scale.
+ *
+ * # Safety
+ *
+ * `app` must come from `ve_app_new`.
+ */
+int ve_set_render_scale(VeApp *app, float scale);
+
+/**
+ * Saves the next rendered frame as a PNG file at `path`.
+ *
+ * # Safety
+ *
+ * `app` must come from `ve_app_new` and `path` must be a nul-terminated string.
+ */
+int ve_screenshot(VeApp *app, const char *path);
+
+/**
+ * Asks for a frame to be rendered when the app renders on demand.
+ *
+ * # Safety
+ *
+ * `app` must come from `ve_app_new`.
+ */
+int ve_request_redraw(const VeApp *app);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* VULKAN_TUTORIAL_H */
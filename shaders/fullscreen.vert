@@ -0,0 +1,11 @@
+#version 450
+
+// Vertex shader of the fullscreen passes, see src/graphic_engine/fullscreen.rs.
+
+layout(location = 0) out vec2 uv;
+
+// one triangle covering the screen
+void main() {
+    uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    gl_Position = vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+}
@@ -0,0 +1,107 @@
+//! Frame time statistics over the last frames, for measuring uncapped render throughput.
+
+use std::{collections::VecDeque, fmt, time::Duration};
+
+/// Durations between the last rendered frames, oldest first.
+#[derive(Clone, Debug)]
+pub struct FrameStats {
+    frame_times: VecDeque<Duration>,
+    window: usize,
+}
+
+impl FrameStats {
+    /// Keeps the times of the last `window` frames.
+    pub fn new(window: usize) -> FrameStats {
+        let window = window.max(1);
+        FrameStats {
+            frame_times: VecDeque::with_capacity(window),
+            window,
+        }
+    }
+
+    pub fn record(&mut self, frame_time: Duration) {
+        if self.frame_times.len() == self.window {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time);
+    }
+
+    pub fn clear(&mut self) {
+        self.frame_times.clear();
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frame_times.len()
+    }
+
+    /// Average frames per second over the window, 0.0 without frames.
+    pub fn fps(&self) -> f64 {
+        let total: Duration = self.frame_times.iter().sum();
+        if total.is_zero() {
+            return 0.0;
+        }
+        self.frame_times.len() as f64 / total.as_secs_f64()
+    }
+
+    /// Frame time that `percentile` percent of the frames in the window were at most, e.g. 99.0
+    /// for the slowest 1%. Zero without frames.
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        let mut sorted: Vec<_> = self.frame_times.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+        sorted
+            .get(rank.saturating_sub(1))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:.1} fps, frame time p50 {:.2?} p95 {:.2?} p99 {:.2?}",
+            self.fps(),
+            self.percentile(50.0),
+            self.percentile(95.0),
+            self.percentile(99.0)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(milliseconds: &[u64]) -> FrameStats {
+        let mut stats = FrameStats::new(milliseconds.len());
+        for &ms in milliseconds {
+            stats.record(Duration::from_millis(ms));
+        }
+        stats
+    }
+
+    #[test]
+    fn percentile_picks_the_nearest_rank() {
+        let stats = stats(&[40, 10, 30, 20, 50, 60, 70, 80, 90, 100]);
+        assert_eq!(stats.percentile(50.0), Duration::from_millis(50));
+        assert_eq!(stats.percentile(95.0), Duration::from_millis(100));
+        assert_eq!(stats.percentile(10.0), Duration::from_millis(10));
+        // out of range percentiles are clamped
+        assert_eq!(stats.percentile(0.0), Duration::from_millis(10));
+        assert_eq!(stats.percentile(150.0), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn percentile_covers_the_window_only() {
+        let mut stats = stats(&[100, 100]);
+        stats.record(Duration::from_millis(10));
+        stats.record(Duration::from_millis(20));
+        assert_eq!(stats.percentile(100.0), Duration::from_millis(20));
+    }
+
+    #[test]
+    fn percentile_without_frames_is_zero() {
+        assert_eq!(FrameStats::new(8).percentile(99.0), Duration::ZERO);
+    }
+}
@@ -0,0 +1,210 @@
+//! C API over `App`, enabled with the `capi` feature.
+//!
+//! Build a library a C or C++ host can link with
+//! `cargo rustc --release --features capi --crate-type cdylib` (or `staticlib`), and include
+//! `include/vulkan_tutorial.h`. The header is generated by cbindgen from this module:
+//! `cbindgen --config cbindgen.toml --output include/vulkan_tutorial.h`.
+//!
+//! The app is an opaque `VeApp` pointer. Functions report errors as the `VE_*` codes below, `0`
+//! meaning success.
+
+use std::{
+    ffi::CStr,
+    os::raw::{c_char, c_float, c_int},
+};
+
+use crate::{graphic_engine::EngineError, App, AppConfig};
+
+pub const VE_OK: c_int = 0;
+/// A pointer argument was null or a string wasn't valid UTF-8.
+pub const VE_ERROR_INVALID_ARGUMENT: c_int = -1;
+/// The device or surface doesn't support the requested feature or setting.
+pub const VE_ERROR_UNSUPPORTED: c_int = -2;
+pub const VE_ERROR_OUT_OF_MEMORY: c_int = -3;
+/// Any other engine error, with the details printed to stdout.
+pub const VE_ERROR_OTHER: c_int = -4;
+
+fn error_code(error: EngineError) -> c_int {
+    match error {
+        EngineError::UnsupportedInstanceExtensions(_)
+        | EngineError::UnsupportedSurfaceFormat(..)
+        | EngineError::UnsupportedColorSpace(_)
+        | EngineError::UnsupportedPresentMode(_)
+        | EngineError::UnsupportedCompositeAlpha(_)
+        | EngineError::TimestampsUnsupported
+        | EngineError::SampleShadingUnsupported
+        | EngineError::RenderScaleUnsupported
+        | EngineError::UnsupportedTopology(_)
+        | EngineError::UnsupportedSampleCount(_)
+        | EngineError::UnsupportedColorTargetFormat(_)
+        | EngineError::UnsupportedDepthFormat(_)
+        | EngineError::NoSupportedDepthFormat(_)
+        | EngineError::StorageSwapchainUnsupported(_) => VE_ERROR_UNSUPPORTED,
+        EngineError::OutOfMemory(_) | EngineError::AllocationFailed { .. } => {
+            VE_ERROR_OUT_OF_MEMORY
+        }
+        e => {
+            println!("{}", e);
+            VE_ERROR_OTHER
+        }
+    }
+}
+
+/// Creates the window and the engine, and writes the app to `app`.
+///
+/// # Safety
+///
+/// `app` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ve_app_new(app: *mut *mut App) -> c_int {
+    if app.is_null() {
+        return VE_ERROR_INVALID_ARGUMENT;
+    }
+
+    match App::with_config(AppConfig::default()) {
+        Ok(new_app) => {
+            *app = Box::into_raw(Box::new(new_app));
+            VE_OK
+        }
+        Err(e) => error_code(e),
+    }
+}
+
+/// Destroys an app that wasn't run. Does nothing if `app` is null.
+///
+/// # Safety
+///
+/// `app` must come from `ve_app_new` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn ve_app_free(app: *mut App) {
+    if !app.is_null() {
+        drop(Box::from_raw(app));
+    }
+}
+
+/// Runs the event loop, taking ownership of `app`. Doesn't return: the process exits when the
+/// window is closed.
+///
+/// # Safety
+///
+/// `app` must come from `ve_app_new` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn ve_app_run(app: *mut App) -> c_int {
+    if app.is_null() {
+        return VE_ERROR_INVALID_ARGUMENT;
+    }
+
+    Box::from_raw(app).run();
+    VE_OK
+}
+
+/// Sets the color the frame is cleared to, linear for sRGB swapchains and stored as is otherwise.
+///
+/// # Safety
+///
+/// `app` must come from `ve_app_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ve_set_clear_color(
+    app: *mut App,
+    r: c_float,
+    g: c_float,
+    b: c_float,
+    a: c_float,
+) -> c_int {
+    let Some(app) = app.as_mut() else {
+        return VE_ERROR_INVALID_ARGUMENT;
+    };
+
+    app.graphic_engine.set_clear_color([r, g, b, a]);
+    VE_OK
+}
+
+/// Sets the clear color from sRGB encoded components, for sRGB swapchains.
+///
+/// # Safety
+///
+/// `app` must come from `ve_app_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ve_set_clear_color_srgb(
+    app: *mut App,
+    r: c_float,
+    g: c_float,
+    b: c_float,
+    a: c_float,
+) -> c_int {
+    let Some(app) = app.as_mut() else {
+        return VE_ERROR_INVALID_ARGUMENT;
+    };
+
+    app.graphic_engine.set_clear_color_srgb([r, g, b, a]);
+    VE_OK
+}
+
+/// Sets the vertical field of view, in radians.
+///
+/// # Safety
+///
+/// `app` must come from `ve_app_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ve_set_fov(app: *mut App, fov_y: c_float) -> c_int {
+    let Some(app) = app.as_mut() else {
+        return VE_ERROR_INVALID_ARGUMENT;
+    };
+
+    app.graphic_engine.set_fov(fov_y);
+    VE_OK
+}
+
+/// Sets the internal resolution relative to the window, see `Graphicengine::set_render_scale`.
+/// Returns `VE_ERROR_UNSUPPORTED` when the swapchain can't be rendered to at another scale.
+///
+/// # Safety
+///
+/// `app` must come from `ve_app_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ve_set_render_scale(app: *mut App, scale: c_float) -> c_int {
+    let Some(app) = app.as_mut() else {
+        return VE_ERROR_INVALID_ARGUMENT;
+    };
+
+    match app.graphic_engine.set_render_scale(scale) {
+        Ok(()) => VE_OK,
+        Err(e) => error_code(e),
+    }
+}
+
+/// Saves the next rendered frame as a PNG file at `path`.
+///
+/// # Safety
+///
+/// `app` must come from `ve_app_new` and `path` must be a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ve_screenshot(app: *mut App, path: *const c_char) -> c_int {
+    let Some(app) = app.as_mut() else {
+        return VE_ERROR_INVALID_ARGUMENT;
+    };
+    if path.is_null() {
+        return VE_ERROR_INVALID_ARGUMENT;
+    }
+    let Ok(path) = CStr::from_ptr(path).to_str() else {
+        return VE_ERROR_INVALID_ARGUMENT;
+    };
+
+    app.graphic_engine.screenshot(path);
+    VE_OK
+}
+
+/// Asks for a frame to be rendered when the app renders on demand.
+///
+/// # Safety
+///
+/// `app` must come from `ve_app_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ve_request_redraw(app: *const App) -> c_int {
+    let Some(app) = app.as_ref() else {
+        return VE_ERROR_INVALID_ARGUMENT;
+    };
+
+    app.request_redraw();
+    VE_OK
+}
@@ -1,45 +1,443 @@
-use std::sync::Arc;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
 
+use bytemuck::Pod;
 use vulkano::{
+    buffer::{
+        BufferAccess, BufferUsage, CpuAccessibleBuffer, DeviceLocalBuffer, TypedBufferAccess,
+    },
     command_buffer::{
-        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, CommandBufferUsage,
-        RenderPassBeginInfo, SubpassContents,
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, BlitImageInfo,
+        BufferImageCopy, ClearAttachment, ClearRect, CommandBufferInheritanceInfo,
+        CommandBufferInheritanceRenderPassType, CommandBufferInheritanceRenderingInfo,
+        CommandBufferUsage, CopyBufferToImageInfo, CopyImageToBufferInfo,
+        DrawIndexedIndirectCommand, PrimaryAutoCommandBuffer, PrimaryCommandBufferAbstract,
+        RenderPassBeginInfo, RenderingAttachmentInfo, RenderingAttachmentResolveInfo,
+        RenderingInfo, SecondaryAutoCommandBuffer, SubpassContents,
+    },
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator,
+        layout::{
+            DescriptorSetLayout, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo,
+            DescriptorType,
+        },
+        DescriptorSetsCollection, PersistentDescriptorSet, WriteDescriptorSet,
     },
     device::{
         physical::{PhysicalDevice, PhysicalDeviceType},
-        Device, DeviceCreateInfo, DeviceExtensions, Queue, QueueCreateInfo,
+        Device, DeviceCreateInfo, DeviceCreationError, DeviceExtensions, Features, Properties,
+        Queue, QueueCreateInfo,
+    },
+    format::{ClearColorValue, ClearValue, Format, NumericType},
+    image::{
+        view::{ImageView, ImageViewType},
+        AttachmentImage, ImageAccess, ImageAspects, ImageDimensions, ImageLayout,
+        ImageSubresourceLayers, ImageUsage, ImageViewAbstract, ImmutableImage, MipmapsCount,
+        SampleCount, SampleCounts, SwapchainImage,
     },
-    format::ClearValue,
-    image::{view::ImageView, ImageAccess, SwapchainImage},
     instance::Instance,
-    pipeline::graphics::viewport::Viewport,
-    render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass},
+    memory::{allocator::StandardMemoryAllocator, MemoryHeap},
+    pipeline::{
+        cache::PipelineCache,
+        graphics::{
+            depth_stencil::{CompareOp, DepthState, DepthStencilState},
+            input_assembly::{InputAssemblyState, PrimitiveTopology},
+            multisample::MultisampleState,
+            rasterization::{DepthBias, DepthBiasState, PolygonMode, RasterizationState},
+            vertex_input::VertexBuffersCollection,
+            viewport::Viewport,
+            GraphicsPipeline,
+        },
+        DynamicState, Pipeline, PipelineBindPoint, PipelineLayout, StateMode,
+    },
+    render_pass::{
+        AttachmentDescription, AttachmentReference, Framebuffer, FramebufferCreateInfo, LoadOp,
+        RenderPass, RenderPassCreateInfo, StoreOp, Subpass, SubpassDescription,
+    },
+    sampler::{Filter, Sampler},
+    shader::ShaderModule,
     swapchain::{
-        self, AcquireError, Surface, Swapchain, SwapchainCreateInfo, SwapchainCreationError,
-        SwapchainPresentInfo,
+        self, AcquireError, ColorSpace, CompositeAlpha, PresentMode, RectangleLayer, Surface,
+        Swapchain, SwapchainCreateInfo, SwapchainCreationError, SwapchainPresentInfo,
     },
     sync::{self, FlushError, GpuFuture},
+    Version,
 };
 
 use winit::window::Window;
 
+mod adapter;
+#[cfg(feature = "renderdoc")]
+mod capture;
+mod clock;
+mod compute_present;
+mod deferred;
+mod dynamic_buffer;
+mod environment;
+mod error;
+mod frame;
+mod frustum;
+mod fullscreen;
+mod fxaa;
+mod gpu_timer;
+mod hdr;
+mod latency;
+mod light;
+mod lines;
+mod material;
+mod morph;
+mod overlay;
+mod pass;
+mod pipeline_cache;
+mod projection;
+mod recording;
+mod render_texture;
+mod sampler;
+mod screenshot;
+mod shaders;
+mod tangent;
+mod text;
+mod texture;
+mod timeline;
+mod tonemap;
+
+// buffers above this size are uploaded to device-local memory
+const DEFAULT_STAGING_THRESHOLD: u64 = 64 * 1024;
+
+// frames kept by the frame timeline
+const FRAME_TIMELINE_WINDOW: usize = 1000;
+
+// internal resolution relative to the swapchain, see set_render_scale
+const MIN_RENDER_SCALE: f32 = 0.25;
+const MAX_RENDER_SCALE: f32 = 2.0;
+
+/// Depth formats from most to least precise, for `set_depth_format_from`. Vulkan requires
+/// `D16_UNORM` to be supported.
+pub const DEPTH_FORMAT_PREFERENCE: [Format; 3] = [
+    Format::D32_SFLOAT,
+    Format::D24_UNORM_S8_UINT,
+    Format::D16_UNORM,
+];
+
+pub use adapter::AdapterInfo;
+use clock::FrameClock;
+use compute_present::ComputePresent;
+pub use deferred::GBUFFER_FORMATS;
+pub use dynamic_buffer::{DynamicVertexBuffer, DynamicVertexSlice};
+use environment::{Environment, CUBEMAP_FACES};
+pub use error::EngineError;
+use frame::FrameUniformPool;
+pub use frame::{DebugMode, Frame, FrameUniforms, FRAME_SET};
+pub use frustum::{Aabb, Frustum};
+use fullscreen::FullscreenPass;
+use gpu_timer::GpuTimer;
+pub use gpu_timer::GpuTimings;
+pub use hdr::HdrMetadata;
+use latency::PresentPacer;
+pub use light::{PointLight, MAX_LIGHTS_CONSTANT_ID, MAX_POINT_LIGHTS};
+use lines::LineRenderer;
+pub use lines::{Line, LineMode};
+use material::MaterialRegistry;
+pub use morph::{MorphSample, MorphTargets};
+use overlay::OverlayPasses;
+pub use pass::{Pass, PassCycleError};
+pub use projection::{Matrix4, Projection};
+use recording::{FrameLayout, Recording};
+use render_texture::RenderTexture;
+pub use render_texture::RenderTextureHandle;
+use sampler::SamplerCache;
+pub use sampler::SamplerPreset;
+pub use screenshot::ScreenshotError;
+pub use tangent::generate_tangents;
+use text::TextRenderer;
+pub use text::{FontError, SdfFont, TextRun, TextStyle};
+use texture::TextureSamplers;
+pub use texture::{TextureError, TextureErrorKind, TextureOptions};
+pub use timeline::FrameTimeline;
+pub use tonemap::Tonemap;
+use tonemap::TonemapParams;
+
+// swapchain image views, the offscreen image when scaled or post-processed, the multisampled image when
+// enabled, the extra color targets, the depth image when enabled, and framebuffers when rendering
+// with a render pass
+type ImageTargets = (
+    Vec<Arc<ImageView<SwapchainImage>>>,
+    Option<Arc<ImageView<AttachmentImage>>>,
+    Option<Arc<ImageView<AttachmentImage>>>,
+    Vec<Arc<ImageView<AttachmentImage>>>,
+    Option<Arc<ImageView<AttachmentImage>>>,
+    Vec<Arc<Framebuffer>>,
+);
+
+// store ops of the main pass attachments that may be discarded after it
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct StoreOps {
+    multisampled: StoreOp,
+    color_targets: Vec<StoreOp>,
+    depth: StoreOp,
+}
+
+impl Default for StoreOps {
+    fn default() -> StoreOps {
+        StoreOps {
+            multisampled: StoreOp::DontCare,
+            color_targets: Vec::new(),
+            depth: StoreOp::Store,
+        }
+    }
+}
+
+/// Main pass attachment whose contents can be kept or discarded at the end of the pass, see
+/// `Graphicengine::set_store_op`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MainAttachment {
+    /// The multisampled color attachment, resolved into the main target.
+    Multisampled,
+    /// The extra color target at this index of `color_targets`.
+    ColorTarget(usize),
+    Depth,
+}
+
+/// Whether the main pass has a depth attachment and how it is used, see
+/// `Graphicengine::set_depth_mode`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DepthMode {
+    /// No depth attachment, nothing allocated, cleared or stored, for 2D rendering.
+    #[default]
+    Disabled,
+    /// Cleared to the far plane every frame, and tested and written by pipelines.
+    Enabled,
+    /// Loaded as the previous frame stored it and never written in the pass, so pipelines test
+    /// against it without depth writes, e.g. overlays over a frozen 3D scene.
+    ReadOnly,
+}
+
+/// How the swapchain images are rendered to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderPath {
+    /// Render pass and framebuffer objects.
+    RenderPass,
+    /// `VK_KHR_dynamic_rendering`, attaching the swapchain image views directly.
+    DynamicRendering,
+}
+
+/// The swapchain as passed to the `set_on_recreate` callback.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapchainInfo {
+    pub extent: [u32; 2],
+    /// Size of the main pass target, see `Graphicengine::set_render_scale`.
+    pub render_extent: [u32; 2],
+    pub format: Format,
+    pub color_space: ColorSpace,
+    pub image_count: u32,
+}
+
+type RecreateFn = Box<dyn FnMut(&SwapchainInfo)>;
+
+/// How often device creation is tried before giving up, for drivers that fail transiently e.g.
+/// right after being installed or while recovering from a GPU reset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeviceRetry {
+    /// Total number of tries, at least 1.
+    pub attempts: u32,
+    /// Wait before the first retry, doubled after each failed one.
+    pub delay: Duration,
+}
+
+impl Default for DeviceRetry {
+    fn default() -> DeviceRetry {
+        DeviceRetry {
+            attempts: 1,
+            delay: Duration::from_millis(500),
+        }
+    }
+}
+
+/// A rectangle of an image, in pixels from its top left corner.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub offset: [u32; 2],
+    pub extent: [u32; 2],
+}
+
+impl Rect {
+    // the part inside an image of `extent`, if any
+    fn clip(self, extent: [u32; 2]) -> Option<Rect> {
+        let offset = [self.offset[0].min(extent[0]), self.offset[1].min(extent[1])];
+        let extent = [
+            self.extent[0].min(extent[0] - offset[0]),
+            self.extent[1].min(extent[1] - offset[1]),
+        ];
+        (extent[0] > 0 && extent[1] > 0).then_some(Rect { offset, extent })
+    }
+}
+
+/// Main pass attachments cleared by `Graphicengine::clear_region`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClearAspect {
+    /// The main color attachment, to the clear color.
+    Color,
+    /// The depth attachment, to the far plane.
+    Depth,
+    ColorAndDepth,
+}
+
+/// What happened to the last frame the engine tried to render, see
+/// `Graphicengine::last_frame_status`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrameStatus {
+    #[default]
+    Ok,
+    /// Presented, but the swapchain image no longer matched the surface exactly when acquired,
+    /// e.g. during a resize. The swapchain is recreated before the next frame.
+    Suboptimal,
+    /// Presented after recreating the swapchain, e.g. once a resize settled.
+    Recreated,
+    /// Nothing was presented.
+    Skipped(SkipReason),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The swapchain couldn't be recreated for the surface yet, e.g. while minimized.
+    SwapchainUnavailable,
+    /// The swapchain was out of date when acquiring an image, before recording the frame.
+    AcquireOutOfDate,
+    /// The swapchain was out of date when presenting, after the frame was rendered.
+    PresentOutOfDate,
+    /// Submitting the frame failed, see the error returned or printed by the render call.
+    SubmissionFailed,
+    /// Acquiring an image failed, e.g. the surface or device was lost, see the error as above.
+    AcquireFailed,
+    /// Recreating the swapchain failed for another reason than the surface extent, see the
+    /// error as above.
+    SwapchainRecreationFailed,
+}
+
+/// Which kind of GPU is preferred when several can render to the surface.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PowerPreference {
+    /// Discrete GPUs first, then integrated ones.
+    #[default]
+    HighPerformance,
+    /// Integrated GPUs first, e.g. for laptops on battery, then discrete ones.
+    LowPower,
+}
+
 pub struct Graphicengine {
     surface: Arc<Surface>,
+    physical_device: Arc<PhysicalDevice>,
     device: Arc<Device>,
     swapchain: Arc<Swapchain>,
     queue: Arc<Queue>,
+    image_views: Vec<Arc<ImageView<SwapchainImage>>>,
+    offscreen_view: Option<Arc<ImageView<AttachmentImage>>>,
+    msaa_view: Option<Arc<ImageView<AttachmentImage>>>,
+    color_targets: Vec<Arc<ImageView<AttachmentImage>>>,
+    color_target_formats: Vec<Format>,
+    color_target_clears: Vec<ClearValue>,
+    store_ops: StoreOps,
+    main_target_format: Option<Format>,
+    depth_view: Option<Arc<ImageView<AttachmentImage>>>,
+    depth_format: Option<Format>,
+    // Disabled exactly when depth_format is None
+    depth_mode: DepthMode,
+    depth_compare: CompareOp,
     framebuffers: Vec<Arc<Framebuffer>>,
+    memory_allocator: Arc<StandardMemoryAllocator>,
     command_buffer_allocator: StandardCommandBufferAllocator,
+    descriptor_set_allocator: StandardDescriptorSetAllocator,
     render_pass: Arc<RenderPass>,
+    load_op: LoadOp,
+    clear_color: [f32; 4],
+    debug_mode: DebugMode,
+    sample_count: SampleCount,
+    sample_shading: Option<f32>,
+    topology: PrimitiveTopology,
+    primitive_restart: bool,
+    render_scale: f32,
+    fxaa: Option<FullscreenPass>,
+    tonemap: Tonemap,
+    exposure: f32,
+    tonemap_pass: Option<FullscreenPass>,
+    deferred: bool,
+    deferred_lighting: Option<FullscreenPass>,
+    hdr_metadata: Option<HdrMetadata>,
+    present_pacer: PresentPacer,
+    compute_present: Option<ComputePresent>,
+    render_path: RenderPath,
     viewport: Viewport,
+    projection: Projection,
+    view: Matrix4,
+    view_proj: Matrix4,
+    samplers: SamplerCache,
+    materials: MaterialRegistry,
+    frame_uniforms: FrameUniformPool,
+    point_lights: Vec<PointLight>,
+    environment: Environment,
+    reflectivity: f32,
+    clock: FrameClock,
+    passes: Vec<Pass>,
+    pass_order: Vec<usize>,
+    secondaries: Vec<SecondaryAutoCommandBuffer>,
+    text_renderer: Option<TextRenderer>,
+    line_mode: LineMode,
+    line_renderer: Option<LineRenderer>,
+    render_textures: Vec<RenderTexture>,
+    overlay_passes: OverlayPasses,
+    texture_samplers: TextureSamplers,
+    gpu_timer: Option<GpuTimer>,
+    frame_timeline: Option<FrameTimeline>,
+    frame_submissions: Vec<PrimaryAutoCommandBuffer>,
+    // uploads held back until `flush_uploads` while a batch is open
+    upload_batch: Option<Vec<PrimaryAutoCommandBuffer>>,
+    pending_screenshot: Option<PathBuf>,
+    recording: Option<Recording>,
+    present_damage: Vec<Rect>,
+    last_frame_status: FrameStatus,
+    #[cfg(feature = "renderdoc")]
+    capture: capture::FrameCapture,
+    staging_threshold: u64,
+    min_extent: [u32; 2],
+    // a rebuild that couldn't happen yet, e.g. while minimized, redone by the next frame
+    pending_swapchain: Option<SwapchainCreateInfo>,
+    pipeline_cache: Arc<PipelineCache>,
+    pipeline_cache_path: Option<PathBuf>,
+    on_recreate: Option<RecreateFn>,
 
+    frame_count: u64,
+    image_index: Option<u32>,
     previous_frame_end: Option<Box<dyn GpuFuture>>,
 }
 
 impl Graphicengine {
     pub fn new(instance: Arc<Instance>, surface: Arc<Surface>) -> Graphicengine {
+        Graphicengine::with_device_retry(instance, surface, DeviceRetry::default()).unwrap()
+    }
+
+    /// Like `new`, retrying device creation as configured by `retry`.
+    pub fn with_device_retry(
+        instance: Arc<Instance>,
+        surface: Arc<Surface>,
+        retry: DeviceRetry,
+    ) -> Result<Graphicengine, EngineError> {
+        Graphicengine::with_device_options(instance, surface, retry, PowerPreference::default())
+    }
+
+    /// Like `with_device_retry`, picking the GPU by `power_preference`.
+    pub fn with_device_options(
+        instance: Arc<Instance>,
+        surface: Arc<Surface>,
+        retry: DeviceRetry,
+        power_preference: PowerPreference,
+    ) -> Result<Graphicengine, EngineError> {
         // Finding device
-        let device_extensions = DeviceExtensions {
+        let mut device_extensions = DeviceExtensions {
             khr_swapchain: true,
             ..DeviceExtensions::empty()
         };
@@ -48,169 +446,3517 @@ impl Graphicengine {
                 instance,
                 &device_extensions,
                 &surface,
+                power_preference,
             );
-        let (device, mut queues) =
-            Graphicengine::get_device(physical_device, queue_family_index, device_extensions);
+
+        // Prefer dynamic rendering when available
+        let render_path = if Graphicengine::supports_dynamic_rendering(&physical_device) {
+            device_extensions.khr_dynamic_rendering = true;
+            RenderPath::DynamicRendering
+        } else {
+            RenderPath::RenderPass
+        };
+
+        // Bindless materials when descriptor indexing is available
+        let bindless_materials = Graphicengine::supports_descriptor_indexing(&physical_device);
+        device_extensions.ext_descriptor_indexing = bindless_materials;
+
+        // Per-draw bindings pushed into command buffers when possible
+        device_extensions.khr_push_descriptor = physical_device.api_version() >= Version::V1_1
+            && physical_device.supported_extensions().khr_push_descriptor;
+
+        // Damage hints for present_with_damage
+        device_extensions.khr_incremental_present = physical_device
+            .supported_extensions()
+            .khr_incremental_present;
+
+        // Mastering metadata for HDR displays, see set_hdr_metadata
+        device_extensions.ext_hdr_metadata =
+            physical_device.supported_extensions().ext_hdr_metadata;
+
+        // Waiting for presents to reach the display, see set_max_frame_latency
+        let present_wait = physical_device.supported_extensions().khr_present_id
+            && physical_device.supported_extensions().khr_present_wait
+            && physical_device.supported_features().present_id
+            && physical_device.supported_features().present_wait;
+        device_extensions.khr_present_id = present_wait;
+        device_extensions.khr_present_wait = present_wait;
+
+        let (device, mut queues) = Graphicengine::get_device(
+            physical_device.clone(),
+            queue_family_index,
+            device_extensions,
+            retry,
+        )
+        .map_err(EngineError::DeviceCreationFailed)?;
 
         let queue = queues.next().unwrap();
 
-        // Getting swapchain
-        let (swapchain, images) = Graphicengine::get_swapchain(&device, &surface);
+        // Getting swapchain
+        let (swapchain, images) = Graphicengine::get_swapchain(&device, &surface);
+
+        // Allocators
+        let memory_allocator = Arc::new(StandardMemoryAllocator::new_default(device.clone()));
+        let command_buffer_allocator =
+            StandardCommandBufferAllocator::new(device.clone(), Default::default());
+        // takes no pool sizes: each thread gets a pool of 256 sets per layout, doubled when it
+        // runs out, and variable count sets get pools of their own
+        let descriptor_set_allocator = StandardDescriptorSetAllocator::new(device.clone());
+        // Render pass
+        let render_pass = Graphicengine::get_render_pass(
+            &device,
+            swapchain.image_format(),
+            LoadOp::Clear,
+            SampleCount::Sample1,
+            &[],
+            None,
+            &StoreOps::default(),
+        );
+
+        // Viewport
+        let mut viewport = Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [0.0, 0.0],
+            depth_range: 0.0..1.0,
+        };
+
+        // Framebuffer
+        let (image_views, offscreen_view, msaa_view, color_targets, depth_view, framebuffers) =
+            Graphicengine::window_size_dependent_setup(
+                &images,
+                &render_pass,
+                render_path,
+                &memory_allocator,
+                1.0,
+                false,
+                &mut viewport,
+            )?;
+
+        // Camera
+        let projection = Projection::default();
+        let view_proj = projection.matrix(projection::aspect_ratio(swapchain.image_extent()));
+
+        // Samplers
+        let samplers = SamplerCache::new(&device);
+
+        // Materials
+        let materials = MaterialRegistry::new(&device, bindless_materials);
+
+        // in memory until set_pipeline_cache_path
+        let pipeline_cache = PipelineCache::empty(device.clone()).unwrap();
+
+        // Frame uniforms
+        let frame_uniforms = FrameUniformPool::new(&device, memory_allocator.clone());
+
+        // Environment, black until set_environment_map
+        let mut cmd_buffer_builder = AutoCommandBufferBuilder::primary(
+            &command_buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        let black = environment::upload_cubemap(
+            &memory_allocator,
+            vec![0; 4 * CUBEMAP_FACES],
+            1,
+            Format::R8G8B8A8_UNORM,
+            &mut cmd_buffer_builder,
+        )
+        .map_err(EngineError::immutable_image_allocation(
+            4 * CUBEMAP_FACES as u64,
+        ))?;
+        let environment = Environment::new(black, samplers.get(SamplerPreset::LinearRepeat));
+
+        // for renders, after the environment upload
+        let previous_frame_end = Some(Box::new(
+            cmd_buffer_builder
+                .build()
+                .unwrap()
+                .execute(queue.clone())
+                .unwrap(),
+        ) as Box<dyn GpuFuture>);
+
+        Ok(Graphicengine {
+            physical_device,
+            device,
+            surface,
+            swapchain,
+            queue,
+            image_views,
+            offscreen_view,
+            msaa_view,
+            color_targets,
+            color_target_formats: Vec::new(),
+            color_target_clears: Vec::new(),
+            store_ops: StoreOps::default(),
+            main_target_format: None,
+            depth_view,
+            depth_format: None,
+            depth_mode: DepthMode::Disabled,
+            depth_compare: CompareOp::Less,
+            framebuffers,
+            memory_allocator,
+            command_buffer_allocator,
+            descriptor_set_allocator,
+            render_pass,
+            load_op: LoadOp::Clear,
+            clear_color: [0.0, 0.68, 1.0, 1.0],
+            debug_mode: DebugMode::None,
+            sample_count: SampleCount::Sample1,
+            sample_shading: None,
+            topology: PrimitiveTopology::TriangleList,
+            primitive_restart: false,
+            render_scale: 1.0,
+            fxaa: None,
+            tonemap: Tonemap::None,
+            exposure: 1.0,
+            tonemap_pass: None,
+            deferred: false,
+            deferred_lighting: None,
+            hdr_metadata: None,
+            present_pacer: PresentPacer::new(),
+            compute_present: None,
+            render_path,
+            viewport,
+            projection,
+            view: projection::IDENTITY,
+            view_proj,
+            samplers,
+            materials,
+            frame_uniforms,
+            point_lights: Vec::new(),
+            environment,
+            reflectivity: 0.0,
+            clock: FrameClock::new(),
+            passes: Vec::new(),
+            pass_order: Vec::new(),
+            secondaries: Vec::new(),
+            text_renderer: None,
+            line_mode: LineMode::Hardware,
+            line_renderer: None,
+            render_textures: Vec::new(),
+            overlay_passes: OverlayPasses::default(),
+            texture_samplers: TextureSamplers::default(),
+            gpu_timer: None,
+            frame_timeline: None,
+            frame_submissions: Vec::new(),
+            upload_batch: None,
+            pending_screenshot: None,
+            recording: None,
+            present_damage: Vec::new(),
+            last_frame_status: FrameStatus::Ok,
+            #[cfg(feature = "renderdoc")]
+            capture: capture::FrameCapture::new(),
+            staging_threshold: DEFAULT_STAGING_THRESHOLD,
+            min_extent: [0, 0],
+            pending_swapchain: None,
+            pipeline_cache,
+            pipeline_cache_path: None,
+            on_recreate: None,
+            frame_count: 0,
+            image_index: None,
+            previous_frame_end,
+        })
+    }
+
+    pub fn adapter_info(&self) -> AdapterInfo {
+        AdapterInfo::new(&self.physical_device)
+    }
+
+    pub fn physical_device(&self) -> &Arc<PhysicalDevice> {
+        &self.physical_device
+    }
+
+    /// Limits and properties of the GPU.
+    pub fn device_properties(&self) -> &Properties {
+        self.physical_device.properties()
+    }
+
+    /// Features the GPU supports, not all of which are enabled.
+    pub fn supported_features(&self) -> &Features {
+        self.physical_device.supported_features()
+    }
+
+    pub fn enabled_features(&self) -> &Features {
+        self.device.enabled_features()
+    }
+
+    /// Largest width or height of a 2D texture, which `load_texture` and `load_sdf_font` check
+    /// images against.
+    pub fn max_texture_size(&self) -> u32 {
+        self.physical_device.properties().max_image_dimension2_d
+    }
+
+    pub fn max_texture_array_layers(&self) -> u32 {
+        self.physical_device.properties().max_image_array_layers
+    }
+
+    pub fn memory_heaps(&self) -> &[MemoryHeap] {
+        &self.physical_device.memory_properties().memory_heaps
+    }
+
+    pub fn render_path(&self) -> RenderPath {
+        self.render_path
+    }
+
+    /// Sets the vertical field of view, in radians.
+    pub fn set_fov(&mut self, fov_y: f32) {
+        self.projection.fov_y = fov_y;
+        self.update_view_proj();
+    }
+
+    pub fn set_near_far(&mut self, near: f32, far: f32) {
+        self.projection.near = near;
+        self.projection.far = far;
+        self.update_view_proj();
+    }
+
+    /// See `Projection::reverse_z`. `depth_stencil_state` flips the depth test to match.
+    pub fn set_reverse_z(&mut self, reverse_z: bool) {
+        self.projection.reverse_z = reverse_z;
+        self.update_view_proj();
+    }
+
+    /// Depth test of `depth_stencil_state`, `CompareOp::Less` by default, written for depth
+    /// growing away from the camera: it is flipped with reverse Z, e.g. `LessOrEqual` becomes
+    /// `GreaterOrEqual`. Pipelines are built by their owners, so rebuild them after changing it.
+    pub fn set_depth_compare(&mut self, compare_op: CompareOp) {
+        self.depth_compare = compare_op;
+    }
+
+    pub fn depth_compare(&self) -> CompareOp {
+        self.depth_compare
+    }
+
+    pub fn set_view(&mut self, view: Matrix4) {
+        self.view = view;
+        self.update_view_proj();
+    }
+
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    /// Projection times view, using the aspect ratio of the internal resolution.
+    pub fn view_proj(&self) -> Matrix4 {
+        self.view_proj
+    }
+
+    /// The camera frustum of `view_proj`, to test bounds against before recording draws.
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(&self.view_proj)
+    }
+
+    fn update_view_proj(&mut self) {
+        let aspect = projection::aspect_ratio(self.render_extent());
+        self.view_proj = projection::multiply(&self.projection.matrix(aspect), &self.view);
+    }
+
+    /// Registers a texture sampled with the default sampler and returns its material id. With
+    /// bindless materials, fails once the texture array, sized to the device limits, is full.
+    pub fn register_material(
+        &mut self,
+        texture: Arc<dyn ImageViewAbstract>,
+    ) -> Result<u32, EngineError> {
+        let sampler = self.default_sampler();
+        self.materials
+            .register(&self.descriptor_set_allocator, texture, sampler)
+    }
+
+    /// Whether all materials share one descriptor set indexed by material id.
+    pub fn bindless_materials(&self) -> bool {
+        self.materials.is_bindless()
+    }
+
+    /// Whether per-draw sets are pushed into the command buffer with `VK_KHR_push_descriptor`
+    /// rather than allocated, see `bind_per_draw_set`.
+    pub fn push_descriptors(&self) -> bool {
+        self.device.enabled_extensions().khr_push_descriptor
+    }
+
+    /// Creates a layout for a set rebound for every draw, e.g. a model matrix or a texture, to
+    /// bind with `bind_per_draw_set`. It is a push descriptor layout when `push_descriptors` is
+    /// true, so it can't hold dynamic buffers.
+    pub fn per_draw_set_layout(
+        &self,
+        bindings: BTreeMap<u32, DescriptorSetLayoutBinding>,
+    ) -> Arc<DescriptorSetLayout> {
+        DescriptorSetLayout::new(
+            self.device.clone(),
+            DescriptorSetLayoutCreateInfo {
+                bindings,
+                push_descriptor: self.push_descriptors(),
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    }
+
+    /// Binds `writes` at `set` of a pipeline layout, usually using a `per_draw_set_layout` there.
+    /// They are pushed into the command buffer directly when that set layout is a push descriptor
+    /// one, otherwise a descriptor set is allocated for them.
+    pub fn bind_per_draw_set<L>(
+        &self,
+        cmd_buffer_builder: &mut AutoCommandBufferBuilder<L>,
+        pipeline_bind_point: PipelineBindPoint,
+        pipeline_layout: Arc<PipelineLayout>,
+        set: u32,
+        writes: impl IntoIterator<Item = WriteDescriptorSet>,
+    ) {
+        if pipeline_layout.set_layouts()[set as usize].push_descriptor() {
+            cmd_buffer_builder.push_descriptor_set(
+                pipeline_bind_point,
+                pipeline_layout,
+                set,
+                writes,
+            );
+        } else {
+            let descriptor_set = PersistentDescriptorSet::new(
+                &self.descriptor_set_allocator,
+                pipeline_layout.set_layouts()[set as usize].clone(),
+                writes,
+            )
+            .unwrap();
+            cmd_buffer_builder.bind_descriptor_sets(
+                pipeline_bind_point,
+                pipeline_layout,
+                set,
+                descriptor_set,
+            );
+        }
+    }
+
+    /// Binds a uniform buffer the caller owns at `binding` of `set` for the next draws or
+    /// dispatches with `pipeline`, e.g. parameters of a custom shader updated by the caller.
+    ///
+    /// The set is bound with this buffer only, like `bind_per_draw_set`, so its layout must have
+    /// no other binding the shader reads; write several bindings at once with
+    /// `uniform_buffer_write` and `bind_per_draw_set`. Fails if the pipeline layout has no
+    /// `UniformBuffer` binding there, or the buffer lacks the `uniform_buffer` usage.
+    pub fn bind_uniform_buffer<L>(
+        &self,
+        cmd_buffer_builder: &mut AutoCommandBufferBuilder<L>,
+        pipeline: &impl Pipeline,
+        set: u32,
+        binding: u32,
+        buffer: Arc<dyn BufferAccess>,
+    ) -> Result<(), EngineError> {
+        let write = self.uniform_buffer_write(pipeline.layout(), set, binding, buffer)?;
+        self.bind_per_draw_set(
+            cmd_buffer_builder,
+            pipeline.bind_point(),
+            pipeline.layout().clone(),
+            set,
+            [write],
+        );
+        Ok(())
+    }
+
+    /// Like `bind_uniform_buffer` for a `StorageBuffer` binding and a buffer with the
+    /// `storage_buffer` usage, which shaders may also write.
+    pub fn bind_storage_buffer<L>(
+        &self,
+        cmd_buffer_builder: &mut AutoCommandBufferBuilder<L>,
+        pipeline: &impl Pipeline,
+        set: u32,
+        binding: u32,
+        buffer: Arc<dyn BufferAccess>,
+    ) -> Result<(), EngineError> {
+        let write = self.storage_buffer_write(pipeline.layout(), set, binding, buffer)?;
+        self.bind_per_draw_set(
+            cmd_buffer_builder,
+            pipeline.bind_point(),
+            pipeline.layout().clone(),
+            set,
+            [write],
+        );
+        Ok(())
+    }
+
+    /// Write of a uniform buffer at `binding` of `set`, validated as in `bind_uniform_buffer`.
+    pub fn uniform_buffer_write(
+        &self,
+        pipeline_layout: &PipelineLayout,
+        set: u32,
+        binding: u32,
+        buffer: Arc<dyn BufferAccess>,
+    ) -> Result<WriteDescriptorSet, EngineError> {
+        let usage = buffer.usage().uniform_buffer;
+        buffer_write(
+            pipeline_layout,
+            (set, binding),
+            DescriptorType::UniformBuffer,
+            usage,
+            buffer,
+        )
+    }
+
+    /// Write of a storage buffer at `binding` of `set`, validated as in `bind_storage_buffer`.
+    pub fn storage_buffer_write(
+        &self,
+        pipeline_layout: &PipelineLayout,
+        set: u32,
+        binding: u32,
+        buffer: Arc<dyn BufferAccess>,
+    ) -> Result<WriteDescriptorSet, EngineError> {
+        let usage = buffer.usage().storage_buffer;
+        buffer_write(
+            pipeline_layout,
+            (set, binding),
+            DescriptorType::StorageBuffer,
+            usage,
+            buffer,
+        )
+    }
+
+    /// Layout of the material descriptor sets, for building pipeline layouts.
+    pub fn material_set_layout(&self) -> Arc<DescriptorSetLayout> {
+        self.materials.layout()
+    }
+
+    /// Descriptor set to bind when drawing with `material`. With bindless materials the same set
+    /// is returned for every material and only needs binding once.
+    pub fn material_descriptor_set(&self, material: u32) -> Option<Arc<PersistentDescriptorSet>> {
+        self.materials.descriptor_set(material)
+    }
+
+    /// Layout of the frame uniforms set passes get each frame, to put at `FRAME_SET` in pipeline
+    /// layouts.
+    pub fn frame_set_layout(&self) -> Arc<DescriptorSetLayout> {
+        self.frame_uniforms.layout()
+    }
+
+    /// Adds a point light to the `Lights` frame uniform block, up to `MAX_POINT_LIGHTS`: lights
+    /// past it are ignored with a warning. With deferred shading, see `set_deferred`, the
+    /// lighting pass also adds it to the G-buffer.
+    ///
+    /// Shaders size their light array with the `MAX_LIGHTS_CONSTANT_ID` specialization constant,
+    /// and only see the lights that fit in it.
+    pub fn add_point_light(&mut self, position: [f32; 3], color: [f32; 3], radius: f32) {
+        if self.point_lights.len() == MAX_POINT_LIGHTS {
+            println!(
+                "Point light ignored, no more than {} are supported",
+                MAX_POINT_LIGHTS
+            );
+            return;
+        }
+
+        self.point_lights
+            .push(PointLight::new(position, color, radius));
+    }
+
+    pub fn point_lights(&self) -> &[PointLight] {
+        &self.point_lights
+    }
+
+    /// Removes the light at `index` in `point_lights`, shifting the later ones down.
+    pub fn remove_point_light(&mut self, index: usize) -> Option<PointLight> {
+        (index < self.point_lights.len()).then(|| self.point_lights.remove(index))
+    }
+
+    pub fn clear_point_lights(&mut self) {
+        self.point_lights.clear();
+    }
+
+    /// Enables deferred shading: the color targets become the G-buffer in `GBUFFER_FORMATS`,
+    /// which `set_color_targets` can no longer change, and a lighting pass adds the point lights
+    /// to the main target after the main pass, see `deferred` for the shader outputs.
+    ///
+    /// The main target keeps what pipelines write at location 0, e.g. unlit or ambient colors.
+    /// Like other color targets it requires a sample count of 1.
+    pub fn set_deferred(&mut self, enabled: bool) -> Result<(), EngineError> {
+        if enabled == self.deferred {
+            return Ok(());
+        }
+
+        self.deferred = enabled;
+        let formats: &[Format] = if enabled { &GBUFFER_FORMATS } else { &[] };
+        if let Err(e) = self.set_color_targets(formats) {
+            self.deferred = !enabled;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    pub fn deferred(&self) -> bool {
+        self.deferred
+    }
+
+    /// Binds `cubemap`, e.g. from `load_cubemap`, as the environment map of frame uniforms set
+    /// binding 2 for reflections, see `set_reflectivity`. `None` binds a black cubemap again.
+    ///
+    /// Shaders sample it directly with the reflected view vector. It is not image-based
+    /// lighting: nothing is prefiltered, so there is no diffuse term and no roughness.
+    pub fn set_environment_map(
+        &mut self,
+        cubemap: Option<Arc<dyn ImageViewAbstract>>,
+    ) -> Result<(), EngineError> {
+        if let Some(cubemap) = &cubemap {
+            if cubemap.view_type() != ImageViewType::Cube {
+                return Err(EngineError::NotACubemap(cubemap.view_type()));
+            }
+        }
+        self.environment.set_map(cubemap);
+        Ok(())
+    }
+
+    pub fn environment_map(&self) -> Option<&Arc<dyn ImageViewAbstract>> {
+        self.environment.map()
+    }
+
+    /// Sets the `reflectivity` frame uniform, how much of the environment map shaders mix into
+    /// their color, clamped between 0, the default, and 1 for a mirror.
+    pub fn set_reflectivity(&mut self, reflectivity: f32) {
+        self.reflectivity = reflectivity.clamp(0.0, 1.0);
+    }
+
+    pub fn reflectivity(&self) -> f32 {
+        self.reflectivity
+    }
+
+    /// Seconds of the frame clock at the frame being rendered, or last rendered between frames,
+    /// the `time` frame uniform. It starts at 0 with the engine and advances at each frame that
+    /// acquired a swapchain image, see `set_fixed_frame_time`.
+    pub fn elapsed_seconds(&self) -> f32 {
+        self.clock.time().as_secs_f32()
+    }
+
+    /// How much the frame clock advanced for the frame being rendered, or last rendered between
+    /// frames.
+    pub fn frame_delta(&self) -> Duration {
+        self.clock.delta()
+    }
+
+    /// Advances the frame clock by exactly `step` every frame instead of the real time elapsed,
+    /// so animations driven by it and recordings come out the same whatever the frame rate, e.g.
+    /// for golden image tests or capturing video at a fixed rate. `None`, the default, goes back
+    /// to real time from the current clock value.
+    pub fn set_fixed_frame_time(&mut self, step: Option<Duration>) {
+        self.clock.set_fixed_step(step);
+    }
+
+    pub fn fixed_frame_time(&self) -> Option<Duration> {
+        self.clock.fixed_step()
+    }
+
+    /// Whether indirect draws may read more than one command from their buffer.
+    pub fn supports_multi_draw_indirect(&self) -> bool {
+        self.device.enabled_features().multi_draw_indirect
+    }
+
+    /// Highest sample count usable by both color and depth framebuffer attachments.
+    pub fn max_sample_count(&self) -> SampleCount {
+        max_sample_count(&self.supported_sample_counts())
+    }
+
+    pub fn supported_sample_counts(&self) -> Vec<SampleCount> {
+        let properties = self.physical_device.properties();
+        framebuffer_sample_counts(
+            properties.framebuffer_color_sample_counts,
+            properties.framebuffer_depth_sample_counts,
+        )
+    }
+
+    /// Cache to build pipelines with, through `build_with_cache`, so that rebuilding them in this
+    /// run or the next ones skips shader compilation.
+    pub fn pipeline_cache(&self) -> Arc<PipelineCache> {
+        self.pipeline_cache.clone()
+    }
+
+    /// Loads the pipeline cache saved at `path` by a previous run, and saves it there with
+    /// `save_pipeline_cache`. A missing cache, or one from another GPU or driver, is started
+    /// afresh.
+    ///
+    /// Pipelines built before this call don't benefit from it, so call it right after creating
+    /// the engine.
+    pub fn set_pipeline_cache_path(&mut self, path: impl AsRef<Path>) {
+        let path = path.as_ref().to_path_buf();
+        let cache = pipeline_cache::load(&self.device, &path);
+        // keep what was cached so far in this run
+        cache.merge(&[&self.pipeline_cache]).unwrap();
+        self.pipeline_cache = cache;
+        self.pipeline_cache_path = Some(path);
+    }
+
+    /// Writes the pipeline cache to the path given to `set_pipeline_cache_path`, if any. The app
+    /// calls it when the event loop exits.
+    pub fn save_pipeline_cache(&self) -> Result<(), EngineError> {
+        match &self.pipeline_cache_path {
+            Some(path) => pipeline_cache::save(&self.pipeline_cache, path),
+            None => Ok(()),
+        }
+    }
+
+    /// Loads a shader compiled from the `shaders/` directory at build time, by file name.
+    pub fn embedded_shader(&self, name: &str) -> Result<Arc<ShaderModule>, EngineError> {
+        shaders::load(&self.device, name)
+    }
+
+    pub fn embedded_shader_names(&self) -> Vec<&'static str> {
+        shaders::names().collect()
+    }
+
+    pub fn sampler(&self, preset: SamplerPreset) -> Arc<Sampler> {
+        self.samplers.get(preset)
+    }
+
+    pub fn default_sampler(&self) -> Arc<Sampler> {
+        self.samplers.get(SamplerPreset::default())
+    }
+
+    /// Comparison sampler for a shadow map in `depth_format`, filtered linearly when the format
+    /// allows it.
+    pub fn shadow_sampler(&self, depth_format: Format) -> Result<Arc<Sampler>, EngineError> {
+        if !depth_format.aspects().depth {
+            return Err(EngineError::NotDepthFormat(depth_format));
+        }
+
+        let linear = self
+            .physical_device
+            .format_properties(depth_format)
+            .unwrap()
+            .optimal_tiling_features
+            .sampled_image_filter_linear;
+        Ok(self.samplers.get(if linear {
+            SamplerPreset::LinearCompareLess
+        } else {
+            SamplerPreset::NearestCompareLess
+        }))
+    }
+
+    /// Adds a pass recorded every frame before the swapchain render pass, after the passes
+    /// writing the images it reads.
+    pub fn add_pass(&mut self, pass: Pass) -> Result<(), PassCycleError> {
+        self.passes.push(pass);
+        match pass::sort_passes(&self.passes) {
+            Ok(order) => {
+                self.pass_order = order;
+                Ok(())
+            }
+            Err(e) => {
+                self.passes.pop();
+                Err(e)
+            }
+        }
+    }
+
+    pub fn clear_passes(&mut self) {
+        self.passes.clear();
+        self.pass_order.clear();
+    }
+
+    /// Records `record` for every item of `items` into secondary command buffers built on
+    /// worker threads, which the next frame executes in `items` order inside the main pass.
+    ///
+    /// The items are split in contiguous chunks, one per available core, each recorded into its
+    /// own secondary command buffer. The command buffer allocator gives each thread its own
+    /// pools, so recording needs no locking; the command buffers may be dropped on any thread.
+    /// They are only valid for the main pass as currently configured and are consumed by one
+    /// frame, so record them again every frame, after changing main pass settings.
+    pub fn record_parallel<T, F>(&mut self, items: &[T], record: F)
+    where
+        T: Sync,
+        F: Fn(&T, &mut AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>) + Sync,
+    {
+        if items.is_empty() {
+            return;
+        }
+
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk_size = items.len().div_ceil(threads);
+        let inheritance_info = CommandBufferInheritanceInfo {
+            render_pass: Some(self.main_pass_inheritance()),
+            ..Default::default()
+        };
+
+        let (allocator, queue_family_index) = (
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+        );
+        let secondaries: Vec<_> = thread::scope(|scope| {
+            let workers: Vec<_> = items
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    let (record, inheritance_info) = (&record, inheritance_info.clone());
+                    scope.spawn(move || {
+                        let mut cmd_buffer_builder = AutoCommandBufferBuilder::secondary(
+                            allocator,
+                            queue_family_index,
+                            CommandBufferUsage::OneTimeSubmit,
+                            inheritance_info,
+                        )
+                        .unwrap();
+                        for item in chunk {
+                            record(item, &mut cmd_buffer_builder);
+                        }
+                        cmd_buffer_builder.build().unwrap()
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .collect()
+        });
+        self.secondaries.extend(secondaries);
+    }
+
+    /// Loads the PNG at `path` as described by `options`, and returns it with the sampler to read
+    /// it with. Samplers are shared between textures loaded with the same sampling options.
+    ///
+    /// Mips are only generated when the format supports linear blits, check
+    /// `image().mip_levels()` when it matters.
+    pub fn load_texture(
+        &mut self,
+        path: impl AsRef<Path>,
+        options: &TextureOptions,
+    ) -> Result<(Arc<ImageView<ImmutableImage>>, Arc<Sampler>), TextureError> {
+        let path = path.as_ref();
+        let ([width, height], texels) = texture::read_rgba(path).map_err(TextureError::at(path))?;
+        let max = self.max_texture_size();
+        if width > max || height > max {
+            return Err(TextureError::at(path)(TextureErrorKind::TooLarge {
+                dimensions: [width, height],
+                max,
+            }));
+        }
+        let size = texels.len() as u64;
+        let format = if options.srgb {
+            Format::R8G8B8A8_SRGB
+        } else {
+            Format::R8G8B8A8_UNORM
+        };
+        let format_features = self
+            .physical_device
+            .format_properties(format)
+            .unwrap()
+            .optimal_tiling_features;
+        let mipmaps = if options.generate_mips
+            && format_features.blit_src
+            && format_features.blit_dst
+            && format_features.sampled_image_filter_linear
+        {
+            MipmapsCount::Log2
+        } else {
+            MipmapsCount::One
+        };
+
+        let mut cmd_buffer_builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        let image = ImmutableImage::from_iter(
+            &self.memory_allocator,
+            texels,
+            ImageDimensions::Dim2d {
+                width,
+                height,
+                array_layers: 1,
+            },
+            mipmaps,
+            format,
+            &mut cmd_buffer_builder,
+        )
+        .map_err(EngineError::immutable_image_allocation(size))
+        .map_err(TextureError::at(path))?;
+        self.submit_upload(cmd_buffer_builder.build().unwrap());
+
+        let sampler = self
+            .texture_samplers
+            .get(&self.device, options, image.mip_levels() > 1)
+            .map_err(TextureError::at(path))?;
+        Ok((ImageView::new_default(image).unwrap(), sampler))
+    }
+
+    /// Loads a signed distance field font from its PNG atlas and BMFont text metrics. See
+    /// `draw_text_sdf`.
+    pub fn load_sdf_font(
+        &mut self,
+        atlas: impl AsRef<Path>,
+        metrics: impl AsRef<Path>,
+    ) -> Result<SdfFont, FontError> {
+        let ([width, height], texels) = text::read_atlas(atlas.as_ref())?;
+        let max = self.max_texture_size();
+        if width > max || height > max {
+            return Err(FontError::AtlasTooLarge {
+                dimensions: [width, height],
+                max,
+            });
+        }
+        let size = texels.len() as u64;
+
+        let mut cmd_buffer_builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        let image = ImmutableImage::from_iter(
+            &self.memory_allocator,
+            texels,
+            ImageDimensions::Dim2d {
+                width,
+                height,
+                array_layers: 1,
+            },
+            MipmapsCount::One,
+            Format::R8_UNORM,
+            &mut cmd_buffer_builder,
+        )
+        .map_err(EngineError::immutable_image_allocation(size))?;
+        self.submit_upload(cmd_buffer_builder.build().unwrap());
+
+        text::new_font(ImageView::new_default(image).unwrap(), metrics.as_ref())
+    }
+
+    /// Loads a cubemap from six square PNG faces of the same size, in the order +X, -X, +Y, -Y,
+    /// +Z, -Z, and returns its cube view, e.g. for `set_environment_map`. `srgb` is as in
+    /// `TextureOptions`. No mips are generated.
+    pub fn load_cubemap<P: AsRef<Path>>(
+        &mut self,
+        faces: [P; CUBEMAP_FACES],
+        srgb: bool,
+    ) -> Result<Arc<ImageView<ImmutableImage>>, TextureError> {
+        let mut texels = Vec::new();
+        let mut size = 0;
+        for (face, path) in faces.iter().enumerate() {
+            let path = path.as_ref();
+            let ([width, height], face_texels) =
+                texture::read_rgba(path).map_err(TextureError::at(path))?;
+            if face == 0 {
+                size = width;
+            }
+            if width != size || height != size {
+                return Err(TextureError::at(path)(TextureErrorKind::CubemapFaceSize {
+                    face,
+                    dimensions: [width, height],
+                    size,
+                }));
+            }
+            texels.extend(face_texels);
+        }
+        let max = self.physical_device.properties().max_image_dimension_cube;
+        let first_face = faces[0].as_ref();
+        if size > max {
+            return Err(TextureError::at(first_face)(TextureErrorKind::TooLarge {
+                dimensions: [size, size],
+                max,
+            }));
+        }
+        let format = if srgb {
+            Format::R8G8B8A8_SRGB
+        } else {
+            Format::R8G8B8A8_UNORM
+        };
+
+        let allocation_size = texels.len() as u64;
+        let mut cmd_buffer_builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        let view = environment::upload_cubemap(
+            &self.memory_allocator,
+            texels,
+            size,
+            format,
+            &mut cmd_buffer_builder,
+        )
+        .map_err(EngineError::immutable_image_allocation(allocation_size))
+        .map_err(TextureError::at(first_face))?;
+        self.submit_upload(cmd_buffer_builder.build().unwrap());
+
+        Ok(view)
+    }
+
+    /// Draws `text` with `font` in the main pass of the next frame, over what was recorded before.
+    /// `position` is the top left corner of the first line in pixels of `render_extent`, and
+    /// `size` the font size in pixels; `\n` starts a new line.
+    ///
+    /// All the glyphs go in one draw. Like `record_parallel`, it has to be called again every
+    /// frame. Fails if the glyph quads can't be allocated.
+    pub fn draw_text_sdf(
+        &mut self,
+        font: &SdfFont,
+        text: &str,
+        position: [f32; 2],
+        size: f32,
+        style: &TextStyle,
+    ) -> Result<(), EngineError> {
+        self.draw_text_sdf_batch(
+            font,
+            &[TextRun {
+                text,
+                position,
+                size,
+                style: *style,
+            }],
+        )
+    }
+
+    /// Like `draw_text_sdf` for several texts, each with its own position, size and style, all in
+    /// one draw: colors and outlines are vertex attributes rather than uniforms.
+    pub fn draw_text_sdf_batch(
+        &mut self,
+        font: &SdfFont,
+        runs: &[TextRun],
+    ) -> Result<(), EngineError> {
+        if !matches!(&self.text_renderer, Some(renderer) if renderer.is_for(&self.render_pass)) {
+            self.text_renderer = Some(TextRenderer::new(
+                &self.device,
+                &self.render_pass,
+                self.render_path,
+                &self.pipeline_cache,
+            ));
+        }
+
+        let vertices: Vec<_> = runs
+            .iter()
+            .flat_map(|run| {
+                font.layout(
+                    run.text,
+                    run.position,
+                    run.size,
+                    &run.style,
+                    self.viewport.dimensions,
+                )
+            })
+            .collect();
+        if vertices.is_empty() {
+            return Ok(());
+        }
+
+        let mut cmd_buffer_builder = AutoCommandBufferBuilder::secondary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+            CommandBufferInheritanceInfo {
+                render_pass: Some(self.main_pass_inheritance()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        self.text_renderer.as_ref().unwrap().draw(
+            &mut cmd_buffer_builder,
+            &self.memory_allocator,
+            &self.descriptor_set_allocator,
+            &self.viewport,
+            font,
+            vertices,
+        )?;
+        self.secondaries.push(cmd_buffer_builder.build().unwrap());
+        Ok(())
+    }
+
+    /// Draws `lines` in the main pass of the next frame, over what was recorded before, seen
+    /// through the `view_proj` of the call and rasterized as set with `set_line_mode`. They
+    /// aren't depth tested. All the lines go in one draw; like `draw_text_sdf`, it has to be
+    /// called again every frame. Fails if the line vertices can't be allocated.
+    pub fn draw_lines(&mut self, lines: &[Line]) -> Result<(), EngineError> {
+        let lines = lines::clip_space_lines(lines, &self.view_proj, self.projection.reverse_z);
+        if lines.is_empty() {
+            return Ok(());
+        }
+        let current = self
+            .line_renderer
+            .as_ref()
+            .is_some_and(|renderer| renderer.is_for(&self.render_pass, self.line_mode));
+        if !current {
+            self.line_renderer = Some(LineRenderer::new(
+                &self.device,
+                &self.render_pass,
+                self.render_path,
+                self.line_mode,
+                &self.pipeline_cache,
+            ));
+        }
+
+        let mut cmd_buffer_builder = AutoCommandBufferBuilder::secondary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+            CommandBufferInheritanceInfo {
+                render_pass: Some(self.main_pass_inheritance()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        self.line_renderer.as_ref().unwrap().draw(
+            &mut cmd_buffer_builder,
+            &self.memory_allocator,
+            &self.viewport,
+            self.line_mode,
+            &lines,
+        )?;
+        self.secondaries.push(cmd_buffer_builder.build().unwrap());
+        Ok(())
+    }
+
+    /// Draws with `pipeline` in the main pass of the next frame, over what was recorded before,
+    /// from one indexed draw per element of `commands`, which e.g. a culling compute pass can
+    /// write on the GPU. The engine keeps no geometry buffers of its own: the vertex and `u32`
+    /// index buffers are the caller's, and `descriptor_sets` are bound from set 0. The dynamic
+    /// viewport, if any, is the engine's. Like `draw_lines`, it has to be called again every
+    /// frame.
+    ///
+    /// `commands` must have the `indirect_buffer` usage, and more than one command needs
+    /// `supports_multi_draw_indirect`. The draw count is always the length of `commands`:
+    /// vulkano has no `vkCmdDrawIndexedIndirectCount` to read it from a buffer.
+    pub fn draw_indexed_indirect<Ib, Cb>(
+        &mut self,
+        pipeline: &Arc<GraphicsPipeline>,
+        descriptor_sets: impl DescriptorSetsCollection,
+        vertex_buffers: impl VertexBuffersCollection,
+        index_buffer: Arc<Ib>,
+        commands: Arc<Cb>,
+    ) -> Result<(), EngineError>
+    where
+        Ib: TypedBufferAccess<Content = [u32]> + 'static,
+        Cb: TypedBufferAccess<Content = [DrawIndexedIndirectCommand]> + 'static,
+    {
+        if !commands.usage().indirect_buffer {
+            return Err(EngineError::IndirectBufferUsage);
+        }
+
+        let mut cmd_buffer_builder = AutoCommandBufferBuilder::secondary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+            CommandBufferInheritanceInfo {
+                render_pass: Some(self.main_pass_inheritance()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        cmd_buffer_builder.bind_pipeline_graphics(pipeline.clone());
+        if pipeline.dynamic_state(DynamicState::Viewport) == Some(true) {
+            cmd_buffer_builder.set_viewport(0, [self.viewport.clone()]);
+        }
+        let descriptor_sets = descriptor_sets.into_vec();
+        if !descriptor_sets.is_empty() {
+            cmd_buffer_builder.bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                pipeline.layout().clone(),
+                0,
+                descriptor_sets,
+            );
+        }
+        cmd_buffer_builder
+            .bind_vertex_buffers(0, vertex_buffers)
+            .bind_index_buffer(index_buffer)
+            .draw_indexed_indirect(commands)
+            .map_err(EngineError::IndirectDraw)?;
+        self.secondaries.push(cmd_buffer_builder.build().unwrap());
+        Ok(())
+    }
+
+    /// How `draw_lines` rasterizes lines. `LineMode::Hardware`, the default, draws one pixel
+    /// wide line primitives. `LineMode::Quad` draws wider, anti-aliased lines as quads expanded
+    /// in the vertex shader, on any device: there is no hardware line width to depend on.
+    pub fn set_line_mode(&mut self, mode: LineMode) {
+        self.line_mode = mode;
+    }
+
+    pub fn line_mode(&self) -> LineMode {
+        self.line_mode
+    }
+
+    /// Clears `rect` of the main pass attachments in `aspect`, in `render_extent` pixels, at this
+    /// point of the next frame's main pass: over what `record_parallel` and `draw_text_sdf`
+    /// recorded before, and under what they record after. Unlike the load op, it can e.g. clear
+    /// depth between two layers of draws and keep the color.
+    ///
+    /// It is recorded inside the main pass, so it can't end up outside a render pass. Fails when
+    /// clearing depth without a depth attachment, or with a read-only one.
+    pub fn clear_region(&mut self, aspect: ClearAspect, rect: Rect) -> Result<(), EngineError> {
+        if aspect != ClearAspect::Color {
+            match self.depth_mode {
+                DepthMode::Disabled => return Err(EngineError::NoDepthAttachment),
+                DepthMode::ReadOnly => return Err(EngineError::DepthReadOnly),
+                DepthMode::Enabled => {}
+            }
+        }
+        let Some(rect) = rect.clip(self.render_extent()) else {
+            return Ok(());
+        };
+
+        let mut attachments = Vec::with_capacity(2);
+        if aspect != ClearAspect::Depth {
+            attachments.push(ClearAttachment::Color {
+                color_attachment: 0,
+                clear_value: ClearColorValue::Float(self.clear_color),
+            });
+        }
+        if aspect != ClearAspect::Color {
+            attachments.push(ClearAttachment::Depth(self.far_depth()));
+        }
+
+        let mut cmd_buffer_builder = AutoCommandBufferBuilder::secondary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+            CommandBufferInheritanceInfo {
+                render_pass: Some(self.main_pass_inheritance()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        cmd_buffer_builder
+            .clear_attachments(
+                attachments,
+                [ClearRect {
+                    offset: rect.offset,
+                    extent: rect.extent,
+                    array_layers: 0..1,
+                }],
+            )
+            .unwrap();
+        self.secondaries.push(cmd_buffer_builder.build().unwrap());
+        Ok(())
+    }
+
+    // what secondary command buffers executed in the main pass draw to
+    fn main_pass_inheritance(&self) -> CommandBufferInheritanceRenderPassType {
+        match self.render_path {
+            RenderPath::RenderPass => Subpass::from(self.render_pass.clone(), 0).unwrap().into(),
+            RenderPath::DynamicRendering => CommandBufferInheritanceRenderingInfo {
+                color_attachment_formats: [self.main_format()]
+                    .iter()
+                    .chain(&self.color_target_formats)
+                    .map(|&format| Some(format))
+                    .collect(),
+                depth_attachment_format: self.depth_format,
+                rasterization_samples: self.sample_count,
+                ..Default::default()
+            }
+            .into(),
+        }
+    }
+
+    /// Maximum number of frames the GPU may be working on at once.
+    pub fn frames_in_flight(&self) -> usize {
+        self.swapchain.image_count() as usize
+    }
+
+    /// In-flight slot of the frame being rendered, or last rendered between frames, in
+    /// `0..frames_in_flight`. Resources ringed per frame in flight can be picked with it.
+    ///
+    /// It advances once per frame that acquired a swapchain image, before passes are recorded.
+    /// It is 0 before the first frame.
+    pub fn current_frame_index(&self) -> usize {
+        (self.frame_count.saturating_sub(1) % self.frames_in_flight() as u64) as usize
+    }
+
+    /// Measures how long the GPU spends on each part of every frame, read with `gpu_timings`.
+    /// Fails if the graphics queue can't write timestamps.
+    pub fn set_gpu_timing(&mut self, enabled: bool) -> Result<(), EngineError> {
+        self.gpu_timer = if enabled {
+            Some(GpuTimer::new(
+                &self.device,
+                &self.memory_allocator,
+                self.queue.queue_family_index(),
+                self.frames_in_flight(),
+            )?)
+        } else {
+            None
+        };
+        Ok(())
+    }
+
+    /// Most recent frame timings the GPU has finished, without waiting for it. They lag
+    /// `frames_in_flight` frames behind, and are `None` until the first ones are read back or
+    /// while GPU timing is disabled.
+    pub fn gpu_timings(&self) -> Option<GpuTimings> {
+        self.gpu_timer.as_ref().and_then(GpuTimer::latest)
+    }
+
+    /// Records CPU timestamps along the render path of every frame from now on, for
+    /// `frame_timeline_stats`: when acquire is called and returns, and when the frame is
+    /// submitted and its present completes. Off by default, restarting it clears the stats.
+    ///
+    /// Present completion is measured while present wait bounds the frame latency, see
+    /// `set_max_frame_latency`. Otherwise it is approximated by the next acquire returning,
+    /// which can be a frame or more later than the image was shown.
+    pub fn enable_frame_timeline(&mut self) {
+        self.frame_timeline = Some(FrameTimeline::new(FRAME_TIMELINE_WINDOW));
+    }
+
+    pub fn disable_frame_timeline(&mut self) {
+        self.frame_timeline = None;
+    }
+
+    /// Distributions of the stage times of the last frames, `None` while the frame timeline is
+    /// disabled.
+    pub fn frame_timeline_stats(&self) -> Option<&FrameTimeline> {
+        self.frame_timeline.as_ref()
+    }
+
+    /// Swapchain image the frame being rendered, or last rendered between frames, draws to.
+    /// `None` before the first frame and after the swapchain is recreated, until the next frame
+    /// acquires an image.
+    pub fn last_acquired_image_index(&self) -> Option<u32> {
+        self.image_index
+    }
+
+    /// Creates a ring of `frames_in_flight` vertex buffers for geometry rewritten every frame.
+    pub fn create_dynamic_vertex_buffer<T: Pod + Send + Sync>(
+        &self,
+        capacity: usize,
+    ) -> Result<DynamicVertexBuffer<T>, EngineError> {
+        DynamicVertexBuffer::new(
+            self.memory_allocator.clone(),
+            self.frames_in_flight(),
+            capacity,
+        )
+    }
+
+    /// Uploads the positions of a mesh at each keyframe, in the same vertex order, to animate it
+    /// by blending them. See `MorphTargets::sample`.
+    pub fn create_morph_targets(
+        &mut self,
+        keyframes: &[&[[f32; 3]]],
+    ) -> Result<MorphTargets, EngineError> {
+        let vertex_count = keyframes
+            .first()
+            .ok_or(EngineError::NoMorphKeyframes)?
+            .len();
+        if let Some(keyframe) = keyframes.iter().find(|k| k.len() != vertex_count) {
+            return Err(EngineError::MorphVertexCount {
+                expected: vertex_count,
+                actual: keyframe.len(),
+            });
+        }
+
+        let usage = BufferUsage {
+            vertex_buffer: true,
+            ..BufferUsage::empty()
+        };
+        let buffers = keyframes
+            .iter()
+            .map(|keyframe| self.create_buffer(usage, keyframe))
+            .collect::<Result<_, _>>()?;
+        Ok(MorphTargets::new(buffers, vertex_count))
+    }
+
+    /// Size in bytes above which `create_buffer` uploads through a staging buffer to device-local
+    /// memory instead of leaving the data host-visible. Defaults to 64 KiB, `0` stages everything.
+    pub fn set_staging_threshold(&mut self, bytes: u64) {
+        self.staging_threshold = bytes;
+    }
+
+    pub fn staging_threshold(&self) -> u64 {
+        self.staging_threshold
+    }
+
+    /// Creates a buffer holding `data`, for data written once and read by the GPU afterwards.
+    ///
+    /// Small buffers stay host-visible and skip the copy, larger ones are made device-local
+    /// according to the staging threshold. The upload is submitted right away and orders before
+    /// the next frame, or is held in the upload batch if one is open.
+    pub fn create_buffer<T: Pod + Send + Sync>(
+        &mut self,
+        usage: BufferUsage,
+        data: &[T],
+    ) -> Result<Arc<dyn BufferAccess>, EngineError> {
+        let size = std::mem::size_of_val(data) as u64;
+        if size <= self.staging_threshold {
+            return Ok(CpuAccessibleBuffer::from_iter(
+                &self.memory_allocator,
+                usage,
+                false,
+                data.iter().copied(),
+            )
+            .map_err(EngineError::allocation(size))?);
+        }
+
+        let mut cmd_buffer_builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        let buffer = DeviceLocalBuffer::from_iter(
+            &self.memory_allocator,
+            data.iter().copied(),
+            usage,
+            &mut cmd_buffer_builder,
+        )
+        .map_err(EngineError::allocation(size))?;
+        self.submit_upload(cmd_buffer_builder.build().unwrap());
+
+        Ok(buffer)
+    }
+
+    /// Overwrites a region of mip level 0 of the first layer of `view` with tightly packed texels.
+    ///
+    /// The image needs the `transfer_dst` usage and an uncompressed format. The copy is submitted
+    /// right away and orders before the next frame, or is held in the upload batch if one is open.
+    pub fn update_texture_region(
+        &mut self,
+        view: &Arc<dyn ImageViewAbstract>,
+        origin: [u32; 3],
+        extent: [u32; 3],
+        data: &[u8],
+    ) -> Result<(), EngineError> {
+        let image = view.image();
+        if !image.usage().transfer_dst {
+            return Err(EngineError::TextureNotTransferDst);
+        }
+        // the size check below counts texels, not blocks
+        if image.format().compression().is_some() {
+            return Err(EngineError::CompressedTextureRegion(image.format()));
+        }
+
+        let dimensions = image.dimensions().width_height_depth();
+        if (0..3).any(|i| origin[i].saturating_add(extent[i]) > dimensions[i]) {
+            return Err(EngineError::TextureRegionOutOfBounds {
+                origin,
+                extent,
+                dimensions,
+            });
+        }
+
+        let block_size = image.format().block_size().unwrap() as usize;
+        let expected = extent.iter().map(|&e| e as usize).product::<usize>() * block_size;
+        if data.len() != expected {
+            return Err(EngineError::TextureDataSize {
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        let staging = CpuAccessibleBuffer::from_iter(
+            &self.memory_allocator,
+            BufferUsage {
+                transfer_src: true,
+                ..BufferUsage::empty()
+            },
+            false,
+            data.iter().copied(),
+        )
+        .map_err(EngineError::allocation(data.len() as u64))?;
+
+        let layer = view.subresource_range().array_layers.start;
+        let mut cmd_buffer_builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        cmd_buffer_builder
+            .copy_buffer_to_image(CopyBufferToImageInfo {
+                regions: [BufferImageCopy {
+                    // a zero row length means rows are tightly packed
+                    buffer_row_length: 0,
+                    image_subresource: ImageSubresourceLayers {
+                        mip_level: 0,
+                        array_layers: layer..layer + 1,
+                        ..image.subresource_layers()
+                    },
+                    image_offset: origin,
+                    image_extent: extent,
+                    ..Default::default()
+                }]
+                .into(),
+                ..CopyBufferToImageInfo::buffer_image(staging, image)
+            })
+            .unwrap();
+        self.submit_upload(cmd_buffer_builder.build().unwrap());
+
+        Ok(())
+    }
+
+    fn submit_upload(&mut self, command_buffer: PrimaryAutoCommandBuffer) {
+        match &mut self.upload_batch {
+            Some(batch) => batch.push(command_buffer),
+            None => self.submit_now(command_buffer),
+        }
+    }
+
+    // flushed immediately so it can't be lost with a frame that fails to present
+    fn submit_now(&mut self, command_buffer: PrimaryAutoCommandBuffer) {
+        let future = self
+            .previous_frame_end
+            .take()
+            .unwrap()
+            .then_execute(self.queue.clone(), command_buffer)
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap();
+        self.previous_frame_end = Some(Box::new(future) as Box<_>);
+    }
+
+    /// Holds back the uploads of `load_texture`, `load_cubemap`, `load_sdf_font`, `create_buffer`
+    /// and `update_texture_region` from now on, to submit them all at once with `flush_uploads`
+    /// instead of one submission each, e.g. while loading the assets of a scene.
+    ///
+    /// A frame rendered while the batch is open submits the uploads held so far first, without
+    /// waiting for them, and the batch stays open.
+    pub fn begin_uploads(&mut self) {
+        self.upload_batch.get_or_insert_with(Vec::new);
+    }
+
+    /// Submits the uploads held since `begin_uploads` in a single submission, waits once for all
+    /// of them to complete and closes the batch. Does nothing without an open batch.
+    pub fn flush_uploads(&mut self) -> Result<(), EngineError> {
+        let uploads = self.upload_batch.take().unwrap_or_default();
+        if uploads.is_empty() {
+            return Ok(());
+        }
+
+        // consecutive executions on the queue are batched into a single submission
+        let mut future = self.previous_frame_end.take().unwrap();
+        for upload in uploads {
+            future = Box::new(future.then_execute(self.queue.clone(), upload).unwrap());
+        }
+        let future = future.then_signal_fence_and_flush();
+        self.previous_frame_end = Some(Box::new(sync::now(self.device.clone())) as Box<_>);
+        future
+            .and_then(|future| future.wait(None))
+            .map_err(EngineError::Flush)
+    }
+
+    /// Whether uploads are held back by `begin_uploads` until `flush_uploads`.
+    pub fn uploads_batched(&self) -> bool {
+        self.upload_batch.is_some()
+    }
+
+    /// Queues `command_buffer`, e.g. compute or upload work, to be submitted together with the
+    /// next frame instead of on its own. It must have been built for the graphics queue family.
+    ///
+    /// Queued command buffers execute in the order they were queued, all before the frame's own
+    /// command buffer, and are synchronized with it and with each other like consecutive
+    /// submissions. They are kept for the following frame if no swapchain image could be
+    /// acquired, and dropped with the frame if its submission fails.
+    pub fn submit_with_frame(&mut self, command_buffer: PrimaryAutoCommandBuffer) {
+        self.frame_submissions.push(command_buffer);
+    }
+
+    /// Hints the compositor that only `rects` of the next presented frame changed since the
+    /// previous one, so it may skip updating the rest of the screen and save power. The frame is
+    /// still rendered and presented whole, and the hint may be ignored, so the rest of it must
+    /// look the same as before.
+    ///
+    /// Rectangles are clipped to the swapchain image. Without `VK_KHR_incremental_present`, see
+    /// `supports_present_damage`, or without any rectangle left, the frame is presented as usual.
+    pub fn present_with_damage(&mut self, rects: &[Rect]) {
+        self.present_damage = rects.to_vec();
+    }
+
+    pub fn supports_present_damage(&self) -> bool {
+        self.device.enabled_extensions().khr_incremental_present
+    }
+
+    // the damage of the frame being presented, empty to present all of it
+    fn take_present_regions(&mut self) -> Vec<RectangleLayer> {
+        let image_extent = self.swapchain.image_extent();
+        let damage = std::mem::take(&mut self.present_damage);
+        if !self.supports_present_damage() {
+            return Vec::new();
+        }
+        damage
+            .into_iter()
+            .filter_map(|rect| rect.clip(image_extent))
+            .map(|rect| RectangleLayer {
+                offset: rect.offset,
+                extent: rect.extent,
+                layer: 0,
+            })
+            .collect()
+    }
+
+    /// Saves the next rendered frame as a PNG file at `path`, converted to 8-bit sRGB. A frame
+    /// that fails to be submitted leaves the screenshot to the following one.
+    pub fn screenshot(&mut self, path: impl AsRef<Path>) {
+        self.pending_screenshot = Some(path.as_ref().to_path_buf());
+    }
+
+    /// Saves presented frames as `frame_00000.png`, `frame_00001.png`... in `directory`, which is
+    /// created if needed, at most `fps` per second of the frame clock, until `stop_recording`.
+    /// With a fixed frame time of `1 / fps`, every frame is captured and the clip plays at the
+    /// speed of the animation.
+    ///
+    /// Every captured frame costs a copy of the swapchain image to host memory, and a conversion
+    /// and PNG encoding on a writer thread, so expect lower frame rates while recording, at large
+    /// window sizes especially. Frames rendered slower than `fps` are all captured, so the clip
+    /// then plays faster than it rendered.
+    pub fn start_recording(
+        &mut self,
+        directory: impl AsRef<Path>,
+        fps: f32,
+    ) -> Result<(), ScreenshotError> {
+        if !self.swapchain.image_usage().transfer_src {
+            return Err(ScreenshotError::SwapchainNotCopyable);
+        }
+        if self.recording.is_some() {
+            self.stop_recording()?;
+        }
+        self.recording = Some(Recording::new(directory.as_ref(), fps, self.clock.time())?);
+        Ok(())
+    }
+
+    /// Waits for the captured frames to be rendered and written, and returns how many were
+    /// written, 0 when not recording. Writing stops at the first error, which is returned.
+    pub fn stop_recording(&mut self) -> Result<u32, ScreenshotError> {
+        let Some(recording) = self.recording.take() else {
+            return Ok(0);
+        };
+
+        // dropping the signaled future releases the copies to the host
+        let future = self
+            .previous_frame_end
+            .take()
+            .unwrap()
+            .then_signal_fence_and_flush();
+        self.previous_frame_end = Some(Box::new(sync::now(self.device.clone())) as Box<_>);
+        if let Ok(future) = future {
+            future.wait(None).ok();
+        }
+        recording.finish()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    // copied at the end of the frame's command buffer, once presentable
+    fn copy_swapchain_image(
+        &self,
+        cmd_buffer_builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        image_index: u32,
+    ) -> Result<Arc<CpuAccessibleBuffer<[u8]>>, EngineError> {
+        let [width, height] = self.swapchain.image_extent();
+        let block_size = self.swapchain.image_format().block_size().unwrap();
+        let size = width as u64 * height as u64 * block_size;
+        let buffer = CpuAccessibleBuffer::from_iter(
+            &self.memory_allocator,
+            BufferUsage {
+                transfer_dst: true,
+                ..BufferUsage::empty()
+            },
+            true,
+            (0..size as usize).map(|_| 0u8),
+        )
+        .map_err(EngineError::allocation(size))?;
+
+        cmd_buffer_builder
+            .copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(
+                self.image_views[image_index as usize].image().clone(),
+                buffer.clone(),
+            ))
+            .unwrap();
+        Ok(buffer)
+    }
+
+    fn frame_layout(&self) -> FrameLayout {
+        FrameLayout {
+            extent: self.swapchain.image_extent(),
+            format: self.swapchain.image_format(),
+            color_space: self.swapchain.image_color_space(),
+            opaque: self.swapchain.composite_alpha() == CompositeAlpha::Opaque,
+        }
+    }
+
+    /// Captures the next rendered frame in RenderDoc, if the application runs under it.
+    #[cfg(feature = "renderdoc")]
+    pub fn trigger_capture(&mut self) {
+        self.capture.trigger();
+    }
+
+    pub fn render(&mut self, recreate_swapchain: &mut bool) {
+        match self.draw_frame(recreate_swapchain) {
+            Ok(()) | Err(EngineError::SwapchainOutOfDate) => {}
+            Err(e) => println!("{}", e),
+        }
+
+        #[cfg(feature = "renderdoc")]
+        self.capture.end_frame();
+    }
+
+    /// Outcome of the last `render` or `render_once` call. Updated by every frame, including the
+    /// ones that present nothing.
+    pub fn last_frame_status(&self) -> FrameStatus {
+        self.last_frame_status
+    }
+
+    /// Renders and presents exactly one frame, and blocks until the GPU has finished it, so that
+    /// readbacks afterwards see the complete frame. Doesn't need the event loop to run.
+    pub fn render_once(&mut self) -> Result<(), EngineError> {
+        let mut recreate_swapchain = false;
+        let mut result = self.draw_frame(&mut recreate_swapchain);
+        if let Err(EngineError::SwapchainOutOfDate) = result {
+            // nothing was presented, try again with a recreated swapchain
+            result = self.draw_frame(&mut recreate_swapchain);
+        }
+
+        #[cfg(feature = "renderdoc")]
+        self.capture.end_frame();
+
+        if recreate_swapchain {
+            result = result.and(self.recreate_swapchain(&mut recreate_swapchain));
+        }
+        result?;
+
+        let future = self
+            .previous_frame_end
+            .take()
+            .unwrap()
+            .then_signal_fence_and_flush();
+        self.previous_frame_end = Some(Box::new(sync::now(self.device.clone())) as Box<_>);
+        future
+            .and_then(|future| future.wait(None))
+            .map_err(EngineError::Flush)
+    }
+
+    fn draw_frame(&mut self, recreate_swapchain: &mut bool) -> Result<(), EngineError> {
+        self.previous_frame_end.as_mut().unwrap().cleanup_finished();
+        if let Some(recording) = &mut self.recording {
+            recording.poll();
+        }
+
+        // recreating here rather than on every resize event keeps it to once per frame while the
+        // window is dragged
+        let recreated =
+            *recreate_swapchain || self.pending_swapchain.is_some() || !self.has_image_targets();
+        if recreated {
+            if let Err(e) = self.recreate_swapchain(recreate_swapchain) {
+                self.last_frame_status =
+                    FrameStatus::Skipped(SkipReason::SwapchainRecreationFailed);
+                return Err(e);
+            }
+            if *recreate_swapchain || !self.has_image_targets() {
+                *recreate_swapchain = true;
+                self.last_frame_status = FrameStatus::Skipped(SkipReason::SwapchainUnavailable);
+                return Err(EngineError::SwapchainOutOfDate);
+            }
+        }
+
+        let present_latencies = self.present_pacer.wait(&self.swapchain);
+        let pacing = self.present_pacer.max_latency().is_some();
+        if let Some(timeline) = &mut self.frame_timeline {
+            for latency in present_latencies {
+                timeline.presented(latency);
+            }
+            timeline.begin_acquire();
+        }
+        let (image_index, suboptimal, acquire_future) =
+            match swapchain::acquire_next_image(self.swapchain.clone(), None) {
+                Ok(r) => r,
+                Err(AcquireError::OutOfDate) => {
+                    if let Some(timeline) = &mut self.frame_timeline {
+                        timeline.cancel();
+                    }
+                    *recreate_swapchain = true;
+                    self.last_frame_status = FrameStatus::Skipped(SkipReason::AcquireOutOfDate);
+                    return Err(EngineError::SwapchainOutOfDate);
+                }
+                Err(e) => {
+                    if let Some(timeline) = &mut self.frame_timeline {
+                        timeline.cancel();
+                    }
+                    self.last_frame_status = FrameStatus::Skipped(SkipReason::AcquireFailed);
+                    return Err(EngineError::Acquire(e));
+                }
+            };
+        if let Some(timeline) = &mut self.frame_timeline {
+            timeline.acquired(!pacing);
+        }
+
+        if suboptimal {
+            *recreate_swapchain = true;
+        }
+        let presented_status = match (suboptimal, recreated) {
+            (true, _) => FrameStatus::Suboptimal,
+            (false, true) => FrameStatus::Recreated,
+            (false, false) => FrameStatus::Ok,
+        };
+        self.frame_count += 1;
+        self.clock.tick();
+        self.image_index = Some(image_index);
+
+        #[cfg(feature = "renderdoc")]
+        self.capture.begin_frame();
+
+        let clear_value =
+            Some(ClearValue::Float(self.clear_color)).filter(|_| self.load_op == LoadOp::Clear);
+
+        let mut cmd_buffer_builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        let (frame_index, frames_in_flight) = (self.current_frame_index(), self.frames_in_flight());
+        if matches!(&self.gpu_timer, Some(gpu_timer) if gpu_timer.slots() != frames_in_flight) {
+            // the swapchain image count changed, the timer can't be resized in place
+            self.gpu_timer = match GpuTimer::new(
+                &self.device,
+                &self.memory_allocator,
+                self.queue.queue_family_index(),
+                frames_in_flight,
+            ) {
+                Ok(gpu_timer) => Some(gpu_timer),
+                Err(e) => {
+                    println!("Disabling GPU timing: {}", e);
+                    None
+                }
+            };
+        }
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.begin(&mut cmd_buffer_builder, frame_index);
+        }
+
+        let frame = self.frame_uniforms.next(
+            &self.descriptor_set_allocator,
+            FrameUniforms::new(
+                self.render_extent(),
+                self.elapsed_seconds(),
+                self.debug_mode,
+                self.reflectivity,
+            ),
+            &self.point_lights,
+            &self.environment,
+        );
+        for &i in &self.pass_order {
+            self.passes[i].record(&mut cmd_buffer_builder, &frame);
+        }
+        if let Some(gpu_timer) = &self.gpu_timer {
+            gpu_timer.write(&mut cmd_buffer_builder, frame_index, gpu_timer::PASSES_END);
+        }
+
+        let secondaries = std::mem::take(&mut self.secondaries);
+        let contents = if secondaries.is_empty() {
+            SubpassContents::Inline
+        } else {
+            SubpassContents::SecondaryCommandBuffers
+        };
+
+        match self.render_path {
+            RenderPath::RenderPass => {
+                cmd_buffer_builder
+                    .begin_render_pass(
+                        RenderPassBeginInfo {
+                            clear_values: self.clear_values(clear_value),
+                            ..RenderPassBeginInfo::framebuffer(
+                                self.framebuffers[image_index as usize].clone(),
+                            )
+                        },
+                        contents,
+                    )
+                    .unwrap();
+                if !secondaries.is_empty() {
+                    cmd_buffer_builder
+                        .execute_commands_from_vec(secondaries)
+                        .unwrap();
+                }
+                cmd_buffer_builder.end_render_pass().unwrap();
+            }
+            RenderPath::DynamicRendering => {
+                let target_view: Arc<dyn ImageViewAbstract> = match &self.offscreen_view {
+                    Some(offscreen_view) => offscreen_view.clone(),
+                    None => self.image_views[image_index as usize].clone(),
+                };
+                let color_attachment = match &self.msaa_view {
+                    Some(msaa_view) => RenderingAttachmentInfo {
+                        store_op: if self.load_op == LoadOp::Load {
+                            StoreOp::Store
+                        } else {
+                            self.store_ops.multisampled
+                        },
+                        resolve_info: Some(RenderingAttachmentResolveInfo::image_view(target_view)),
+                        ..RenderingAttachmentInfo::image_view(msaa_view.clone())
+                    },
+                    None => RenderingAttachmentInfo {
+                        store_op: StoreOp::Store,
+                        ..RenderingAttachmentInfo::image_view(target_view)
+                    },
+                };
+                let color_targets = self
+                    .color_targets
+                    .iter()
+                    .zip(&self.store_ops.color_targets)
+                    .zip(&self.color_target_clears)
+                    .map(|((view, &store_op), &clear_value)| {
+                        Some(RenderingAttachmentInfo {
+                            load_op: LoadOp::Clear,
+                            store_op,
+                            clear_value: Some(clear_value),
+                            ..RenderingAttachmentInfo::image_view(view.clone())
+                        })
+                    });
+                let depth_attachment = self.depth_view.as_ref().map(|view| match self.depth_mode {
+                    DepthMode::ReadOnly => RenderingAttachmentInfo {
+                        image_layout: ImageLayout::DepthStencilReadOnlyOptimal,
+                        load_op: LoadOp::Load,
+                        store_op: StoreOp::Store,
+                        ..RenderingAttachmentInfo::image_view(view.clone())
+                    },
+                    _ => RenderingAttachmentInfo {
+                        load_op: LoadOp::Clear,
+                        store_op: self.store_ops.depth,
+                        clear_value: Some(self.depth_clear_value()),
+                        ..RenderingAttachmentInfo::image_view(view.clone())
+                    },
+                });
+                cmd_buffer_builder
+                    .begin_rendering(RenderingInfo {
+                        depth_attachment,
+                        color_attachments: [Some(RenderingAttachmentInfo {
+                            load_op: self.load_op,
+                            clear_value,
+                            ..color_attachment
+                        })]
+                        .into_iter()
+                        .chain(color_targets)
+                        .collect(),
+                        contents,
+                        ..Default::default()
+                    })
+                    .unwrap();
+                if !secondaries.is_empty() {
+                    cmd_buffer_builder
+                        .execute_commands_from_vec(secondaries)
+                        .unwrap();
+                }
+                cmd_buffer_builder.end_rendering().unwrap();
+            }
+        }
+
+        if let Some(deferred_lighting) = &self.deferred_lighting {
+            if !self.point_lights.is_empty() {
+                deferred_lighting.draw(
+                    &mut cmd_buffer_builder,
+                    image_index as usize,
+                    |cmd_buffer_builder, layout| {
+                        cmd_buffer_builder.bind_descriptor_sets(
+                            PipelineBindPoint::Graphics,
+                            layout.clone(),
+                            FRAME_SET,
+                            frame.descriptor_set.clone(),
+                        );
+                    },
+                );
+            }
+        }
+
+        if let Some(gpu_timer) = &self.gpu_timer {
+            gpu_timer.write(
+                &mut cmd_buffer_builder,
+                frame_index,
+                gpu_timer::MAIN_PASS_END,
+            );
+        }
+
+        // copy the offscreen image to the swapchain image, scaled to fit
+        // FXAA tone maps itself, there is at most one post-process pass
+        if let Some(post_pass) = self.fxaa.as_ref().or(self.tonemap_pass.as_ref()) {
+            let params = if self.tonemapping() {
+                TonemapParams::new(self.tonemap, self.exposure)
+            } else {
+                TonemapParams::passthrough()
+            };
+            post_pass.draw(
+                &mut cmd_buffer_builder,
+                image_index as usize,
+                |cmd_buffer_builder, layout| {
+                    cmd_buffer_builder.push_constants(layout.clone(), 0, params);
+                },
+            );
+        } else if let Some(offscreen_view) = &self.offscreen_view {
+            cmd_buffer_builder
+                .blit_image(BlitImageInfo {
+                    filter: Filter::Linear,
+                    ..BlitImageInfo::images(
+                        offscreen_view.image().clone(),
+                        self.image_views[image_index as usize].image().clone(),
+                    )
+                })
+                .unwrap();
+        }
+        if let Some(compute_present) = &self.compute_present {
+            compute_present.dispatch(
+                &mut cmd_buffer_builder,
+                image_index as usize,
+                self.image_views[image_index as usize].image(),
+            );
+        }
+
+        // Screenshot copy, recorded before present. The command buffer builder tracks the
+        // swapchain image layout: it inserts the barrier to `TransferSrcOptimal` before the copy
+        // and the one back to `PresentSrc` at the end of the command buffer.
+        let screenshot = match self.pending_screenshot.take() {
+            Some(_) if !self.swapchain.image_usage().transfer_src => {
+                println!("Failed to take screenshot: swapchain images can't be copied");
+                None
+            }
+            // retried with the next frame
+            Some(path) => match self.copy_swapchain_image(&mut cmd_buffer_builder, image_index) {
+                Ok(buffer) => Some((path, buffer)),
+                Err(e) => {
+                    println!("Failed to take screenshot: {}", e);
+                    self.pending_screenshot = Some(path);
+                    None
+                }
+            },
+            None => None,
+        };
+        let time = self.clock.time();
+        let capture = self.swapchain.image_usage().transfer_src
+            && self
+                .recording
+                .as_mut()
+                .is_some_and(|recording| recording.capture_due(time));
+        // a frame that can't be copied is missing from the recording
+        let recorded = capture
+            .then(|| self.copy_swapchain_image(&mut cmd_buffer_builder, image_index))
+            .and_then(|buffer| {
+                buffer
+                    .map_err(|e| println!("Failed to record frame: {}", e))
+                    .ok()
+            });
+
+        if let Some(gpu_timer) = &self.gpu_timer {
+            gpu_timer.end(&mut cmd_buffer_builder, frame_index);
+        }
+        let command_buffer = cmd_buffer_builder.build().unwrap();
+
+        // consecutive executions on the queue are batched into a single submission
+        let mut future: Box<dyn GpuFuture> =
+            Box::new(self.previous_frame_end.take().unwrap().join(acquire_future));
+        let uploads = self.upload_batch.as_mut().map(std::mem::take);
+        for queued in uploads
+            .into_iter()
+            .flatten()
+            .chain(self.frame_submissions.drain(..))
+        {
+            future = Box::new(future.then_execute(self.queue.clone(), queued).unwrap());
+        }
+        let future = future
+            .then_execute(self.queue.clone(), command_buffer)
+            .unwrap()
+            .then_swapchain_present(
+                self.queue.clone(),
+                SwapchainPresentInfo {
+                    present_regions: self.take_present_regions(),
+                    present_id: self.present_pacer.next_id(),
+                    ..SwapchainPresentInfo::swapchain_image_index(
+                        self.swapchain.clone(),
+                        image_index,
+                    )
+                },
+            )
+            .then_signal_fence_and_flush();
+
+        match future {
+            Ok(future) => {
+                if let Some(timeline) = &mut self.frame_timeline {
+                    timeline.submitted();
+                }
+                if let Some((path, buffer)) = screenshot {
+                    match future.wait(None) {
+                        Ok(()) => {
+                            let layout = self.frame_layout();
+                            if let Err(e) = screenshot::save_png(
+                                &path,
+                                layout.extent,
+                                layout.format,
+                                layout.color_space,
+                                layout.opaque,
+                                &buffer.read().unwrap(),
+                            ) {
+                                println!("{}", e);
+                            }
+                        }
+                        // the copy may not have completed, retried with the next frame
+                        Err(e) => {
+                            println!("Failed to take screenshot: {}", e);
+                            self.pending_screenshot = Some(path);
+                        }
+                    }
+                }
+                if let Some(buffer) = recorded {
+                    let layout = self.frame_layout();
+                    self.recording.as_mut().unwrap().push(layout, buffer);
+                }
+                self.previous_frame_end = Some(Box::new(future) as Box<_>);
+                self.last_frame_status = presented_status;
+                Ok(())
+            }
+            Err(FlushError::OutOfDate) => {
+                // nothing was copied, retried with the next frame
+                if let Some((path, _)) = screenshot {
+                    self.pending_screenshot = Some(path);
+                }
+                *recreate_swapchain = true;
+                self.present_pacer.clear();
+                if let Some(timeline) = &mut self.frame_timeline {
+                    timeline.cancel();
+                }
+                self.previous_frame_end = Some(Box::new(sync::now(self.device.clone())) as Box<_>);
+                self.last_frame_status = FrameStatus::Skipped(SkipReason::PresentOutOfDate);
+                Err(EngineError::SwapchainOutOfDate)
+            }
+            Err(e) => {
+                if let Some((path, _)) = screenshot {
+                    println!("Failed to take screenshot: {}", e);
+                    self.pending_screenshot = Some(path);
+                }
+                self.present_pacer.clear();
+                if let Some(timeline) = &mut self.frame_timeline {
+                    timeline.cancel();
+                }
+                self.previous_frame_end = Some(Box::new(sync::now(self.device.clone())) as Box<_>);
+                self.last_frame_status = FrameStatus::Skipped(SkipReason::SubmissionFailed);
+                Err(EngineError::Flush(e))
+            }
+        }
+    }
+
+    // one per render pass attachment: the main color attachment, its resolve target, the extra
+    // color targets, which are cleared to zero, and the depth attachment
+    fn clear_values(&self, clear_value: Option<ClearValue>) -> Vec<Option<ClearValue>> {
+        let mut clear_values = match self.msaa_view {
+            Some(_) => vec![clear_value, None],
+            None => vec![clear_value],
+        };
+        clear_values.extend(self.color_target_clears.iter().copied().map(Some));
+        if self.depth_view.is_some() {
+            clear_values.push(
+                Some(self.depth_clear_value()).filter(|_| self.depth_mode == DepthMode::Enabled),
+            );
+        }
+        debug_assert_eq!(clear_values.len(), self.render_pass.attachments().len());
+        clear_values
+    }
+
+    // the far plane
+    fn depth_clear_value(&self) -> ClearValue {
+        ClearValue::Depth(self.far_depth())
+    }
+
+    fn main_depth(&self) -> Option<(Format, DepthMode)> {
+        self.depth_format.map(|format| (format, self.depth_mode))
+    }
+
+    fn far_depth(&self) -> f32 {
+        if self.projection.reverse_z {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    /// Reads the depth of pixel `(x, y)` of the last rendered frame, normalized to `0.0..=1.0`, for
+    /// CPU-side picking or debugging. Coordinates are in `render_extent` pixels.
+    ///
+    /// Returns `None` without a depth attachment, with multisampling, when the depth isn't stored
+    /// or when out of range. Blocks until the GPU has finished the submitted frames.
+    pub fn read_depth(&mut self, x: u32, y: u32) -> Option<f32> {
+        let image = self.depth_view.as_ref()?.image().clone();
+        let [width, height] = self.render_extent();
+        if self.sample_count != SampleCount::Sample1
+            || self.store_ops.depth != StoreOp::Store
+            || x >= width
+            || y >= height
+        {
+            return None;
+        }
+
+        // the largest depth texel, D32, is 4 bytes
+        let buffer = CpuAccessibleBuffer::from_data(
+            &self.memory_allocator,
+            BufferUsage {
+                transfer_dst: true,
+                ..BufferUsage::empty()
+            },
+            true,
+            [0u8; 4],
+        )
+        .map_err(|e| println!("Failed to read depth: {}", EngineError::allocation(4)(e)))
+        .ok()?;
+
+        let mut cmd_buffer_builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        cmd_buffer_builder
+            .copy_image_to_buffer(CopyImageToBufferInfo {
+                regions: [BufferImageCopy {
+                    image_subresource: ImageSubresourceLayers {
+                        aspects: ImageAspects {
+                            depth: true,
+                            ..ImageAspects::empty()
+                        },
+                        ..image.subresource_layers()
+                    },
+                    image_offset: [x, y, 0],
+                    image_extent: [1, 1, 1],
+                    ..Default::default()
+                }]
+                .into(),
+                ..CopyImageToBufferInfo::image_buffer(image.clone(), buffer.clone())
+            })
+            .unwrap();
+
+        let future = self
+            .previous_frame_end
+            .take()
+            .unwrap()
+            .then_execute(self.queue.clone(), cmd_buffer_builder.build().unwrap())
+            .unwrap()
+            .then_signal_fence_and_flush();
+        self.previous_frame_end = Some(Box::new(sync::now(self.device.clone())) as Box<_>);
+        if let Err(e) = future.and_then(|future| future.wait(None)) {
+            println!("Failed to read depth: {}", e);
+            return None;
+        }
+
+        let texel = *buffer.read().unwrap();
+        Some(match image.format() {
+            Format::D16_UNORM | Format::D16_UNORM_S8_UINT => {
+                u16::from_ne_bytes([texel[0], texel[1]]) as f32 / u16::MAX as f32
+            }
+            // the 8 high bits are undefined
+            Format::X8_D24_UNORM_PACK32 | Format::D24_UNORM_S8_UINT => {
+                (u32::from_ne_bytes(texel) & 0x00ff_ffff) as f32 / 0x00ff_ffff as f32
+            }
+            _ => f32::from_ne_bytes(texel),
+        })
+    }
+
+    // whether there is a view, and a framebuffer if needed, for every swapchain image
+    fn has_image_targets(&self) -> bool {
+        let image_count = self.swapchain.image_count() as usize;
+        self.image_views.len() == image_count
+            && (self.render_path != RenderPath::RenderPass
+                || self.framebuffers.len() == image_count)
+    }
+
+    /// Blocks until the GPU has finished all submitted work.
+    ///
+    /// This stalls the whole pipeline: use it around setup, teardown or resource swaps, not
+    /// every frame.
+    pub fn wait_idle(&self) -> Result<(), EngineError> {
+        // the engine owns the device's only queue and isn't Sync, so nothing can be submitted
+        // while waiting
+        unsafe { self.device.wait_idle() }.map_err(EngineError::OutOfMemory)
+    }
+
+    pub fn request_redraw(&self) {
+        self.window().request_redraw();
+    }
+
+    /// The window the engine presents to.
+    pub fn window(&self) -> &Window {
+        self.surface
+            .object()
+            .unwrap()
+            .downcast_ref::<Window>()
+            .unwrap()
+    }
+
+    /// Smallest extent swapchain rebuilds use, e.g. the window's minimum size, for surfaces
+    /// that let the swapchain differ from the window size while it is resized below it. Other
+    /// surfaces always match the window. A minimized window still pauses rendering.
+    pub fn set_min_extent(&mut self, extent: [u32; 2]) {
+        self.min_extent = extent;
+    }
+
+    pub fn min_extent(&self) -> [u32; 2] {
+        self.min_extent
+    }
+
+    pub fn swapchain_info(&self) -> SwapchainInfo {
+        SwapchainInfo {
+            extent: self.swapchain.image_extent(),
+            render_extent: self.render_extent(),
+            format: self.swapchain.image_format(),
+            color_space: self.swapchain.image_color_space(),
+            image_count: self.swapchain.image_count(),
+        }
+    }
+
+    /// Calls `f` every time the swapchain has been recreated, after a resize or a change of
+    /// format, present mode or render settings, to rebuild resources depending on its size.
+    ///
+    /// It runs on the render thread in the middle of a frame, so it shouldn't block.
+    pub fn set_on_recreate(&mut self, f: impl FnMut(&SwapchainInfo) + 'static) {
+        self.on_recreate = Some(Box::new(f));
+    }
+
+    /// Recreates the swapchain for the current window size, and clears `recreate_swapchain` once
+    /// it is. While minimized it stays set, for the next frame to retry.
+    pub fn recreate_swapchain(&mut self, recreate_swapchain: &mut bool) -> Result<(), EngineError> {
+        if self.try_rebuild_swapchain(self.swapchain_create_info())? {
+            *recreate_swapchain = false;
+        }
+        Ok(())
+    }
+
+    /// Formats and color spaces the surface can present, to pick one for `set_surface_format`.
+    ///
+    /// Portability implementations may only report one or two entries.
+    pub fn surface_formats(&self) -> Vec<(Format, ColorSpace)> {
+        self.physical_device
+            .surface_formats(&self.surface, Default::default())
+            .unwrap()
+    }
+
+    /// Present modes the surface supports. `PresentMode::Fifo` is always among them.
+    pub fn surface_present_modes(&self) -> Vec<PresentMode> {
+        self.physical_device
+            .surface_present_modes(&self.surface)
+            .unwrap()
+            .collect()
+    }
+
+    /// How the surface can blend swapchain images with what is behind the window.
+    pub fn surface_composite_alphas(&self) -> Vec<CompositeAlpha> {
+        self.physical_device
+            .surface_capabilities(&self.surface, Default::default())
+            .unwrap()
+            .supported_composite_alpha
+            .iter()
+            .collect()
+    }
+
+    /// Recreates the swapchain with one of the modes returned by `surface_composite_alphas`.
+    ///
+    /// Anything but `CompositeAlpha::Opaque` needs a window created transparent to show through.
+    /// With `CompositeAlpha::PreMultiplied`, shaders and the clear color have to write colors
+    /// already multiplied by their alpha.
+    pub fn set_composite_alpha(
+        &mut self,
+        composite_alpha: CompositeAlpha,
+    ) -> Result<(), EngineError> {
+        if !self.surface_composite_alphas().contains(&composite_alpha) {
+            return Err(EngineError::UnsupportedCompositeAlpha(composite_alpha));
+        }
+
+        self.rebuild_swapchain(SwapchainCreateInfo {
+            composite_alpha,
+            ..self.swapchain_create_info()
+        });
+        Ok(())
+    }
+
+    pub fn composite_alpha(&self) -> CompositeAlpha {
+        self.swapchain.composite_alpha()
+    }
+
+    pub fn surface_format(&self) -> (Format, ColorSpace) {
+        (
+            self.swapchain.image_format(),
+            self.swapchain.image_color_space(),
+        )
+    }
+
+    pub fn color_space(&self) -> ColorSpace {
+        self.swapchain.image_color_space()
+    }
+
+    pub fn present_mode(&self) -> PresentMode {
+        self.swapchain.present_mode()
+    }
+
+    /// Recreates the swapchain with one of the pairs returned by `surface_formats`. While the
+    /// window is minimized, it is recreated once it can be.
+    pub fn set_surface_format(
+        &mut self,
+        format: Format,
+        color_space: ColorSpace,
+    ) -> Result<(), EngineError> {
+        if !self.surface_formats().contains(&(format, color_space)) {
+            return Err(EngineError::UnsupportedSurfaceFormat(format, color_space));
+        }
+
+        self.rebuild_swapchain(SwapchainCreateInfo {
+            image_format: Some(format),
+            image_color_space: color_space,
+            ..self.swapchain_create_info()
+        });
+        Ok(())
+    }
+
+    /// Describes the colors and luminance range the content is mastered for to an HDR display,
+    /// e.g. `HdrMetadata::REC_2020` with an `Hdr10St2084` color space, so it can tone map the
+    /// frames to what it can show. Kept across swapchain recreations.
+    ///
+    /// Does nothing but print a warning without `VK_EXT_hdr_metadata`, see
+    /// `supports_hdr_metadata`.
+    pub fn set_hdr_metadata(&mut self, metadata: HdrMetadata) {
+        if !self.supports_hdr_metadata() {
+            println!("HDR metadata not supported by the device, ignored");
+            return;
+        }
+        hdr::set(&self.device, &self.swapchain, &metadata);
+        self.hdr_metadata = Some(metadata);
+    }
+
+    pub fn hdr_metadata(&self) -> Option<HdrMetadata> {
+        self.hdr_metadata
+    }
+
+    pub fn supports_hdr_metadata(&self) -> bool {
+        self.device.enabled_extensions().ext_hdr_metadata
+    }
+
+    /// Switches the swapchain to `color_space`, e.g. `DisplayP3NonLinear` or `Hdr10St2084`.
+    ///
+    /// The current format is kept if the surface supports it in that color space, otherwise the
+    /// first format listed for it is used.
+    pub fn set_color_space(&mut self, color_space: ColorSpace) -> Result<(), EngineError> {
+        let formats: Vec<Format> = self
+            .surface_formats()
+            .into_iter()
+            .filter(|&(_, c)| c == color_space)
+            .map(|(format, _)| format)
+            .collect();
+        let format = if formats.contains(&self.swapchain.image_format()) {
+            self.swapchain.image_format()
+        } else {
+            *formats
+                .first()
+                .ok_or(EngineError::UnsupportedColorSpace(color_space))?
+        };
+
+        self.set_surface_format(format, color_space)
+    }
+
+    /// Recreates the swapchain with one of the modes returned by `surface_present_modes`.
+    ///
+    /// Returns the mode in use afterwards, which falls back to `PresentMode::Fifo` if there isn't
+    /// enough memory for the requested one. `frames_in_flight` may drop for the same reason.
+    /// While the window is minimized, the current mode is returned and the requested one is used
+    /// once the swapchain can be recreated.
+    pub fn set_present_mode(
+        &mut self,
+        present_mode: PresentMode,
+    ) -> Result<PresentMode, EngineError> {
+        if !self.surface_present_modes().contains(&present_mode) {
+            return Err(EngineError::UnsupportedPresentMode(present_mode));
+        }
+
+        self.rebuild_swapchain(SwapchainCreateInfo {
+            present_mode,
+            ..self.swapchain_create_info()
+        });
+        Ok(self.swapchain.present_mode())
+    }
+
+    /// Recreates the swapchain with at least `count` images, clamped to what the surface
+    /// supports, e.g. 2 for double and 3 for triple buffering.
+    ///
+    /// Returns the number of images afterwards, which the driver may make higher and which drops
+    /// if there isn't enough memory. Dynamic vertex buffers created for fewer frames in flight
+    /// stay valid, they reallocate buffers the GPU still holds.
+    pub fn set_image_count(&mut self, count: u32) -> u32 {
+        let caps = self
+            .physical_device
+            .surface_capabilities(&self.surface, Default::default())
+            .unwrap();
+        let count = count
+            .max(caps.min_image_count)
+            .min(caps.max_image_count.unwrap_or(u32::MAX));
+
+        // kept by later recreations, which start from the current create info
+        self.rebuild_swapchain(SwapchainCreateInfo {
+            min_image_count: count,
+            ..self.swapchain_create_info()
+        });
+        self.image_count()
+    }
+
+    pub fn image_count(&self) -> u32 {
+        self.swapchain.image_count()
+    }
+
+    /// Bounds how many frames can be on their way to the display at once, trading throughput
+    /// for input latency: with 1, a frame only starts once the previous one is displayed. `None`,
+    /// the default, lets the CPU run as far ahead as the swapchain images allow.
+    ///
+    /// With present wait, see `supports_present_wait`, each frame waits for the present from
+    /// `frames` frames ago. Otherwise the swapchain is recreated with `frames + 1` images, as
+    /// with `set_image_count`, which `None` doesn't undo.
+    pub fn set_max_frame_latency(&mut self, frames: Option<u32>) {
+        if self.supports_present_wait() {
+            self.present_pacer.set_max_latency(frames);
+        } else if let Some(frames) = frames {
+            self.set_image_count(frames.max(1) + 1);
+        }
+    }
+
+    /// The latency bound enforced with present wait, `None` without one or without support.
+    pub fn max_frame_latency(&self) -> Option<u32> {
+        self.present_pacer.max_latency()
+    }
+
+    /// Whether the device can wait for presents to reach the display, with `VK_KHR_present_wait`.
+    pub fn supports_present_wait(&self) -> bool {
+        self.device.enabled_features().present_wait
+    }
+
+    /// Time from the submission of a recent frame to its present reaching the display, measured
+    /// while present wait bounds the frame latency. `None` otherwise, or before the first one.
+    pub fn present_latency(&self) -> Option<Duration> {
+        self.present_pacer.latency()
+    }
+
+    /// What happens to the swapchain image contents when rendering begins.
+    ///
+    /// `LoadOp::Load` keeps what was last rendered to the acquired image, which is the frame from
+    /// `frames_in_flight` frames ago rather than the previous one. The swapchain is then recreated
+    /// unclipped so that the presentation engine keeps obscured pixels too.
+    ///
+    /// With multisampling, see `set_sample_count`, it loads the multisampled attachment instead,
+    /// which is then stored every frame: that one image is shared by all frames, so it holds the
+    /// previous frame, and the swapchain image is only overwritten by the resolve.
+    ///
+    /// While the window is minimized, the new load op applies from the first frame after it is
+    /// restored: frames are skipped until then.
+    pub fn set_load_op(&mut self, load_op: LoadOp) {
+        self.load_op = load_op;
+        self.render_pass = Graphicengine::get_render_pass(
+            &self.device,
+            self.main_format(),
+            load_op,
+            self.sample_count,
+            &self.color_target_formats,
+            self.main_depth(),
+            &self.store_ops,
+        );
+        self.rebuild_swapchain(self.swapchain_create_info());
+    }
+
+    pub fn load_op(&self) -> LoadOp {
+        self.load_op
+    }
+
+    /// Color the main pass target is cleared to with `LoadOp::Clear`, in the target's numeric
+    /// space: linear values that the hardware encodes when the target has an sRGB format, see
+    /// `set_clear_color_srgb`, and the stored values otherwise.
+    pub fn set_clear_color(&mut self, color: [f32; 4]) {
+        self.clear_color = color;
+    }
+
+    /// Sets the clear color from sRGB encoded components, e.g. picked in an image editor, for
+    /// targets with an sRGB format: they are decoded to linear, and encoded back on write. Alpha
+    /// is linear either way. Targets with a UNORM format store values as they are, so take
+    /// encoded colors with `set_clear_color` instead.
+    pub fn set_clear_color_srgb(&mut self, color: [f32; 4]) {
+        let [r, g, b, a] = color;
+        self.clear_color = [srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a];
+    }
+
+    pub fn clear_color(&self) -> [f32; 4] {
+        self.clear_color
+    }
+
+    /// Switches shaders reading the frame uniforms to a debug visualization from the next frame.
+    pub fn set_debug_mode(&mut self, mode: DebugMode) {
+        self.debug_mode = mode;
+    }
+
+    pub fn debug_mode(&self) -> DebugMode {
+        self.debug_mode
+    }
+
+    /// Sets the sample count of the main color attachment, which is resolved into the swapchain
+    /// image. Must be one of `supported_sample_counts`.
+    ///
+    /// Every pipeline drawing in the main pass has to use the same count as
+    /// `rasterization_samples`, so rebuild them after changing it, e.g. with `multisample_state`.
+    /// Changed while the window is minimized, the attachments are reallocated once it is
+    /// restored, and no frame is drawn in between.
+    pub fn set_sample_count(&mut self, sample_count: SampleCount) -> Result<(), EngineError> {
+        if !self.supported_sample_counts().contains(&sample_count) {
+            return Err(EngineError::UnsupportedSampleCount(sample_count));
+        }
+        if sample_count != SampleCount::Sample1 && !self.color_target_formats.is_empty() {
+            return Err(EngineError::MultisampledColorTargets);
+        }
+
+        self.sample_count = sample_count;
+        self.render_pass = Graphicengine::get_render_pass(
+            &self.device,
+            self.main_format(),
+            self.load_op,
+            sample_count,
+            &self.color_target_formats,
+            self.main_depth(),
+            &self.store_ops,
+        );
+        self.rebuild_swapchain(self.swapchain_create_info());
+        Ok(())
+    }
+
+    pub fn sample_count(&self) -> SampleCount {
+        self.sample_count
+    }
+
+    /// Runs fragment shaders for at least `fraction` of the samples of each pixel instead of
+    /// once per pixel, clamped to `0.0..=1.0`, which also antialiases texture and shading detail
+    /// inside triangles at the cost of more shading. `None`, the default, shades once per pixel.
+    ///
+    /// Only pipelines built with `multisample_state` afterwards use it, and it has no effect
+    /// with a sample count of 1. Requires the `sample_rate_shading` feature, enabled when
+    /// supported.
+    pub fn set_sample_shading(&mut self, fraction: Option<f32>) -> Result<(), EngineError> {
+        if fraction.is_some() && !self.device.enabled_features().sample_rate_shading {
+            return Err(EngineError::SampleShadingUnsupported);
+        }
+        self.sample_shading = fraction.map(|fraction| fraction.clamp(0.0, 1.0));
+        Ok(())
+    }
+
+    pub fn sample_shading(&self) -> Option<f32> {
+        self.sample_shading
+    }
+
+    /// Sets the primitive topology of `input_assembly_state`, triangle lists by default, e.g.
+    /// `TriangleStrip` for terrain or `PointList` for point clouds. Topologies with adjacency need
+    /// the `geometry_shader` feature, and triangle fans the `triangle_fans` feature on portability
+    /// implementations like MoltenVK, both enabled when supported. Patch lists aren't supported.
+    ///
+    /// Turns primitive restart off for list topologies, which can't use it.
+    pub fn set_topology(&mut self, topology: PrimitiveTopology) -> Result<(), EngineError> {
+        let features = self.device.enabled_features();
+        let supported = match topology {
+            PrimitiveTopology::LineListWithAdjacency
+            | PrimitiveTopology::LineStripWithAdjacency
+            | PrimitiveTopology::TriangleListWithAdjacency
+            | PrimitiveTopology::TriangleStripWithAdjacency => features.geometry_shader,
+            PrimitiveTopology::TriangleFan => {
+                !self.device.enabled_extensions().khr_portability_subset || features.triangle_fans
+            }
+            PrimitiveTopology::PatchList => false,
+            _ => true,
+        };
+        if !supported {
+            return Err(EngineError::UnsupportedTopology(topology));
+        }
+
+        self.topology = topology;
+        self.primitive_restart = keeps_primitive_restart(self.primitive_restart, topology);
+        Ok(())
+    }
+
+    pub fn topology(&self) -> PrimitiveTopology {
+        self.topology
+    }
+
+    /// Makes the maximum index value, e.g. `u32::MAX` for 32-bit indices, end the current strip
+    /// or fan in indexed draws with `input_assembly_state`, so several strips go in one draw.
+    /// Only strip and fan topologies support it.
+    pub fn set_primitive_restart(&mut self, enabled: bool) -> Result<(), EngineError> {
+        if enabled && !supports_primitive_restart(self.topology) {
+            return Err(EngineError::PrimitiveRestartUnsupported(self.topology));
+        }
+        self.primitive_restart = enabled;
+        Ok(())
+    }
+
+    pub fn primitive_restart(&self) -> bool {
+        self.primitive_restart
+    }
+
+    /// Input assembly state for pipelines, with the topology and primitive restart set on the
+    /// engine. Pipelines are built by their owners, so rebuild them after changing either.
+    pub fn input_assembly_state(&self) -> InputAssemblyState {
+        let state = InputAssemblyState::new().topology(self.topology);
+        if self.primitive_restart {
+            state.primitive_restart_enable()
+        } else {
+            state
+        }
+    }
+
+    /// Multisample state for pipelines drawing in the main pass, with its sample count and
+    /// sample shading.
+    pub fn multisample_state(&self) -> MultisampleState {
+        MultisampleState {
+            rasterization_samples: self.sample_count,
+            sample_shading: self.sample_shading,
+            ..Default::default()
+        }
+    }
+
+    /// Depth stencil state for pipelines drawing in the main pass: the `set_depth_compare` test,
+    /// flipped with reverse Z, writing depth unless it is `DepthMode::ReadOnly`, or no depth
+    /// test without a depth attachment. Rebuild pipelines after changing any of them.
+    pub fn depth_stencil_state(&self) -> DepthStencilState {
+        if self.depth_mode == DepthMode::Disabled {
+            return DepthStencilState::disabled();
+        }
+
+        let compare_op = if self.projection.reverse_z {
+            reversed_compare_op(self.depth_compare)
+        } else {
+            self.depth_compare
+        };
+        DepthStencilState {
+            depth: Some(DepthState {
+                enable_dynamic: false,
+                write_enable: StateMode::Fixed(self.depth_mode == DepthMode::Enabled),
+                compare_op: StateMode::Fixed(compare_op),
+            }),
+            ..DepthStencilState::disabled()
+        }
+    }
+
+    /// Whether pipelines can rasterize polygons as lines, see `wireframe_overlay_state`.
+    pub fn supports_wireframe(&self) -> bool {
+        self.device.enabled_features().fill_mode_non_solid
+    }
+
+    /// Rasterization state for a second pipeline redrawing a mesh as its wireframe over the
+    /// solid draw, e.g. for editors: polygons become their edges, pulled slightly towards the
+    /// camera so they pass a `LessOrEqual` depth test against the solid surface, `GreaterOrEqual`
+    /// with reverse Z. Draw the mesh with it after the solid draw, with the overlay color from
+    /// the fragment shader. Pipelines are built by their owners, so rebuild it after changing
+    /// reverse Z.
+    ///
+    /// `None` when the device can't rasterize lines from polygons, and the overlay should be
+    /// skipped.
+    pub fn wireframe_overlay_state(&self) -> Option<RasterizationState> {
+        if !self.supports_wireframe() {
+            println!("Polygon line mode not supported, skipping the wireframe overlay");
+            return None;
+        }
+
+        // depth values shrink towards the camera, unless reversed
+        let towards_camera = if self.projection.reverse_z { 1.0 } else { -1.0 };
+        Some(RasterizationState {
+            polygon_mode: PolygonMode::Line,
+            depth_bias: Some(DepthBiasState {
+                enable_dynamic: false,
+                bias: StateMode::Fixed(DepthBias {
+                    constant_factor: towards_camera,
+                    clamp: 0.0,
+                    slope_factor: towards_camera,
+                }),
+            }),
+            ..RasterizationState::new()
+        })
+    }
+
+    /// Whether `attachment` is kept at the end of the main pass. `StoreOp::DontCare` saves the
+    /// bandwidth of writing it to memory on tiled GPUs, for attachments nothing reads afterwards.
+    ///
+    /// The multisampled attachment is discarded by default, and stored while `LoadOp::Load` is
+    /// used since the next frame loads it. Color targets and depth are stored by default; the
+    /// contents of discarded color targets are undefined for later passes, and so is a discarded
+    /// depth, which `read_depth` then returns `None` for. Color target store ops are reset by
+    /// `set_color_targets`.
+    pub fn set_store_op(
+        &mut self,
+        attachment: MainAttachment,
+        store_op: StoreOp,
+    ) -> Result<(), EngineError> {
+        match attachment {
+            MainAttachment::Multisampled => {
+                if store_op == StoreOp::DontCare && self.load_op == LoadOp::Load {
+                    return Err(EngineError::StoreOpRequired(attachment));
+                }
+                self.store_ops.multisampled = store_op;
+            }
+            MainAttachment::ColorTarget(index) => {
+                *self
+                    .store_ops
+                    .color_targets
+                    .get_mut(index)
+                    .ok_or(EngineError::NoColorTarget(index))? = store_op;
+            }
+            MainAttachment::Depth => self.store_ops.depth = store_op,
+        }
+
+        self.render_pass = Graphicengine::get_render_pass(
+            &self.device,
+            self.main_format(),
+            self.load_op,
+            self.sample_count,
+            &self.color_target_formats,
+            self.main_depth(),
+            &self.store_ops,
+        );
+        self.rebuild_swapchain(self.swapchain_create_info());
+        Ok(())
+    }
+
+    pub fn store_op(&self, attachment: MainAttachment) -> Option<StoreOp> {
+        match attachment {
+            MainAttachment::Multisampled if self.load_op == LoadOp::Load => Some(StoreOp::Store),
+            MainAttachment::Multisampled => Some(self.store_ops.multisampled),
+            MainAttachment::ColorTarget(index) => self.store_ops.color_targets.get(index).copied(),
+            MainAttachment::Depth => Some(self.store_ops.depth),
+        }
+    }
+
+    /// Adds color attachments in `formats` to the main pass after the swapchain one, written by
+    /// fragment shader outputs at `layout(location = 1)` onwards. They are cleared every frame, to
+    /// zero unless set otherwise with `set_color_target_clear`, sized to `render_extent`, and can
+    /// be sampled by later passes through `color_targets`.
+    ///
+    /// Pipelines drawing in the main pass need one `ColorBlendState` attachment per target,
+    /// including the swapchain one. Requires a sample count of 1.
+    pub fn set_color_targets(&mut self, formats: &[Format]) -> Result<(), EngineError> {
+        if self.deferred && formats != GBUFFER_FORMATS {
+            return Err(EngineError::DeferredColorTargets);
+        }
+        if !formats.is_empty() && self.sample_count != SampleCount::Sample1 {
+            return Err(EngineError::MultisampledColorTargets);
+        }
+        let max_color_attachments = self.physical_device.properties().max_color_attachments;
+        if formats.len() as u32 >= max_color_attachments {
+            return Err(EngineError::TooManyColorTargets(max_color_attachments));
+        }
+        for &format in formats {
+            let format_features = self
+                .physical_device
+                .format_properties(format)
+                .unwrap()
+                .optimal_tiling_features;
+            if !format_features.color_attachment || !format_features.sampled_image {
+                return Err(EngineError::UnsupportedColorTargetFormat(format));
+            }
+        }
+
+        self.color_target_formats = formats.to_vec();
+        self.color_target_clears = formats.iter().map(|&f| zero_clear_value(f)).collect();
+        self.store_ops.color_targets = vec![StoreOp::Store; formats.len()];
+        self.render_pass = Graphicengine::get_render_pass(
+            &self.device,
+            self.main_format(),
+            self.load_op,
+            self.sample_count,
+            &self.color_target_formats,
+            self.main_depth(),
+            &self.store_ops,
+        );
+        self.rebuild_swapchain(self.swapchain_create_info());
+        Ok(())
+    }
+
+    /// Sets the value the color target at `index` is cleared to every frame, e.g. a flat normal
+    /// for a normal buffer. It must match the numeric type of the target format: `Float` for
+    /// normalized and float formats, `Uint` or `Int` for integer ones. Reset to zero by
+    /// `set_color_targets`.
+    ///
+    /// The swapchain attachment is cleared to `set_clear_color` and depth to the far plane.
+    pub fn set_color_target_clear(
+        &mut self,
+        index: usize,
+        clear_value: ClearValue,
+    ) -> Result<(), EngineError> {
+        let &format = self
+            .color_target_formats
+            .get(index)
+            .ok_or(EngineError::NoColorTarget(index))?;
+        if std::mem::discriminant(&clear_value) != std::mem::discriminant(&zero_clear_value(format))
+        {
+            return Err(EngineError::ClearValueMismatch(format, clear_value));
+        }
+        self.color_target_clears[index] = clear_value;
+        Ok(())
+    }
+
+    pub fn color_target_clear(&self, index: usize) -> Option<ClearValue> {
+        self.color_target_clears.get(index).copied()
+    }
+
+    /// Adds a depth attachment in `format` to the main pass, or removes it with `None`, see
+    /// `set_depth_mode`. Adding one to a pass without depth enables it.
+    ///
+    /// It is cleared to the far plane every frame: 1.0, or 0.0 with reverse Z. Pipelines drawing
+    /// in the main pass then need a depth stencil state. While the window is minimized, the
+    /// attachment is only allocated once it is restored.
+    pub fn set_depth_format(&mut self, format: Option<Format>) -> Result<(), EngineError> {
+        if let Some(format) = format {
+            if !format.aspects().depth {
+                return Err(EngineError::NotDepthFormat(format));
+            }
+            if !self.supports_depth_attachment(format) {
+                return Err(EngineError::UnsupportedDepthFormat(format));
+            }
+        }
+
+        self.depth_format = format;
+        self.depth_mode = match (format, self.depth_mode) {
+            (None, _) => DepthMode::Disabled,
+            (Some(_), DepthMode::Disabled) => DepthMode::Enabled,
+            (Some(_), mode) => mode,
+        };
+        self.render_pass = Graphicengine::get_render_pass(
+            &self.device,
+            self.main_format(),
+            self.load_op,
+            self.sample_count,
+            &self.color_target_formats,
+            self.main_depth(),
+            &self.store_ops,
+        );
+        self.rebuild_swapchain(self.swapchain_create_info());
+        Ok(())
+    }
+
+    /// Adds a depth attachment in the first format of `preference` the device supports, see
+    /// `set_depth_format`, and returns it. `DEPTH_FORMAT_PREFERENCE` is a portable default.
+    pub fn set_depth_format_from(&mut self, preference: &[Format]) -> Result<Format, EngineError> {
+        let format = preference
+            .iter()
+            .copied()
+            .find(|&format| format.aspects().depth && self.supports_depth_attachment(format))
+            .ok_or_else(|| EngineError::NoSupportedDepthFormat(preference.to_vec()))?;
+        self.set_depth_format(Some(format))?;
+        Ok(format)
+    }
+
+    pub fn depth_format(&self) -> Option<Format> {
+        self.depth_format
+    }
+
+    /// Adds, removes or switches the main pass depth attachment. `Disabled` is the default and
+    /// suits 2D rendering. Enabling depth without a depth format picks the first supported one
+    /// of `DEPTH_FORMAT_PREFERENCE`, otherwise the current format is kept.
+    ///
+    /// `ReadOnly` depth is only meaningful after `Enabled` frames stored it: switching modes keeps
+    /// the depth image, but it is undefined after the depth attachment is recreated, e.g. on
+    /// resize, until depth is enabled again. Pipelines must be built with depth writes off, and
+    /// `clear_region` can't clear it.
+    pub fn set_depth_mode(&mut self, mode: DepthMode) -> Result<(), EngineError> {
+        if mode == DepthMode::Disabled {
+            return self.set_depth_format(None);
+        }
+        if self.depth_format.is_none() {
+            self.set_depth_format_from(&DEPTH_FORMAT_PREFERENCE)?;
+        }
+        if self.depth_mode != mode {
+            self.depth_mode = mode;
+            self.render_pass = Graphicengine::get_render_pass(
+                &self.device,
+                self.main_format(),
+                self.load_op,
+                self.sample_count,
+                &self.color_target_formats,
+                self.main_depth(),
+                &self.store_ops,
+            );
+            // only the depth load and store ops changed, the depth image is kept so `ReadOnly`
+            // reads what `Enabled` frames stored. A pending rebuild, e.g. of a depth format set
+            // while minimized, reallocates it anyway.
+            if self.depth_view.is_some() && self.pending_swapchain.is_none() {
+                if self.render_path == RenderPath::RenderPass {
+                    self.framebuffers = Graphicengine::create_framebuffers(
+                        &self.render_pass,
+                        &self.image_views,
+                        self.offscreen_view.as_ref(),
+                        self.msaa_view.as_ref(),
+                        &self.color_targets,
+                        self.depth_view.as_ref(),
+                    );
+                }
+            } else {
+                self.rebuild_swapchain(self.swapchain_create_info());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn depth_mode(&self) -> DepthMode {
+        self.depth_mode
+    }
+
+    fn supports_depth_attachment(&self, format: Format) -> bool {
+        self.physical_device
+            .format_properties(format)
+            .unwrap()
+            .optimal_tiling_features
+            .depth_stencil_attachment
+    }
+
+    /// Creates an `extent` sized color image in `format` to render into with `render_to_texture`
+    /// and sample with `render_texture_view`, with a depth attachment in `depth_format` when set.
+    pub fn create_render_texture(
+        &mut self,
+        extent: [u32; 2],
+        format: Format,
+        depth_format: Option<Format>,
+    ) -> Result<RenderTextureHandle, EngineError> {
+        let format_features = self
+            .physical_device
+            .format_properties(format)
+            .unwrap()
+            .optimal_tiling_features;
+        if !format_features.color_attachment || !format_features.sampled_image {
+            return Err(EngineError::UnsupportedColorTargetFormat(format));
+        }
+        if let Some(depth_format) = depth_format {
+            if !depth_format.aspects().depth {
+                return Err(EngineError::NotDepthFormat(depth_format));
+            }
+            if !self.supports_depth_attachment(depth_format) {
+                return Err(EngineError::UnsupportedDepthFormat(depth_format));
+            }
+        }
+
+        self.render_textures.push(RenderTexture::new(
+            &self.device,
+            &self.memory_allocator,
+            extent,
+            format,
+            depth_format,
+        )?);
+        Ok(RenderTextureHandle(self.render_textures.len() - 1))
+    }
+
+    /// The subpass to build pipelines drawing into the render texture with.
+    pub fn render_texture_subpass(&self, handle: RenderTextureHandle) -> Subpass {
+        self.render_textures[handle.0].subpass()
+    }
+
+    /// The color image of the render texture, to sample in the main pass or another render
+    /// texture. It holds what was last rendered into it.
+    pub fn render_texture_view(
+        &self,
+        handle: RenderTextureHandle,
+    ) -> Arc<ImageView<AttachmentImage>> {
+        self.render_textures[handle.0].color_view().clone()
+    }
+
+    /// Renders into the render texture before the next frame, see `submit_with_frame`: it is
+    /// cleared to `clear_color` and the depth to the far plane, then `record` draws with the
+    /// viewport covering it, which is already set.
+    pub fn render_to_texture(
+        &mut self,
+        handle: RenderTextureHandle,
+        clear_color: [f32; 4],
+        record: impl FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, &Viewport),
+    ) {
+        let mut cmd_buffer_builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        self.render_textures[handle.0].record(
+            &mut cmd_buffer_builder,
+            vec![Some(clear_color.into()), Some(self.depth_clear_value())],
+            record,
+        );
+        self.submit_with_frame(cmd_buffer_builder.build().unwrap());
+    }
+
+    /// The subpass to build pipelines drawing with `render_into` over views of `formats`, in the
+    /// same order.
+    pub fn overlay_subpass(&mut self, formats: &[Format]) -> Subpass {
+        Subpass::from(self.overlay_passes.get(&self.device, formats), 0).unwrap()
+    }
+
+    /// Draws over the contents of `views`, e.g. images a compositor rendered into, bound as color
+    /// attachments in order: nothing is cleared, and `record` draws with the viewport covering
+    /// them, which is already set, with pipelines built for `overlay_subpass`. There is no depth
+    /// attachment. It is submitted right away and orders before the next frame; `wait_idle`
+    /// before another system reads the images without synchronizing with the engine.
+    ///
+    /// The views must be 2D, of a single mip level and layer of images created on the engine's
+    /// device with the `color_attachment` usage, in a format supporting it, and all of the same
+    /// extent. The images are tracked by vulkano, so their contents must be in the layout their
+    /// `ImageAccess` reports as initial, e.g. `ColorAttachmentOptimal` for an `AttachmentImage`:
+    /// they are transitioned from it to render and back, keeping the contents.
+    pub fn render_into(
+        &mut self,
+        views: &[Arc<dyn ImageViewAbstract>],
+        record: impl FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, &Viewport),
+    ) -> Result<(), EngineError> {
+        let mut extent = None;
+        for (index, view) in views.iter().enumerate() {
+            let range = view.subresource_range();
+            let supported = view.device() == &self.device
+                && view.usage().color_attachment
+                && view.view_type() == ImageViewType::Dim2d
+                && range.mip_levels.len() == 1
+                && range.array_layers.len() == 1
+                && view.format().is_some_and(|format| {
+                    self.physical_device
+                        .format_properties(format)
+                        .unwrap()
+                        .optimal_tiling_features
+                        .color_attachment
+                });
+            if !supported {
+                return Err(EngineError::UnsupportedOverlayTarget(index));
+            }
+
+            let view_extent = view
+                .image()
+                .dimensions()
+                .mip_level_dimensions(range.mip_levels.start)
+                .unwrap()
+                .width_height();
+            let expected = *extent.get_or_insert(view_extent);
+            if view_extent != expected {
+                return Err(EngineError::OverlayTargetExtent {
+                    index,
+                    expected,
+                    actual: view_extent,
+                });
+            }
+        }
+        if views.is_empty() {
+            return Ok(());
+        }
+
+        let formats: Vec<_> = views.iter().map(|view| view.format().unwrap()).collect();
+        let render_pass = self.overlay_passes.get(&self.device, &formats);
+        let mut cmd_buffer_builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        overlay::record(&mut cmd_buffer_builder, render_pass, views, record);
+        self.submit_now(cmd_buffer_builder.build().unwrap());
+        Ok(())
+    }
+
+    /// The extra color targets of the main pass, in the order given to `set_color_targets`.
+    pub fn color_targets(&self) -> &[Arc<ImageView<AttachmentImage>>] {
+        &self.color_targets
+    }
+
+    /// Renders at `scale` times the swapchain resolution, clamped to `0.25..=2.0`, and blits the
+    /// result to the swapchain image with linear filtering. `1.0` renders to the swapchain
+    /// directly.
+    ///
+    /// Pipelines drawing in the main pass should take their viewport from `render_extent`, which
+    /// only changes once the swapchain is recreated: while the window is minimized, on restore.
+    pub fn set_render_scale(&mut self, scale: f32) -> Result<(), EngineError> {
+        let scale = if scale.is_nan() {
+            1.0
+        } else {
+            scale.clamp(MIN_RENDER_SCALE, MAX_RENDER_SCALE)
+        };
+        if scale != 1.0 && !self.supports_render_scale() {
+            return Err(EngineError::RenderScaleUnsupported);
+        }
+
+        self.render_scale = scale;
+        // while minimized, the next frames retry it
+        if !self.rebuild_swapchain(self.swapchain_create_info()) {
+            println!("Swapchain can't be recreated yet, the render scale applies once it is");
+        }
+        Ok(())
+    }
+
+    pub fn render_scale(&self) -> f32 {
+        self.render_scale
+    }
+
+    /// Enables the FXAA post-process, which smooths edges of the main pass output while drawing
+    /// it to the swapchain image.
+    ///
+    /// It is much cheaper than MSAA in memory and bandwidth and also smooths shading and
+    /// alpha-tested edges, but slightly blurs textures and text and can't resolve geometry smaller
+    /// than a pixel. The two can be combined.
+    pub fn set_fxaa(&mut self, enabled: bool) {
+        self.fxaa = enabled.then(|| {
+            fxaa::pass(
+                &self.device,
+                self.swapchain.image_format(),
+                &self.pipeline_cache,
+            )
+        });
+        self.rebuild_swapchain(self.swapchain_create_info());
+    }
+
+    pub fn fxaa(&self) -> bool {
+        self.fxaa.is_some()
+    }
+
+    /// Tone maps the main pass output with `tonemap` while drawing it to the swapchain image,
+    /// for floating point main targets, see `set_main_target_format`, whose lighting goes above
+    /// 1.0. Other targets are already in the display range and pass through unchanged.
+    ///
+    /// It adds a fullscreen pass in place of the blit to the swapchain image, or is part of the
+    /// FXAA pass when enabled. The result is encoded to sRGB, by the swapchain when its format is
+    /// sRGB and by the pass otherwise.
+    pub fn set_tonemap(&mut self, tonemap: Tonemap) {
+        self.tonemap = tonemap;
+        self.rebuild_swapchain(self.swapchain_create_info());
+    }
+
+    pub fn tonemap(&self) -> Tonemap {
+        self.tonemap
+    }
 
-        // Allocator
-        let command_buffer_allocator =
-            StandardCommandBufferAllocator::new(device.clone(), Default::default());
-        // Render pass
-        let render_pass = vulkano::single_pass_renderpass!(
-            device.clone(),
-            attachments: {
-                color: {
-                    load: Clear,
-                    store: Store,
-                    format: swapchain.image_format(),
-                    samples: 1,
-                }
-            },
-            pass: {
-                color: [color],
-                depth_stencil: {}
-            }
-        )
-        .unwrap();
+    /// Multiplies the main pass output by `exposure`, 1 by default, before tone mapping it.
+    /// Ignored while colors pass through, see `set_tonemap`.
+    pub fn set_exposure(&mut self, exposure: f32) {
+        self.exposure = exposure.max(0.0);
+    }
 
-        // Viewport
-        let mut viewport = Viewport {
-            origin: [0.0, 0.0],
-            dimensions: [0.0, 0.0],
-            depth_range: 0.0..1.0,
-        };
+    pub fn exposure(&self) -> f32 {
+        self.exposure
+    }
 
-        // Framebuffer
-        let framebuffers =
-            Graphicengine::window_size_dependent_setup(&images, render_pass.clone(), &mut viewport);
+    // whether the main pass output is tone mapped on its way to the swapchain image
+    fn tonemapping(&self) -> bool {
+        self.tonemap != Tonemap::None
+            && matches!(
+                self.main_format().type_color(),
+                Some(NumericType::SFLOAT | NumericType::UFLOAT)
+            )
+    }
 
-        // for renders
-        let previous_frame_end = Some(Box::new(sync::now(device.clone())) as Box<dyn GpuFuture>);
+    /// Runs the `main` entry point of the compute `shader` over the swapchain image every frame,
+    /// after the main pass and FXAA and before present, with one invocation per pixel in
+    /// workgroups of `local_size`, which must match the shader's. Invocations past the image edge
+    /// must be skipped by the shader, e.g. by comparing against `imageSize`.
+    ///
+    /// The swapchain image is the only descriptor, a storage image at set 0, binding 0. Swapchain
+    /// formats like `B8G8R8A8_UNORM` have no GLSL format qualifier, so declare it `writeonly`
+    /// without one, which needs the `shader_storage_image_write_without_format` feature, enabled
+    /// when supported.
+    ///
+    /// Recreates the swapchain with storage usage. Fails if the surface or the current swapchain
+    /// format doesn't support it, see `supports_compute_post_process`; sRGB formats usually don't.
+    /// It is turned off if a later format change loses that support.
+    pub fn set_compute_post_process(
+        &mut self,
+        shader: &Arc<ShaderModule>,
+        local_size: [u32; 2],
+    ) -> Result<(), EngineError> {
+        let format = self.swapchain.image_format();
+        if !self.supports_storage_swapchain(format) {
+            return Err(EngineError::StorageSwapchainUnsupported(format));
+        }
+        self.compute_present = Some(ComputePresent::new(
+            &self.device,
+            shader,
+            local_size,
+            &self.pipeline_cache,
+        )?);
+        self.rebuild_swapchain(self.swapchain_create_info());
+        Ok(())
+    }
 
-        Graphicengine {
-            device,
-            surface,
-            swapchain,
-            queue,
-            framebuffers,
-            command_buffer_allocator,
-            render_pass,
-            viewport,
-            previous_frame_end,
+    /// Stops the compute post-process, and recreates the swapchain without storage usage.
+    pub fn clear_compute_post_process(&mut self) {
+        if self.compute_present.take().is_some() {
+            self.rebuild_swapchain(self.swapchain_create_info());
         }
     }
 
-    pub fn render(&mut self, recreate_swapchain: &mut bool) {
-        self.previous_frame_end
-            .as_mut()
-            .take()
+    pub fn compute_post_process(&self) -> bool {
+        self.compute_present.is_some()
+    }
+
+    /// Whether the swapchain images can be written by a compute shader in the current format.
+    pub fn supports_compute_post_process(&self) -> bool {
+        self.supports_storage_swapchain(self.swapchain.image_format())
+    }
+
+    fn supports_storage_swapchain(&self, format: Format) -> bool {
+        self.physical_device
+            .surface_capabilities(&self.surface, Default::default())
             .unwrap()
-            .cleanup_finished();
+            .supported_usage_flags
+            .storage
+            && self
+                .physical_device
+                .format_properties(format)
+                .unwrap()
+                .optimal_tiling_features
+                .storage_image
+    }
 
-        let (image_index, suboptimal, acquire_future) =
-            match swapchain::acquire_next_image(self.swapchain.clone(), None) {
-                Ok(r) => r,
-                Err(AcquireError::OutOfDate) => {
-                    *recreate_swapchain = true;
-                    return;
-                }
-                Err(e) => panic!("Failed to acquire next image: {:?}", e),
-            };
+    /// Size of the image the main pass renders to, the swapchain extent times the render scale.
+    pub fn render_extent(&self) -> [u32; 2] {
+        let [width, height] = self.viewport.dimensions;
+        [width as u32, height as u32]
+    }
 
-        if suboptimal {
-            *recreate_swapchain = true;
+    /// Renders the main pass to an intermediate image of `format` instead of the swapchain
+    /// image, then blits or post-processes it to the swapchain image. `None` renders in the
+    /// swapchain format.
+    ///
+    /// Intermediates should be linear, e.g. `R16G16B16A16_SFLOAT` to keep values above 1.0 for
+    /// HDR, or `R8G8B8A8_UNORM`: with an sRGB swapchain, shaders then write linear values and the
+    /// sRGB encoding only happens once, on the final write to the swapchain image.
+    ///
+    /// Pipelines drawing in the main pass have to use this format for their first color
+    /// attachment, so rebuild them after changing it.
+    pub fn set_main_target_format(&mut self, format: Option<Format>) -> Result<(), EngineError> {
+        if let Some(format) = format {
+            let format_features = self
+                .physical_device
+                .format_properties(format)
+                .unwrap()
+                .optimal_tiling_features;
+            if !format_features.color_attachment
+                || !format_features.sampled_image
+                || !self.supports_blit_from(format)
+            {
+                return Err(EngineError::UnsupportedColorTargetFormat(format));
+            }
         }
 
-        let clear_values: Vec<Option<ClearValue>> =
-            vec![Some(ClearValue::Float([0.0, 0.68, 1.0, 1.0]))];
+        self.main_target_format = format;
+        self.render_pass = Graphicengine::get_render_pass(
+            &self.device,
+            self.main_format(),
+            self.load_op,
+            self.sample_count,
+            &self.color_target_formats,
+            self.main_depth(),
+            &self.store_ops,
+        );
+        self.rebuild_swapchain(self.swapchain_create_info());
+        Ok(())
+    }
 
-        let mut cmd_buffer_builder = AutoCommandBufferBuilder::primary(
-            &self.command_buffer_allocator,
-            self.queue.queue_family_index(),
-            CommandBufferUsage::OneTimeSubmit,
-        )
-        .unwrap();
+    pub fn main_target_format(&self) -> Option<Format> {
+        self.main_target_format
+    }
 
-        cmd_buffer_builder
-            .begin_render_pass(
-                RenderPassBeginInfo {
-                    clear_values,
-                    ..RenderPassBeginInfo::framebuffer(
-                        self.framebuffers[image_index as usize].clone(),
-                    )
-                },
-                SubpassContents::Inline,
-            )
-            .unwrap()
-            .end_render_pass()
-            .unwrap();
+    // format the main pass renders in
+    fn main_format(&self) -> Format {
+        self.main_target_format
+            .unwrap_or(self.swapchain.image_format())
+    }
 
-        let command_buffer = cmd_buffer_builder.build().unwrap();
+    fn supports_render_scale(&self) -> bool {
+        self.supports_blit_from(self.main_format())
+    }
 
-        let future = self
-            .previous_frame_end
-            .take()
-            .unwrap()
-            .join(acquire_future)
-            .then_execute(self.queue.clone(), command_buffer)
-            .unwrap()
-            .then_swapchain_present(
-                self.queue.clone(),
-                SwapchainPresentInfo::swapchain_image_index(self.swapchain.clone(), image_index),
-            )
-            .then_signal_fence_and_flush();
+    // the blit needs the swapchain images as destination and linear filtering of the source
+    fn supports_blit_from(&self, format: Format) -> bool {
+        let format_features = |format| {
+            self.physical_device
+                .format_properties(format)
+                .unwrap()
+                .optimal_tiling_features
+        };
+        self.swapchain.image_usage().transfer_dst
+            && format_features(format).blit_src
+            && format_features(format).sampled_image_filter_linear
+            && format_features(self.swapchain.image_format()).blit_dst
+    }
 
-        match future {
-            Ok(future) => {
-                self.previous_frame_end = Some(Box::new(future) as Box<_>);
-            }
-            Err(FlushError::OutOfDate) => {
-                *recreate_swapchain = true;
-                self.previous_frame_end = Some(Box::new(sync::now(self.device.clone())) as Box<_>);
-            }
+    // what the swapchain is, or is next recreated with when a rebuild is pending
+    fn swapchain_create_info(&self) -> SwapchainCreateInfo {
+        self.pending_swapchain
+            .clone()
+            .unwrap_or_else(|| self.swapchain.create_info())
+    }
+
+    // returns false when the swapchain can't be recreated for now, e.g. while minimized, and
+    // keeps `create_info` for the next frame to retry, so settings changed meanwhile aren't lost.
+    // Other failures are printed, the next frame retries and returns them.
+    fn rebuild_swapchain(&mut self, create_info: SwapchainCreateInfo) -> bool {
+        match self.try_rebuild_swapchain(create_info) {
+            Ok(rebuilt) => rebuilt,
             Err(e) => {
-                println!("Failed to flush future: {:?}", e);
-                self.previous_frame_end = Some(Box::new(sync::now(self.device.clone())) as Box<_>);
+                println!("{}", e);
+                false
             }
         }
     }
 
-    pub fn recreate_swapchain(&mut self, recreate_swapchain: &mut bool) {
-        let window = self
-            .surface
-            .object()
-            .unwrap()
-            .downcast_ref::<Window>()
-            .unwrap();
-        let image_extent: [u32; 2] = window.inner_size().into();
+    fn try_rebuild_swapchain(
+        &mut self,
+        create_info: SwapchainCreateInfo,
+    ) -> Result<bool, EngineError> {
+        self.pending_swapchain = Some(create_info.clone());
+        let mut image_extent: [u32; 2] = self.window().inner_size().into();
+        // a zero extent is a minimized window, which keeps failing below
+        if !image_extent.contains(&0) {
+            let caps = self
+                .physical_device
+                .surface_capabilities(&self.surface, Default::default())
+                .map_err(EngineError::SurfaceQuery)?;
+            image_extent = [0, 1].map(|i| {
+                image_extent[i]
+                    .max(self.min_extent[i])
+                    .clamp(caps.min_image_extent[i], caps.max_image_extent[i])
+            });
+        }
+
+        // storage images are only requested for the compute post-process, as most sRGB formats
+        // can't have storage views
+        let format = create_info.image_format.unwrap();
+        if self.compute_present.is_some() && !self.supports_storage_swapchain(format) {
+            println!(
+                "{:?} swapchain images can't be storage images, compute post-process disabled",
+                format
+            );
+            self.compute_present = None;
+        }
+        let image_usage = ImageUsage {
+            storage: self.compute_present.is_some(),
+            ..create_info.image_usage
+        };
 
-        let (new_swapchain, new_images) = match self.swapchain.recreate(SwapchainCreateInfo {
-            image_extent,
-            ..self.swapchain.create_info()
-        }) {
+        let (new_swapchain, new_images) = match Graphicengine::create_swapchain(
+            &self.device,
+            &self.surface,
+            Some(&self.swapchain),
+            SwapchainCreateInfo {
+                image_extent,
+                image_usage,
+                clipped: self.load_op != LoadOp::Load,
+                ..create_info
+            },
+        ) {
             Ok(r) => r,
-            Err(SwapchainCreationError::ImageExtentNotSupported { .. }) => return,
-            Err(e) => panic!("Failed to recreate swapchain: {:?}", e),
+            Err(SwapchainCreationError::ImageExtentNotSupported { .. }) => return Ok(false),
+            Err(e) => return Err(EngineError::SwapchainRecreation(e)),
         };
+        self.pending_swapchain = None;
 
+        if new_swapchain.image_format() != self.swapchain.image_format() {
+            self.render_pass = Graphicengine::get_render_pass(
+                &self.device,
+                self.main_target_format
+                    .unwrap_or(new_swapchain.image_format()),
+                self.load_op,
+                self.sample_count,
+                &self.color_target_formats,
+                self.main_depth(),
+                &self.store_ops,
+            );
+        }
         self.swapchain = new_swapchain;
-        self.framebuffers = Graphicengine::window_size_dependent_setup(
+        self.present_pacer.clear();
+        self.image_index = None;
+        // metadata belongs to the swapchain it was set on
+        if let Some(metadata) = &self.hdr_metadata {
+            hdr::set(&self.device, &self.swapchain, metadata);
+        }
+        // a new format may not support blitting
+        if self.render_scale != 1.0 && !self.supports_render_scale() {
+            println!("Render scale not supported with this swapchain, rendering at full size");
+            self.render_scale = 1.0;
+        }
+        if let Some(format) = self.main_target_format {
+            if !self.supports_blit_from(format) {
+                println!(
+                    "{:?} can't be blitted to this swapchain, rendering in the swapchain format",
+                    format
+                );
+                self.main_target_format = None;
+                self.render_pass = Graphicengine::get_render_pass(
+                    &self.device,
+                    self.swapchain.image_format(),
+                    self.load_op,
+                    self.sample_count,
+                    &self.color_target_formats,
+                    self.main_depth(),
+                    &self.store_ops,
+                );
+            }
+        }
+        let targets = Graphicengine::window_size_dependent_setup(
             &new_images,
-            self.render_pass.clone(),
+            &self.render_pass,
+            self.render_path,
+            &self.memory_allocator,
+            self.render_scale,
+            self.fxaa.is_some() || self.tonemapping(),
             &mut self.viewport,
         );
-        *recreate_swapchain = false;
+        (
+            self.image_views,
+            self.offscreen_view,
+            self.msaa_view,
+            self.color_targets,
+            self.depth_view,
+            self.framebuffers,
+        ) = match targets {
+            Ok(targets) => targets,
+            Err(e) => {
+                // the old targets are for the old images, without any the next frame retries
+                self.image_views.clear();
+                self.framebuffers.clear();
+                return Err(e);
+            }
+        };
+        if self.deferred {
+            let format = self.main_format();
+            if !matches!(&self.deferred_lighting, Some(pass) if pass.format() == format) {
+                self.deferred_lighting = Some(deferred::lighting_pass(
+                    &self.device,
+                    format,
+                    &self.frame_uniforms.layout(),
+                    &self.pipeline_cache,
+                ));
+            }
+            // lit in the main target, before it is post-processed or blitted
+            let targets = self
+                .image_views
+                .iter()
+                .map(|view| -> Arc<dyn ImageViewAbstract> {
+                    match &self.offscreen_view {
+                        Some(offscreen_view) => offscreen_view.clone(),
+                        None => view.clone(),
+                    }
+                })
+                .collect();
+            let deferred_lighting = self.deferred_lighting.as_mut().unwrap();
+            let writes = deferred::gbuffer_writes(deferred_lighting, &self.color_targets);
+            deferred_lighting.set_targets(&self.descriptor_set_allocator, writes, targets);
+        } else {
+            self.deferred_lighting = None;
+        }
+        let format = self.swapchain.image_format();
+        if let Some(fxaa) = &mut self.fxaa {
+            if fxaa.format() != format {
+                *fxaa = fxaa::pass(&self.device, format, &self.pipeline_cache);
+            }
+        }
+        // FXAA tone maps itself
+        if self.tonemapping() && self.fxaa.is_none() {
+            if !matches!(&self.tonemap_pass, Some(pass) if pass.format() == format) {
+                self.tonemap_pass = Some(tonemap::pass(&self.device, format, &self.pipeline_cache));
+            }
+        } else {
+            self.tonemap_pass = None;
+        }
+        for post_pass in self.fxaa.iter_mut().chain(&mut self.tonemap_pass) {
+            let writes = [
+                WriteDescriptorSet::image_view(0, self.offscreen_view.clone().unwrap()),
+                WriteDescriptorSet::sampler(1, post_pass.sampler().clone()),
+            ];
+            let targets = self
+                .image_views
+                .iter()
+                .map(|view| view.clone() as Arc<dyn ImageViewAbstract>)
+                .collect();
+            post_pass.set_targets(&self.descriptor_set_allocator, writes, targets);
+        }
+        if let Some(compute_present) = &mut self.compute_present {
+            compute_present.set_targets(&self.descriptor_set_allocator, &self.image_views);
+        }
+        self.update_view_proj();
+
+        let info = self.swapchain_info();
+        if let Some(on_recreate) = &mut self.on_recreate {
+            on_recreate(&info);
+        }
+        Ok(true)
     }
 
     fn get_best_compatible_physical_device(
         instance: Arc<Instance>,
         device_extensions: &DeviceExtensions,
         surface: &Arc<Surface>,
+        power_preference: PowerPreference,
     ) -> (Arc<PhysicalDevice>, u32) {
         instance
             .enumerate_physical_devices()
@@ -236,35 +3982,89 @@ impl Graphicengine {
             })
             .min_by_key(|(p, _)| {
                 // lower score for preferred device types
-                match p.properties().device_type {
-                    PhysicalDeviceType::DiscreteGpu => 0,
-                    PhysicalDeviceType::IntegratedGpu => 1,
-                    PhysicalDeviceType::VirtualGpu => 2,
-                    PhysicalDeviceType::Cpu => 3,
-                    PhysicalDeviceType::Other => 4,
+                match (p.properties().device_type, power_preference) {
+                    (PhysicalDeviceType::DiscreteGpu, PowerPreference::HighPerformance)
+                    | (PhysicalDeviceType::IntegratedGpu, PowerPreference::LowPower) => 0,
+                    (PhysicalDeviceType::DiscreteGpu | PhysicalDeviceType::IntegratedGpu, _) => 1,
+                    (PhysicalDeviceType::VirtualGpu, _) => 2,
+                    (PhysicalDeviceType::Cpu, _) => 3,
+                    (PhysicalDeviceType::Other, _) => 4,
                     _ => 5,
                 }
             })
             .expect("No suitable physical device found")
     }
 
+    fn supports_dynamic_rendering(physical_device: &Arc<PhysicalDevice>) -> bool {
+        physical_device.api_version() >= Version::V1_1
+            && physical_device.supported_extensions().khr_dynamic_rendering
+            && physical_device.supported_features().dynamic_rendering
+    }
+
+    fn supports_descriptor_indexing(physical_device: &Arc<PhysicalDevice>) -> bool {
+        let features = physical_device.supported_features();
+        physical_device.api_version() >= Version::V1_1
+            && physical_device
+                .supported_extensions()
+                .ext_descriptor_indexing
+            && features.runtime_descriptor_array
+            && features.descriptor_binding_variable_descriptor_count
+            && features.shader_sampled_image_array_dynamic_indexing
+    }
+
     fn get_device(
         physical_device: Arc<PhysicalDevice>,
         queue_family_index: u32,
         device_extensions: DeviceExtensions,
-    ) -> (Arc<Device>, impl ExactSizeIterator<Item = Arc<Queue>>) {
-        Device::new(
-            physical_device,
-            DeviceCreateInfo {
-                enabled_extensions: device_extensions,
-                queue_create_infos: vec![QueueCreateInfo {
-                    queue_family_index,
+        retry: DeviceRetry,
+    ) -> Result<(Arc<Device>, impl ExactSizeIterator<Item = Arc<Queue>>), DeviceCreationError> {
+        // optional features, only enabled when the device supports them
+        let enabled_features = Features {
+            sampler_anisotropy: physical_device.supported_features().sampler_anisotropy,
+            multi_draw_indirect: physical_device.supported_features().multi_draw_indirect,
+            sample_rate_shading: physical_device.supported_features().sample_rate_shading,
+            geometry_shader: physical_device.supported_features().geometry_shader,
+            triangle_fans: physical_device.supported_features().triangle_fans,
+            fill_mode_non_solid: physical_device.supported_features().fill_mode_non_solid,
+            shader_storage_image_write_without_format: physical_device
+                .supported_features()
+                .shader_storage_image_write_without_format,
+            dynamic_rendering: device_extensions.khr_dynamic_rendering,
+            present_id: device_extensions.khr_present_id,
+            present_wait: device_extensions.khr_present_wait,
+            runtime_descriptor_array: device_extensions.ext_descriptor_indexing,
+            descriptor_binding_variable_descriptor_count: device_extensions.ext_descriptor_indexing,
+            shader_sampled_image_array_dynamic_indexing: device_extensions.ext_descriptor_indexing,
+            ..Features::empty()
+        };
+
+        let mut delay = retry.delay;
+        for attempt in 1.. {
+            let result = Device::new(
+                physical_device.clone(),
+                DeviceCreateInfo {
+                    enabled_extensions: device_extensions,
+                    enabled_features,
+                    queue_create_infos: vec![QueueCreateInfo {
+                        queue_family_index,
+                        ..Default::default()
+                    }],
                     ..Default::default()
-                }],
-                ..Default::default()
-            },
-        )
-        .unwrap()
+                },
+            );
+            match result {
+                Err(e) if attempt < retry.attempts => {
+                    println!(
+                        "Device creation failed ({}), retrying in {:?} ({}/{})",
+                        e, delay, attempt, retry.attempts
+                    );
+                    thread::sleep(delay);
+                    delay *= 2;
+                }
+                result => return result,
+            }
+        }
+        unreachable!()
     }
 
     fn get_swapchain(
@@ -276,56 +4076,561 @@ impl Graphicengine {
             .surface_capabilities(surface, Default::default())
             .unwrap();
 
-        let usage = caps.supported_usage_flags;
+        // storage usage is added for the compute post-process only, see rebuild_swapchain
+        let usage = ImageUsage {
+            storage: false,
+            ..caps.supported_usage_flags
+        };
         let alpha = caps.supported_composite_alpha.iter().next().unwrap();
 
-        let image_format = Some(
-            device
-                .physical_device()
-                .surface_formats(surface, Default::default())
-                .unwrap()[1]
-                .0,
-        );
+        // portability implementations may report a single format
+        let formats = device
+            .physical_device()
+            .surface_formats(surface, Default::default())
+            .unwrap();
+        let image_format = Some(formats.get(1).unwrap_or(&formats[0]).0);
 
         let window = surface.object().unwrap().downcast_ref::<Window>().unwrap();
         let image_extent: [u32; 2] = window.inner_size().into();
 
-        Swapchain::new(
-            device.clone(),
-            surface.clone(),
+        Graphicengine::create_swapchain(
+            device,
+            surface,
+            None,
             SwapchainCreateInfo {
                 min_image_count: caps.min_image_count,
                 image_format,
                 image_extent,
                 image_usage: usage,
                 composite_alpha: alpha,
+                // the exclusive default is right: the queue family used for rendering is also the
+                // one presenting, see get_best_compatible_physical_device
                 ..Default::default()
             },
         )
         .unwrap()
     }
 
+    // Creates the swapchain, from `old_swapchain` if there is one. When running out of memory,
+    // retries with fewer images down to the surface minimum, then with FIFO presentation.
+    fn create_swapchain(
+        device: &Arc<Device>,
+        surface: &Arc<Surface>,
+        mut old_swapchain: Option<&Arc<Swapchain>>,
+        mut create_info: SwapchainCreateInfo,
+    ) -> Result<(Arc<Swapchain>, Vec<Arc<SwapchainImage>>), SwapchainCreationError> {
+        let min_image_count = device
+            .physical_device()
+            .surface_capabilities(surface, Default::default())
+            .unwrap()
+            .min_image_count;
+
+        loop {
+            // a swapchain is retired even when recreating from it fails
+            let result = match old_swapchain.take() {
+                Some(old_swapchain) => old_swapchain.recreate(create_info.clone()),
+                None => Swapchain::new(device.clone(), surface.clone(), create_info.clone()),
+            };
+
+            match result {
+                Err(SwapchainCreationError::OomError(e)) => {
+                    if create_info.min_image_count > min_image_count {
+                        create_info.min_image_count -= 1;
+                    } else if create_info.present_mode != PresentMode::Fifo {
+                        create_info.present_mode = PresentMode::Fifo;
+                    } else {
+                        return Err(SwapchainCreationError::OomError(e));
+                    }
+                    println!(
+                        "Failed to create swapchain ({}), retrying with {} images in {:?} mode",
+                        e, create_info.min_image_count, create_info.present_mode
+                    );
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // with multisampling, attachment 0 is the multisampled image resolved into attachment 1, the
+    // swapchain image, otherwise any extra color targets follow attachment 0. The depth attachment
+    // comes last.
+    fn get_render_pass(
+        device: &Arc<Device>,
+        format: Format,
+        load_op: LoadOp,
+        sample_count: SampleCount,
+        color_target_formats: &[Format],
+        depth: Option<(Format, DepthMode)>,
+        store_ops: &StoreOps,
+    ) -> Arc<RenderPass> {
+        let color_attachment = AttachmentDescription {
+            format: Some(format),
+            samples: sample_count,
+            load_op,
+            store_op: StoreOp::Store,
+            initial_layout: ImageLayout::ColorAttachmentOptimal,
+            final_layout: ImageLayout::ColorAttachmentOptimal,
+            ..Default::default()
+        };
+        let attachment_reference = |attachment| {
+            Some(AttachmentReference {
+                attachment,
+                layout: ImageLayout::ColorAttachmentOptimal,
+                ..Default::default()
+            })
+        };
+
+        let mut create_info = if sample_count == SampleCount::Sample1 {
+            RenderPassCreateInfo {
+                attachments: vec![color_attachment],
+                subpasses: vec![SubpassDescription {
+                    color_attachments: vec![attachment_reference(0)],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }
+        } else {
+            RenderPassCreateInfo {
+                attachments: vec![
+                    AttachmentDescription {
+                        // the samples only need to outlive the frame when loaded by the next one
+                        store_op: if load_op == LoadOp::Load {
+                            StoreOp::Store
+                        } else {
+                            store_ops.multisampled
+                        },
+                        ..color_attachment
+                    },
+                    AttachmentDescription {
+                        samples: SampleCount::Sample1,
+                        load_op: LoadOp::DontCare,
+                        ..color_attachment
+                    },
+                ],
+                subpasses: vec![SubpassDescription {
+                    color_attachments: vec![attachment_reference(0)],
+                    resolve_attachments: vec![attachment_reference(1)],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }
+        };
+
+        for (&format, &store_op) in color_target_formats.iter().zip(&store_ops.color_targets) {
+            let attachment = create_info.attachments.len() as u32;
+            create_info.attachments.push(AttachmentDescription {
+                format: Some(format),
+                load_op: LoadOp::Clear,
+                store_op,
+                ..color_attachment
+            });
+            create_info.subpasses[0]
+                .color_attachments
+                .push(attachment_reference(attachment));
+        }
+
+        if let Some((format, mode)) = depth {
+            // read-only depth is kept from frame to frame
+            let (load_op, store_op, layout) = match mode {
+                DepthMode::ReadOnly => (
+                    LoadOp::Load,
+                    StoreOp::Store,
+                    ImageLayout::DepthStencilReadOnlyOptimal,
+                ),
+                _ => (
+                    LoadOp::Clear,
+                    store_ops.depth,
+                    ImageLayout::DepthStencilAttachmentOptimal,
+                ),
+            };
+            let attachment = create_info.attachments.len() as u32;
+            create_info.attachments.push(AttachmentDescription {
+                format: Some(format),
+                samples: sample_count,
+                load_op,
+                store_op,
+                initial_layout: layout,
+                final_layout: layout,
+                ..Default::default()
+            });
+            create_info.subpasses[0].depth_stencil_attachment = Some(AttachmentReference {
+                attachment,
+                layout,
+                ..Default::default()
+            });
+        }
+
+        RenderPass::new(device.clone(), create_info).unwrap()
+    }
+
     fn window_size_dependent_setup(
         images: &[Arc<SwapchainImage>],
-        render_pass: Arc<RenderPass>,
+        render_pass: &Arc<RenderPass>,
+        render_path: RenderPath,
+        memory_allocator: &StandardMemoryAllocator,
+        render_scale: f32,
+        post_process: bool,
         viewport: &mut Viewport,
-    ) -> Vec<Arc<Framebuffer>> {
-        let dimensions = images[0].dimensions().width_height();
+    ) -> Result<ImageTargets, EngineError> {
+        // the render pass holds the sample count and formats of the attachments to create
+        let attachments = render_pass.attachments();
+        let subpass = &render_pass.subpasses()[0];
+        let sample_count = attachments[0].samples;
+        let main_format = attachments[0].format.unwrap();
+        let attachment_format =
+            |reference: &AttachmentReference| attachments[reference.attachment as usize].format;
+        let color_target_formats = subpass.color_attachments[1..]
+            .iter()
+            .flatten()
+            .filter_map(attachment_format);
+        let depth_format = subpass
+            .depth_stencil_attachment
+            .as_ref()
+            .and_then(attachment_format);
+
+        let swapchain_dimensions = images[0].dimensions().width_height();
+        let dimensions = swapchain_dimensions.map(|d| ((d as f32 * render_scale) as u32).max(1));
         viewport.dimensions = [dimensions[0] as f32, dimensions[1] as f32];
+        let attachment_size = |format: Format, samples: SampleCount| {
+            dimensions[0] as u64
+                * dimensions[1] as u64
+                * format.block_size().unwrap_or(4)
+                * samples as u64
+        };
+
+        let image_views = images
+            .iter()
+            .map(|image| ImageView::new_default(image.clone()).unwrap())
+            .collect::<Vec<_>>();
+
+        // rendered at the internal resolution or in the main target format and blitted or
+        // post-processed to the swapchain image, one is enough like the multisampled image
+        let offscreen_view = (post_process
+            || dimensions != swapchain_dimensions
+            || main_format != images[0].format())
+        .then(|| {
+            let image = AttachmentImage::with_usage(
+                memory_allocator,
+                dimensions,
+                main_format,
+                ImageUsage {
+                    color_attachment: true,
+                    transfer_src: true,
+                    sampled: true,
+                    ..ImageUsage::empty()
+                },
+            )
+            .map_err(EngineError::image_allocation(attachment_size(
+                main_format,
+                SampleCount::Sample1,
+            )))?;
+            Ok::<_, EngineError>(ImageView::new_default(image).unwrap())
+        })
+        .transpose()?;
+
+        // one multisampled image is enough, frames using it are ordered on the queue
+        let msaa_view = (sample_count != SampleCount::Sample1)
+            .then(|| {
+                let image = AttachmentImage::multisampled_with_usage(
+                    memory_allocator,
+                    dimensions,
+                    sample_count,
+                    main_format,
+                    ImageUsage {
+                        color_attachment: true,
+                        ..ImageUsage::empty()
+                    },
+                )
+                .map_err(EngineError::image_allocation(attachment_size(
+                    main_format,
+                    sample_count,
+                )))?;
+                Ok::<_, EngineError>(ImageView::new_default(image).unwrap())
+            })
+            .transpose()?;
+
+        let color_targets = color_target_formats
+            .map(|format| {
+                let image = AttachmentImage::with_usage(
+                    memory_allocator,
+                    dimensions,
+                    format,
+                    ImageUsage {
+                        color_attachment: true,
+                        sampled: true,
+                        transfer_src: true,
+                        ..ImageUsage::empty()
+                    },
+                )
+                .map_err(EngineError::image_allocation(attachment_size(
+                    format,
+                    SampleCount::Sample1,
+                )))?;
+                Ok(ImageView::new_default(image).unwrap())
+            })
+            .collect::<Result<Vec<_>, EngineError>>()?;
 
-        images
+        let depth_view = depth_format
+            .map(|format| {
+                let image = AttachmentImage::multisampled_with_usage(
+                    memory_allocator,
+                    dimensions,
+                    sample_count,
+                    format,
+                    ImageUsage {
+                        depth_stencil_attachment: true,
+                        transfer_src: true,
+                        ..ImageUsage::empty()
+                    },
+                )
+                .map_err(EngineError::image_allocation(attachment_size(
+                    format,
+                    sample_count,
+                )))?;
+                Ok::<_, EngineError>(ImageView::new_default(image).unwrap())
+            })
+            .transpose()?;
+
+        // dynamic rendering attaches the views directly
+        let framebuffers = match render_path {
+            RenderPath::RenderPass => Graphicengine::create_framebuffers(
+                render_pass,
+                &image_views,
+                offscreen_view.as_ref(),
+                msaa_view.as_ref(),
+                &color_targets,
+                depth_view.as_ref(),
+            ),
+            RenderPath::DynamicRendering => Vec::new(),
+        };
+
+        Ok((
+            image_views,
+            offscreen_view,
+            msaa_view,
+            color_targets,
+            depth_view,
+            framebuffers,
+        ))
+    }
+
+    // one framebuffer per swapchain image over the existing views, e.g. to switch to a render
+    // pass differing only in load and store ops without reallocating the attachments
+    fn create_framebuffers(
+        render_pass: &Arc<RenderPass>,
+        image_views: &[Arc<ImageView<SwapchainImage>>],
+        offscreen_view: Option<&Arc<ImageView<AttachmentImage>>>,
+        msaa_view: Option<&Arc<ImageView<AttachmentImage>>>,
+        color_targets: &[Arc<ImageView<AttachmentImage>>],
+        depth_view: Option<&Arc<ImageView<AttachmentImage>>>,
+    ) -> Vec<Arc<Framebuffer>> {
+        image_views
             .iter()
-            .map(|image| {
-                let view = ImageView::new_default(image.clone()).unwrap();
+            .map(|view| {
+                let target_view: Arc<dyn ImageViewAbstract> = match offscreen_view {
+                    Some(offscreen_view) => offscreen_view.clone(),
+                    None => view.clone(),
+                };
+                let mut attachments = match msaa_view {
+                    Some(msaa_view) => vec![msaa_view.clone(), target_view],
+                    None => vec![target_view],
+                };
+                attachments.extend(
+                    color_targets
+                        .iter()
+                        .map(|view| view.clone() as Arc<dyn ImageViewAbstract>),
+                );
+                if let Some(depth_view) = depth_view {
+                    attachments.push(depth_view.clone());
+                }
                 Framebuffer::new(
                     render_pass.clone(),
                     FramebufferCreateInfo {
-                        attachments: vec![view],
+                        attachments,
                         ..Default::default()
                     },
                 )
                 .unwrap()
             })
-            .collect::<Vec<_>>()
+            .collect()
+    }
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.040_45 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// clear values are typed after the numeric format of the attachment
+fn zero_clear_value(format: Format) -> ClearValue {
+    match format.type_color() {
+        Some(NumericType::UINT) => ClearValue::Uint([0; 4]),
+        Some(NumericType::SINT) => ClearValue::Int([0; 4]),
+        _ => ClearValue::Float([0.0; 4]),
+    }
+}
+
+// checks the pipeline layout expects a buffer of `descriptor_type` at `(set, binding)`, and that
+// the buffer was created with the matching usage
+fn buffer_write(
+    pipeline_layout: &PipelineLayout,
+    (set, binding): (u32, u32),
+    descriptor_type: DescriptorType,
+    has_usage: bool,
+    buffer: Arc<dyn BufferAccess>,
+) -> Result<WriteDescriptorSet, EngineError> {
+    let expected = pipeline_layout
+        .set_layouts()
+        .get(set as usize)
+        .and_then(|set_layout| set_layout.bindings().get(&binding))
+        .is_some_and(|layout_binding| layout_binding.descriptor_type == descriptor_type);
+    if !expected {
+        return Err(EngineError::NoDescriptorBinding {
+            set,
+            binding,
+            descriptor_type,
+        });
+    }
+    if !has_usage {
+        return Err(EngineError::BufferUsageMismatch(descriptor_type));
+    }
+    Ok(WriteDescriptorSet::buffer(binding, buffer))
+}
+
+// the same test with depth growing towards the camera
+fn reversed_compare_op(compare_op: CompareOp) -> CompareOp {
+    match compare_op {
+        CompareOp::Less => CompareOp::Greater,
+        CompareOp::LessOrEqual => CompareOp::GreaterOrEqual,
+        CompareOp::Greater => CompareOp::Less,
+        CompareOp::GreaterOrEqual => CompareOp::LessOrEqual,
+        op => op,
+    }
+}
+
+// list topologies need VK_EXT_primitive_topology_list_restart, which isn't enabled
+fn supports_primitive_restart(topology: PrimitiveTopology) -> bool {
+    matches!(
+        topology,
+        PrimitiveTopology::LineStrip
+            | PrimitiveTopology::TriangleStrip
+            | PrimitiveTopology::TriangleFan
+            | PrimitiveTopology::LineStripWithAdjacency
+            | PrimitiveTopology::TriangleStripWithAdjacency
+    )
+}
+
+// counts usable by both color and depth attachments, from lowest to highest
+fn framebuffer_sample_counts(color: SampleCounts, depth: SampleCounts) -> Vec<SampleCount> {
+    let sample_counts = color.intersection(&depth);
+    [
+        SampleCount::Sample1,
+        SampleCount::Sample2,
+        SampleCount::Sample4,
+        SampleCount::Sample8,
+        SampleCount::Sample16,
+        SampleCount::Sample32,
+        SampleCount::Sample64,
+    ]
+    .into_iter()
+    .filter(|&sample_count| sample_counts.contains_count(sample_count))
+    .collect()
+}
+
+// Vulkan requires 1 sample to be supported, so it is the fallback
+fn max_sample_count(sample_counts: &[SampleCount]) -> SampleCount {
+    sample_counts
+        .last()
+        .copied()
+        .unwrap_or(SampleCount::Sample1)
+}
+
+// whether primitive restart stays enabled after switching to `topology`
+fn keeps_primitive_restart(primitive_restart: bool, topology: PrimitiveTopology) -> bool {
+    primitive_restart && supports_primitive_restart(topology)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primitive_restart_needs_strip_or_fan() {
+        for topology in [
+            PrimitiveTopology::LineStrip,
+            PrimitiveTopology::TriangleStrip,
+            PrimitiveTopology::TriangleFan,
+            PrimitiveTopology::LineStripWithAdjacency,
+            PrimitiveTopology::TriangleStripWithAdjacency,
+        ] {
+            assert!(supports_primitive_restart(topology), "{:?}", topology);
+        }
+        for topology in [
+            PrimitiveTopology::PointList,
+            PrimitiveTopology::LineList,
+            PrimitiveTopology::TriangleList,
+            PrimitiveTopology::LineListWithAdjacency,
+            PrimitiveTopology::TriangleListWithAdjacency,
+            PrimitiveTopology::PatchList,
+        ] {
+            assert!(!supports_primitive_restart(topology), "{:?}", topology);
+        }
+    }
+
+    #[test]
+    fn sample_counts_usable_by_color_and_depth() {
+        let color = SampleCounts {
+            sample1: true,
+            sample2: true,
+            sample4: true,
+            sample8: true,
+            ..SampleCounts::empty()
+        };
+        let depth = SampleCounts {
+            sample1: true,
+            sample4: true,
+            sample16: true,
+            ..SampleCounts::empty()
+        };
+        let sample_counts = framebuffer_sample_counts(color, depth);
+        assert_eq!(sample_counts, [SampleCount::Sample1, SampleCount::Sample4]);
+        assert_eq!(max_sample_count(&sample_counts), SampleCount::Sample4);
+    }
+
+    #[test]
+    fn no_common_sample_count_falls_back_to_one() {
+        let color = SampleCounts {
+            sample2: true,
+            ..SampleCounts::empty()
+        };
+        let depth = SampleCounts {
+            sample8: true,
+            ..SampleCounts::empty()
+        };
+        let sample_counts = framebuffer_sample_counts(color, depth);
+        assert!(sample_counts.is_empty());
+        assert_eq!(max_sample_count(&sample_counts), SampleCount::Sample1);
+    }
+
+    #[test]
+    fn list_topology_resets_primitive_restart() {
+        assert!(keeps_primitive_restart(
+            true,
+            PrimitiveTopology::TriangleStrip
+        ));
+        assert!(!keeps_primitive_restart(
+            true,
+            PrimitiveTopology::TriangleList
+        ));
+        assert!(!keeps_primitive_restart(true, PrimitiveTopology::PointList));
+        // switching back to a strip doesn't turn it on again
+        assert!(!keeps_primitive_restart(
+            false,
+            PrimitiveTopology::LineStrip
+        ));
     }
 }
@@ -0,0 +1,53 @@
+use vulkano::{
+    device::physical::{PhysicalDevice, PhysicalDeviceType},
+    Version,
+};
+
+const VENDOR_NVIDIA: u32 = 0x10de;
+const VENDOR_INTEL: u32 = 0x8086;
+
+/// Identification of the GPU the engine runs on, e.g. for bug reports.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AdapterInfo {
+    pub name: String,
+    pub device_type: PhysicalDeviceType,
+    /// Driver version in the vendor's own numbering.
+    pub driver_version: String,
+    pub vendor_id: u32,
+    pub api_version: Version,
+}
+
+impl AdapterInfo {
+    pub(super) fn new(physical_device: &PhysicalDevice) -> AdapterInfo {
+        let properties = physical_device.properties();
+
+        AdapterInfo {
+            name: properties.device_name.clone(),
+            device_type: properties.device_type,
+            driver_version: decode_driver_version(properties.vendor_id, properties.driver_version),
+            vendor_id: properties.vendor_id,
+            api_version: properties.api_version,
+        }
+    }
+}
+
+// drivers are free to pack their version as they like, only some follow the Vulkan encoding
+fn decode_driver_version(vendor_id: u32, version: u32) -> String {
+    match vendor_id {
+        VENDOR_NVIDIA => format!(
+            "{}.{}.{}.{}",
+            version >> 22,
+            (version >> 14) & 0xff,
+            (version >> 6) & 0xff,
+            version & 0x3f
+        ),
+        // only the Windows driver, Mesa uses the Vulkan encoding
+        VENDOR_INTEL if cfg!(windows) => format!("{}.{}", version >> 14, version & 0x3fff),
+        _ => format!(
+            "{}.{}.{}",
+            version >> 22,
+            (version >> 12) & 0x3ff,
+            version & 0xfff
+        ),
+    }
+}
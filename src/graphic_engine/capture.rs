@@ -0,0 +1,45 @@
+//! Programmatic RenderDoc captures, with the `renderdoc` feature.
+//!
+//! The in-app API is only available when the application is started from RenderDoc (or the
+//! RenderDoc library is otherwise injected): launch it through "Launch Application" in the
+//! RenderDoc UI or with `renderdoccmd capture <executable>`. On Linux the library is
+//! `librenderdoc.so`, on Windows `renderdoc.dll`; RenderDoc does not support macOS. When
+//! RenderDoc is not loaded, captures are silently skipped.
+
+use renderdoc::{RenderDoc, V110};
+
+pub(super) struct FrameCapture {
+    renderdoc: Option<RenderDoc<V110>>,
+    pending: bool,
+    capturing: bool,
+}
+
+impl FrameCapture {
+    pub fn new() -> FrameCapture {
+        FrameCapture {
+            renderdoc: RenderDoc::new().ok(),
+            pending: false,
+            capturing: false,
+        }
+    }
+
+    pub fn trigger(&mut self) {
+        self.pending = self.renderdoc.is_some();
+    }
+
+    pub fn begin_frame(&mut self) {
+        if let (true, Some(renderdoc)) = (self.pending, &mut self.renderdoc) {
+            // null handles capture on whichever device and window is current
+            renderdoc.start_frame_capture(std::ptr::null(), std::ptr::null());
+            self.pending = false;
+            self.capturing = true;
+        }
+    }
+
+    pub fn end_frame(&mut self) {
+        if let (true, Some(renderdoc)) = (self.capturing, &mut self.renderdoc) {
+            renderdoc.end_frame_capture(std::ptr::null(), std::ptr::null());
+            self.capturing = false;
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! Time as seen by rendered frames, advanced once per frame by the wall-clock time since the
+//! previous one, or by a fixed step for reproducible animations and captures.
+
+use std::time::{Duration, Instant};
+
+pub(super) struct FrameClock {
+    fixed_step: Option<Duration>,
+    last_tick: Instant,
+    time: Duration,
+    delta: Duration,
+}
+
+impl FrameClock {
+    pub fn new() -> FrameClock {
+        FrameClock {
+            fixed_step: None,
+            last_tick: Instant::now(),
+            time: Duration::ZERO,
+            delta: Duration::ZERO,
+        }
+    }
+
+    /// Starts a frame.
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        self.delta = self.fixed_step.unwrap_or(now - self.last_tick);
+        self.time += self.delta;
+        self.last_tick = now;
+    }
+
+    // switching back to wall-clock time continues from the current time, without a jump
+    pub fn set_fixed_step(&mut self, step: Option<Duration>) {
+        self.fixed_step = step;
+    }
+
+    pub fn fixed_step(&self) -> Option<Duration> {
+        self.fixed_step
+    }
+
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+}
@@ -0,0 +1,113 @@
+//! Compute post-process writing straight to the swapchain image, after the main pass and before
+//! present, e.g. for tone mapping or color grading without an extra fullscreen draw.
+//!
+//! The swapchain image is bound as a storage image, which `AutoCommandBufferBuilder` transitions
+//! to the `General` layout for the dispatch and back to `PresentSrc` at the end of the command
+//! buffer, like the screenshot copy.
+
+use std::sync::Arc;
+
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, layout::DescriptorType, PersistentDescriptorSet,
+        WriteDescriptorSet,
+    },
+    device::Device,
+    image::{view::ImageView, ImageAccess, SwapchainImage},
+    pipeline::{cache::PipelineCache, ComputePipeline, Pipeline, PipelineBindPoint},
+    shader::{ShaderExecution, ShaderModule},
+};
+
+use super::EngineError;
+
+pub(super) struct ComputePresent {
+    pipeline: Arc<ComputePipeline>,
+    local_size: [u32; 2],
+    descriptor_sets: Vec<Arc<PersistentDescriptorSet>>,
+}
+
+impl ComputePresent {
+    pub fn new(
+        device: &Arc<Device>,
+        shader: &Arc<ShaderModule>,
+        local_size: [u32; 2],
+        pipeline_cache: &Arc<PipelineCache>,
+    ) -> Result<ComputePresent, EngineError> {
+        let entry_point = shader
+            .entry_point("main")
+            .filter(|entry_point| matches!(entry_point.execution(), ShaderExecution::Compute))
+            .ok_or(EngineError::UnsupportedComputeShader)?;
+        let pipeline = ComputePipeline::new(
+            device.clone(),
+            entry_point,
+            &(),
+            Some(pipeline_cache.clone()),
+            |_| {},
+        )
+        .map_err(EngineError::ComputePipeline)?;
+
+        // the only descriptor the engine can write is the swapchain image
+        let set_layouts = pipeline.layout().set_layouts();
+        let binds_target_only = set_layouts.len() == 1
+            && set_layouts[0].bindings().len() == 1
+            && set_layouts[0]
+                .bindings()
+                .get(&0)
+                .is_some_and(|binding| binding.descriptor_type == DescriptorType::StorageImage);
+        if !binds_target_only {
+            return Err(EngineError::UnsupportedComputeShader);
+        }
+
+        Ok(ComputePresent {
+            pipeline,
+            local_size: local_size.map(|size| size.max(1)),
+            descriptor_sets: Vec::new(),
+        })
+    }
+
+    // called whenever the swapchain is rebuilt
+    pub fn set_targets(
+        &mut self,
+        allocator: &StandardDescriptorSetAllocator,
+        image_views: &[Arc<ImageView<SwapchainImage>>],
+    ) {
+        self.descriptor_sets = image_views
+            .iter()
+            .map(|view| {
+                PersistentDescriptorSet::new(
+                    allocator,
+                    self.pipeline.layout().set_layouts()[0].clone(),
+                    [WriteDescriptorSet::image_view(0, view.clone())],
+                )
+                .unwrap()
+            })
+            .collect();
+    }
+
+    /// Records one invocation per pixel of the swapchain image, rounded up to whole workgroups.
+    pub fn dispatch(
+        &self,
+        cmd_buffer_builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        image_index: usize,
+        image: &SwapchainImage,
+    ) {
+        let [width, height] = image.dimensions().width_height();
+        let [local_width, local_height] = self.local_size;
+
+        cmd_buffer_builder
+            .bind_pipeline_compute(self.pipeline.clone())
+            .bind_descriptor_sets(
+                PipelineBindPoint::Compute,
+                self.pipeline.layout().clone(),
+                0,
+                self.descriptor_sets[image_index].clone(),
+            )
+            .dispatch([
+                width.div_ceil(local_width),
+                height.div_ceil(local_height),
+                1,
+            ])
+            .unwrap();
+    }
+}
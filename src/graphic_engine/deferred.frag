@@ -0,0 +1,52 @@
+#version 450
+
+// Deferred lighting: accumulates the point lights over the G-buffer written by the main pass and
+// adds them to the main target.
+
+layout(location = 0) out vec4 color;
+
+layout(set = 0, binding = 0) uniform texture2D albedo;
+layout(set = 0, binding = 1) uniform texture2D normal;
+layout(set = 0, binding = 2) uniform texture2D position;
+layout(set = 0, binding = 3) uniform sampler gbuffer_sampler;
+
+// the frame set's lights, see light.rs
+struct PointLight {
+    vec3 position;
+    float radius;
+    vec3 color;
+};
+
+// left at its default, MAX_POINT_LIGHTS
+layout(constant_id = 0) const uint MAX_LIGHTS = 16;
+
+layout(set = 1, binding = 1) uniform Lights {
+    uint light_count;
+    PointLight lights[MAX_LIGHTS];
+} frame_lights;
+
+void main() {
+    ivec2 texel = ivec2(gl_FragCoord.xy);
+    vec3 n = texelFetch(sampler2D(normal, gbuffer_sampler), texel, 0).xyz;
+    // nothing was drawn here, the normal target is cleared to zero
+    if (dot(n, n) == 0.0) {
+        discard;
+    }
+    n = normalize(n);
+    vec3 p = texelFetch(sampler2D(position, gbuffer_sampler), texel, 0).xyz;
+
+    vec3 light = vec3(0.0);
+    for (uint i = 0; i < min(frame_lights.light_count, MAX_LIGHTS); i++) {
+        PointLight point_light = frame_lights.lights[i];
+        vec3 to_light = point_light.position - p;
+        float distance = length(to_light);
+        // falls off smoothly to zero at the radius
+        float attenuation = clamp(1.0 - distance / point_light.radius, 0.0, 1.0);
+        attenuation *= attenuation;
+        float lambert = max(dot(n, to_light / max(distance, 1e-4)), 0.0);
+        light += point_light.color * lambert * attenuation;
+    }
+
+    vec3 albedo = texelFetch(sampler2D(albedo, gbuffer_sampler), texel, 0).rgb;
+    color = vec4(albedo * light, 0.0);
+}
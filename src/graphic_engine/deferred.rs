@@ -0,0 +1,71 @@
+//! Deferred shading, see `Graphicengine::set_deferred`.
+//!
+//! The main pass writes a G-buffer to color targets 1 to 3, in `GBUFFER_FORMATS`:
+//!
+//! ```glsl
+//! layout(location = 0) out vec4 color;    // unlit or ambient color, kept as it is
+//! layout(location = 1) out vec4 albedo;
+//! layout(location = 2) out vec4 normal;   // world space, left at zero where nothing is lit
+//! layout(location = 3) out vec4 position; // world space
+//! ```
+//!
+//! A fullscreen lighting pass then reads it as sampled textures and adds the point lights of the
+//! frame set, see `light`, to the main target, before any post-process.
+
+use std::sync::Arc;
+
+use vulkano::{
+    descriptor_set::{layout::DescriptorSetLayout, WriteDescriptorSet},
+    device::Device,
+    format::Format,
+    image::{view::ImageView, AttachmentImage},
+    pipeline::cache::PipelineCache,
+};
+
+use super::fullscreen::FullscreenPass;
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/graphic_engine/deferred.frag",
+    }
+}
+
+/// Formats of the albedo, normal and position targets of the G-buffer.
+pub const GBUFFER_FORMATS: [Format; 3] = [
+    Format::R8G8B8A8_SRGB,
+    Format::R16G16B16A16_SFLOAT,
+    Format::R32G32B32A32_SFLOAT,
+];
+
+/// The lighting pass, adding to main targets of `format` what the lights of the frame set, bound
+/// at `FRAME_SET`, contribute over the G-buffer.
+pub(super) fn lighting_pass(
+    device: &Arc<Device>,
+    format: Format,
+    frame_set_layout: &DescriptorSetLayout,
+    pipeline_cache: &Arc<PipelineCache>,
+) -> FullscreenPass {
+    let fs = fs::load(device.clone()).unwrap();
+    FullscreenPass::new(
+        device,
+        format,
+        fs.entry_point("main").unwrap(),
+        true,
+        Some(frame_set_layout),
+        pipeline_cache,
+    )
+}
+
+// set 0 of the lighting pass, the sampler only fetches texel by texel
+pub(super) fn gbuffer_writes(
+    pass: &FullscreenPass,
+    gbuffer: &[Arc<ImageView<AttachmentImage>>],
+) -> [WriteDescriptorSet; 4] {
+    [
+        WriteDescriptorSet::image_view(0, gbuffer[0].clone()),
+        WriteDescriptorSet::image_view(1, gbuffer[1].clone()),
+        WriteDescriptorSet::image_view(2, gbuffer[2].clone()),
+        WriteDescriptorSet::sampler(3, pass.sampler().clone()),
+    ]
+}
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use bytemuck::Pod;
+use vulkano::{
+    buffer::{BufferSlice, BufferUsage, CpuAccessibleBuffer, TypedBufferAccess},
+    memory::allocator::StandardMemoryAllocator,
+};
+
+use super::EngineError;
+
+/// The vertices written by `DynamicVertexBuffer::write`, to bind for this frame.
+pub type DynamicVertexSlice<T> = Arc<BufferSlice<[T], CpuAccessibleBuffer<[T]>>>;
+
+/// Host-visible vertex buffers rewritten every frame, one per frame in flight.
+///
+/// Each `write` goes to the next buffer of the ring, so the CPU never touches a buffer the GPU
+/// may still be reading from a previous frame. Call it once per frame.
+///
+/// `T` is any plain-data vertex type, matching the vertex input of the pipelines drawing it. For
+/// layouts only known at runtime, use `u8` and write the interleaved bytes, with the pipeline's
+/// `VertexInputState` built from the same description.
+pub struct DynamicVertexBuffer<T: Pod + Send + Sync> {
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    buffers: Vec<Arc<CpuAccessibleBuffer<[T]>>>,
+    capacity: usize,
+    next: usize,
+}
+
+impl<T: Pod + Send + Sync> DynamicVertexBuffer<T> {
+    pub(super) fn new(
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        frames_in_flight: usize,
+        capacity: usize,
+    ) -> Result<DynamicVertexBuffer<T>, EngineError> {
+        let capacity = capacity.max(1);
+        let buffers = (0..frames_in_flight.max(1))
+            .map(|_| DynamicVertexBuffer::allocate(&memory_allocator, capacity))
+            .collect::<Result<_, _>>()?;
+
+        Ok(DynamicVertexBuffer {
+            memory_allocator,
+            buffers,
+            capacity,
+            next: 0,
+        })
+    }
+
+    /// Number of vertices each buffer of the ring can hold.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Copies `vertices` into the next buffer of the ring, doubling the capacity if needed, and
+    /// returns the written range to bind. Fails if a grown buffer can't be allocated, leaving the
+    /// ring as it was.
+    pub fn write(&mut self, vertices: &[T]) -> Result<DynamicVertexSlice<T>, EngineError> {
+        let mut capacity = self.capacity;
+        while capacity < vertices.len() {
+            capacity *= 2;
+        }
+
+        let slot = self.next;
+        // buffers are grown lazily when their slot comes up, and replaced if the GPU still
+        // holds them
+        let writable =
+            self.buffers[slot].len() >= capacity as u64 && self.buffers[slot].write().is_ok();
+        if !writable {
+            self.buffers[slot] = DynamicVertexBuffer::allocate(&self.memory_allocator, capacity)?;
+        }
+        self.capacity = capacity;
+        self.next = (self.next + 1) % self.buffers.len();
+        self.buffers[slot].write().unwrap()[..vertices.len()].copy_from_slice(vertices);
+
+        Ok(
+            BufferSlice::from_typed_buffer_access(self.buffers[slot].clone())
+                .slice(0..vertices.len() as u64)
+                .unwrap(),
+        )
+    }
+
+    fn allocate(
+        memory_allocator: &StandardMemoryAllocator,
+        capacity: usize,
+    ) -> Result<Arc<CpuAccessibleBuffer<[T]>>, EngineError> {
+        CpuAccessibleBuffer::from_iter(
+            memory_allocator,
+            BufferUsage {
+                vertex_buffer: true,
+                ..BufferUsage::empty()
+            },
+            false,
+            (0..capacity).map(|_| T::zeroed()),
+        )
+        .map_err(EngineError::allocation(
+            (capacity * std::mem::size_of::<T>()) as u64,
+        ))
+    }
+}
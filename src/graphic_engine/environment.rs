@@ -0,0 +1,136 @@
+//! Environment cubemap for reflections, bound with the frame uniforms at set `FRAME_SET`,
+//! binding 2, together with the `reflectivity` of the frame uniforms:
+//!
+//! ```glsl
+//! layout(set = 1, binding = 2) uniform samplerCube environment;
+//!
+//! // view_dir points from the camera to the fragment, both in world space
+//! vec3 reflected = reflect(normalize(view_dir), normalize(normal));
+//! color.rgb = mix(color.rgb, texture(environment, reflected).rgb, reflectivity);
+//! ```
+//!
+//! The cubemap is sampled directly: this is a mirror reflection term, not image-based lighting.
+//! Nothing is prefiltered, so there is no diffuse irradiance and rough surfaces reflect as
+//! sharply as polished ones. Without an environment map, a black cubemap is bound.
+
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::{BufferUsage, CpuAccessibleBuffer},
+    command_buffer::{
+        AutoCommandBufferBuilder, BufferImageCopy, CopyBufferToImageInfo, PrimaryAutoCommandBuffer,
+    },
+    descriptor_set::WriteDescriptorSet,
+    device::DeviceOwned,
+    format::Format,
+    image::{
+        immutable::ImmutableImageCreationError,
+        view::{ImageView, ImageViewCreateInfo, ImageViewType},
+        ImageCreateFlags, ImageDimensions, ImageLayout, ImageSubresourceLayers, ImageUsage,
+        ImageViewAbstract, ImmutableImage,
+    },
+    memory::allocator::StandardMemoryAllocator,
+    sampler::Sampler,
+};
+
+pub(super) const CUBEMAP_FACES: usize = 6;
+
+pub(super) struct Environment {
+    // bound when no map is set, the descriptor can't be left empty
+    black: Arc<dyn ImageViewAbstract>,
+    map: Option<Arc<dyn ImageViewAbstract>>,
+    sampler: Arc<Sampler>,
+}
+
+impl Environment {
+    pub fn new(black: Arc<dyn ImageViewAbstract>, sampler: Arc<Sampler>) -> Environment {
+        Environment {
+            black,
+            map: None,
+            sampler,
+        }
+    }
+
+    pub fn set_map(&mut self, map: Option<Arc<dyn ImageViewAbstract>>) {
+        self.map = map;
+    }
+
+    pub fn map(&self) -> Option<&Arc<dyn ImageViewAbstract>> {
+        self.map.as_ref()
+    }
+
+    pub fn write(&self, binding: u32) -> WriteDescriptorSet {
+        let view = self.map.as_ref().unwrap_or(&self.black);
+        WriteDescriptorSet::image_view_sampler(binding, view.clone(), self.sampler.clone())
+    }
+}
+
+// `texels` holds the faces one after the other, each `size` texels square
+pub(super) fn upload_cubemap(
+    memory_allocator: &StandardMemoryAllocator,
+    texels: Vec<u8>,
+    size: u32,
+    format: Format,
+    cmd_buffer_builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+) -> Result<Arc<ImageView<ImmutableImage>>, ImmutableImageCreationError> {
+    let source = CpuAccessibleBuffer::from_iter(
+        memory_allocator,
+        BufferUsage {
+            transfer_src: true,
+            ..BufferUsage::empty()
+        },
+        false,
+        texels,
+    )?;
+
+    // ImmutableImage::from_iter can't make the cube compatible images cube views need
+    let dimensions = ImageDimensions::Dim2d {
+        width: size,
+        height: size,
+        array_layers: CUBEMAP_FACES as u32,
+    };
+    let (image, initialization) = ImmutableImage::uninitialized(
+        memory_allocator,
+        dimensions,
+        format,
+        1,
+        ImageUsage {
+            transfer_dst: true,
+            sampled: true,
+            ..ImageUsage::empty()
+        },
+        ImageCreateFlags {
+            cube_compatible: true,
+            ..ImageCreateFlags::empty()
+        },
+        ImageLayout::ShaderReadOnlyOptimal,
+        memory_allocator
+            .device()
+            .active_queue_family_indices()
+            .iter()
+            .copied(),
+    )?;
+    cmd_buffer_builder
+        .copy_buffer_to_image(CopyBufferToImageInfo {
+            regions: [BufferImageCopy {
+                image_subresource: ImageSubresourceLayers::from_parameters(
+                    format,
+                    CUBEMAP_FACES as u32,
+                ),
+                image_extent: dimensions.width_height_depth(),
+                ..Default::default()
+            }]
+            .into(),
+            ..CopyBufferToImageInfo::buffer_image(source, initialization)
+        })
+        .unwrap();
+
+    Ok(ImageView::new(
+        image.clone(),
+        ImageViewCreateInfo {
+            view_type: ImageViewType::Cube,
+            ..ImageViewCreateInfo::from_image(&image)
+        },
+    )
+    .unwrap())
+}
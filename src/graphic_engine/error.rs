@@ -0,0 +1,349 @@
+use std::{error::Error, fmt, io};
+
+use super::MainAttachment;
+use vulkano::{
+    command_buffer::PipelineExecutionError,
+    descriptor_set::layout::DescriptorType,
+    device::{physical::PhysicalDeviceError, DeviceCreationError},
+    format::{ClearValue, Format},
+    image::{immutable::ImmutableImageCreationError, view::ImageViewType, ImageError, SampleCount},
+    instance::{InstanceCreationError, InstanceExtensions},
+    memory::allocator::AllocationCreationError,
+    pipeline::{
+        compute::ComputePipelineCreationError, graphics::input_assembly::PrimitiveTopology,
+    },
+    query::QueryPoolCreationError,
+    shader::ShaderCreationError,
+    swapchain::{AcquireError, ColorSpace, CompositeAlpha, PresentMode, SwapchainCreationError},
+    sync::FlushError,
+    LoadingError, OomError,
+};
+
+#[derive(Debug)]
+pub enum EngineError {
+    /// The Vulkan library couldn't be loaded, e.g. no driver is installed.
+    Loading(LoadingError),
+    InstanceCreation(InstanceCreationError),
+    /// The window or its surface couldn't be created.
+    WindowCreation(vulkano_win::CreationError),
+    OutOfMemory(OomError),
+    /// Allocating a buffer or image failed, usually for lack of device memory. `size` is the
+    /// requested size in bytes, or the size of the uploaded data for images.
+    AllocationFailed {
+        size: u64,
+        error: AllocationCreationError,
+    },
+    DeviceCreationFailed(DeviceCreationError),
+    /// Creating an image failed for another reason than a lack of memory.
+    ImageCreation(ImageError),
+    ImmutableImageCreation(ImmutableImageCreationError),
+    QueryPoolCreation(QueryPoolCreationError),
+    /// The swapchain no longer matches the surface and no frame was presented.
+    SwapchainOutOfDate,
+    /// Acquiring a swapchain image failed for another reason than an out of date swapchain, e.g.
+    /// a lost surface or device.
+    Acquire(AcquireError),
+    SwapchainRecreation(SwapchainCreationError),
+    SurfaceQuery(PhysicalDeviceError),
+    Flush(FlushError),
+    UnsupportedInstanceExtensions(InstanceExtensions),
+    UnsupportedSurfaceFormat(Format, ColorSpace),
+    UnsupportedColorSpace(ColorSpace),
+    UnsupportedPresentMode(PresentMode),
+    UnsupportedCompositeAlpha(CompositeAlpha),
+    UnsupportedSampleCount(SampleCount),
+    UnsupportedColorTargetFormat(Format),
+    TooManyColorTargets(u32),
+    NoColorTarget(usize),
+    /// The clear value's type doesn't match the numeric type of the attachment format.
+    ClearValueMismatch(Format, ClearValue),
+    /// The attachment is loaded by the next frame, so it can't be discarded.
+    StoreOpRequired(MainAttachment),
+    /// Extra color targets and multisampling can't be combined.
+    MultisampledColorTargets,
+    /// The color targets are the G-buffer while deferred shading is enabled, see
+    /// `Graphicengine::set_deferred`.
+    DeferredColorTargets,
+    NotDepthFormat(Format),
+    /// The main pass has no depth attachment to clear, see `Graphicengine::set_depth_format`.
+    NoDepthAttachment,
+    /// The main pass depth attachment is read-only, see `DepthMode::ReadOnly`.
+    DepthReadOnly,
+    UnsupportedDepthFormat(Format),
+    /// None of the formats given to `set_depth_format_from` is supported.
+    NoSupportedDepthFormat(Vec<Format>),
+    /// The bindless material array is full, at this many materials.
+    TooManyMaterials(u32),
+    TimestampsUnsupported,
+    /// The device doesn't support the `sample_rate_shading` feature.
+    SampleShadingUnsupported,
+    /// The swapchain images can't be blitted to from the main target, see
+    /// `Graphicengine::set_render_scale`.
+    RenderScaleUnsupported,
+    UnsupportedTopology(PrimitiveTopology),
+    /// The minimum window size is larger than the maximum one along some axis.
+    WindowSizeLimits {
+        min: [u32; 2],
+        max: [u32; 2],
+    },
+    /// The view at this index of `render_into` can't be rendered into, see its requirements.
+    UnsupportedOverlayTarget(usize),
+    /// The view at `index` of `render_into` isn't the extent of the first one.
+    OverlayTargetExtent {
+        index: usize,
+        expected: [u32; 2],
+        actual: [u32; 2],
+    },
+    /// Environment maps must be cube views.
+    NotACubemap(ImageViewType),
+    /// Primitive restart only applies to strip and fan topologies.
+    PrimitiveRestartUnsupported(PrimitiveTopology),
+    PipelineCacheWrite(io::Error),
+    UnknownShader(String),
+    /// The pipeline layout has no binding of this type at this set and binding.
+    NoDescriptorBinding {
+        set: u32,
+        binding: u32,
+        descriptor_type: DescriptorType,
+    },
+    /// The buffer wasn't created with the usage this descriptor type needs.
+    BufferUsageMismatch(DescriptorType),
+    Shader(ShaderCreationError),
+    /// The shader has no `main` compute entry point, or binds more than a storage image at set 0,
+    /// binding 0, see `Graphicengine::set_compute_post_process`.
+    UnsupportedComputeShader,
+    ComputePipeline(ComputePipelineCreationError),
+    /// Swapchain images of this format or surface can't be storage images.
+    StorageSwapchainUnsupported(Format),
+    TextureNotTransferDst,
+    /// Regions of block-compressed textures can't be updated texel by texel.
+    CompressedTextureRegion(Format),
+    TextureRegionOutOfBounds {
+        origin: [u32; 3],
+        extent: [u32; 3],
+        dimensions: [u32; 3],
+    },
+    TextureDataSize {
+        expected: usize,
+        actual: usize,
+    },
+    NoMorphKeyframes,
+    /// A keyframe of `create_morph_targets` has a different vertex count than the first one.
+    MorphVertexCount {
+        expected: usize,
+        actual: usize,
+    },
+    IndirectBufferUsage,
+    /// The indirect draw doesn't match the pipeline, its bound buffers or the device features.
+    IndirectDraw(PipelineExecutionError),
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EngineError::Loading(e) => write!(f, "Failed to load the Vulkan library: {}", e),
+            EngineError::InstanceCreation(e) => write!(f, "Failed to create instance: {}", e),
+            EngineError::WindowCreation(e) => write!(f, "Failed to create window: {}", e),
+            EngineError::OutOfMemory(e) => write!(f, "{}", e),
+            EngineError::AllocationFailed { size, error } => {
+                write!(f, "Failed to allocate {} bytes: {}", size, error)
+            }
+            EngineError::DeviceCreationFailed(e) => write!(f, "Failed to create device: {}", e),
+            EngineError::ImageCreation(e) => write!(f, "Failed to create image: {}", e),
+            EngineError::ImmutableImageCreation(e) => write!(f, "Failed to create image: {}", e),
+            EngineError::QueryPoolCreation(e) => write!(f, "Failed to create query pool: {}", e),
+            EngineError::SwapchainOutOfDate => write!(f, "Swapchain is out of date"),
+            EngineError::Acquire(e) => write!(f, "Failed to acquire next image: {}", e),
+            EngineError::SwapchainRecreation(e) => write!(f, "Failed to recreate swapchain: {}", e),
+            EngineError::SurfaceQuery(e) => write!(f, "Failed to query the surface: {}", e),
+            EngineError::Flush(e) => write!(f, "Failed to flush future: {}", e),
+            EngineError::UnsupportedInstanceExtensions(extensions) => {
+                write!(f, "Instance extensions not available: {:?}", extensions)
+            }
+            EngineError::UnsupportedSurfaceFormat(format, color_space) => write!(
+                f,
+                "Surface format {:?} in color space {:?} is not supported",
+                format, color_space
+            ),
+            EngineError::UnsupportedColorSpace(color_space) => {
+                write!(f, "Color space {:?} is not supported", color_space)
+            }
+            EngineError::UnsupportedPresentMode(present_mode) => {
+                write!(f, "Present mode {:?} is not supported", present_mode)
+            }
+            EngineError::UnsupportedCompositeAlpha(composite_alpha) => {
+                write!(f, "Composite alpha {:?} is not supported", composite_alpha)
+            }
+            EngineError::UnsupportedSampleCount(sample_count) => {
+                write!(f, "Sample count {:?} is not supported", sample_count)
+            }
+            EngineError::UnsupportedColorTargetFormat(format) => {
+                write!(f, "{:?} can't be rendered to and sampled", format)
+            }
+            EngineError::TooManyColorTargets(max) => {
+                write!(f, "No more than {} color attachments are supported", max)
+            }
+            EngineError::NoColorTarget(index) => write!(f, "No color target at index {}", index),
+            EngineError::ClearValueMismatch(format, clear_value) => {
+                write!(f, "{:?} can't be cleared to {:?}", format, clear_value)
+            }
+            EngineError::StoreOpRequired(attachment) => {
+                write!(
+                    f,
+                    "{:?} is loaded by the next frame and must be stored",
+                    attachment
+                )
+            }
+            EngineError::MultisampledColorTargets => {
+                write!(f, "Extra color targets require a sample count of 1")
+            }
+            EngineError::DeferredColorTargets => {
+                write!(f, "The color targets are the G-buffer of deferred shading")
+            }
+            EngineError::NoDepthAttachment => write!(f, "The main pass has no depth attachment"),
+            EngineError::DepthReadOnly => write!(f, "The main pass depth attachment is read-only"),
+            EngineError::NotDepthFormat(format) => {
+                write!(f, "{:?} is not a depth format", format)
+            }
+            EngineError::TooManyMaterials(capacity) => {
+                write!(f, "No more than {} materials are supported", capacity)
+            }
+            EngineError::TimestampsUnsupported => {
+                write!(f, "The graphics queue doesn't support timestamp queries")
+            }
+            EngineError::SampleShadingUnsupported => {
+                write!(f, "The device doesn't support sample shading")
+            }
+            EngineError::RenderScaleUnsupported => {
+                write!(f, "Swapchain images can't be blitted to at another scale")
+            }
+            EngineError::WindowSizeLimits { min, max } => write!(
+                f,
+                "Minimum window size {}x{} exceeds the maximum size {}x{}",
+                min[0], min[1], max[0], max[1]
+            ),
+            EngineError::UnsupportedOverlayTarget(index) => {
+                write!(
+                    f,
+                    "Overlay target {} can't be used as a color attachment",
+                    index
+                )
+            }
+            EngineError::OverlayTargetExtent {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Overlay target {} is {}x{}, not {}x{} like the first one",
+                index, actual[0], actual[1], expected[0], expected[1]
+            ),
+            EngineError::NotACubemap(view_type) => {
+                write!(f, "Expected a cube view, not {:?}", view_type)
+            }
+            EngineError::UnsupportedTopology(topology) => {
+                write!(f, "Primitive topology {:?} is not supported", topology)
+            }
+            EngineError::PrimitiveRestartUnsupported(topology) => {
+                write!(f, "Primitive restart is not supported with {:?}", topology)
+            }
+            EngineError::UnsupportedDepthFormat(format) => {
+                write!(f, "{:?} can't be used as a depth attachment", format)
+            }
+            EngineError::NoSupportedDepthFormat(formats) => {
+                write!(f, "None of {:?} can be used as a depth attachment", formats)
+            }
+            EngineError::PipelineCacheWrite(e) => write!(f, "Failed to save pipeline cache: {}", e),
+            EngineError::UnknownShader(name) => write!(f, "No embedded shader named {}", name),
+            EngineError::NoDescriptorBinding {
+                set,
+                binding,
+                descriptor_type,
+            } => write!(
+                f,
+                "No {:?} binding at set {}, binding {} of the pipeline layout",
+                descriptor_type, set, binding
+            ),
+            EngineError::BufferUsageMismatch(descriptor_type) => write!(
+                f,
+                "Buffer can't be bound as {:?}, it lacks the usage",
+                descriptor_type
+            ),
+            EngineError::Shader(e) => write!(f, "Failed to create shader module: {}", e),
+            EngineError::UnsupportedComputeShader => write!(
+                f,
+                "Compute post-process shaders need a main entry point writing only a storage \
+                 image at set 0, binding 0"
+            ),
+            EngineError::ComputePipeline(e) => {
+                write!(f, "Failed to create compute pipeline: {}", e)
+            }
+            EngineError::StorageSwapchainUnsupported(format) => write!(
+                f,
+                "{:?} swapchain images can't be written by compute shaders",
+                format
+            ),
+            EngineError::TextureNotTransferDst => {
+                write!(f, "Texture was not created with the transfer_dst usage")
+            }
+            EngineError::CompressedTextureRegion(format) => {
+                write!(f, "Regions of {:?} textures can't be updated", format)
+            }
+            EngineError::TextureRegionOutOfBounds {
+                origin,
+                extent,
+                dimensions,
+            } => write!(
+                f,
+                "Texture region at {:?} of extent {:?} is outside of the {:?} image",
+                origin, extent, dimensions
+            ),
+            EngineError::TextureDataSize { expected, actual } => write!(
+                f,
+                "Texture region needs {} bytes of data, got {}",
+                expected, actual
+            ),
+            EngineError::NoMorphKeyframes => write!(f, "Morph targets need at least one keyframe"),
+            EngineError::MorphVertexCount { expected, actual } => write!(
+                f,
+                "Morph keyframe has {} vertices, expected {}",
+                actual, expected
+            ),
+            EngineError::IndirectBufferUsage => write!(
+                f,
+                "Indirect commands buffer was not created with the indirect_buffer usage"
+            ),
+            EngineError::IndirectDraw(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for EngineError {}
+
+impl EngineError {
+    pub(super) fn allocation(size: u64) -> impl FnOnce(AllocationCreationError) -> EngineError {
+        move |error| EngineError::AllocationFailed { size, error }
+    }
+
+    pub(super) fn image_allocation(size: u64) -> impl FnOnce(ImageError) -> EngineError {
+        move |error| match error {
+            ImageError::AllocError(error) => EngineError::AllocationFailed { size, error },
+            error => EngineError::ImageCreation(error),
+        }
+    }
+
+    pub(super) fn immutable_image_allocation(
+        size: u64,
+    ) -> impl FnOnce(ImmutableImageCreationError) -> EngineError {
+        move |error| match error {
+            ImmutableImageCreationError::AllocError(error)
+            | ImmutableImageCreationError::ImageCreationError(ImageError::AllocError(error)) => {
+                EngineError::AllocationFailed { size, error }
+            }
+            ImmutableImageCreationError::ImageCreationError(error) => {
+                EngineError::ImageCreation(error)
+            }
+            error => EngineError::ImmutableImageCreation(error),
+        }
+    }
+}
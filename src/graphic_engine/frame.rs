@@ -0,0 +1,209 @@
+//! Uniforms the engine updates every frame, for animated shaders.
+//!
+//! They are bound at set `FRAME_SET`, binding 0, and match the GLSL block below. Binding 1 holds
+//! the point lights, see `light`, and binding 2 the environment cubemap, see `environment`.
+//!
+//! ```glsl
+//! layout(set = 1, binding = 0) uniform Frame {
+//!     vec2 resolution;
+//!     float time;
+//!     uint debug_mode;
+//!     float reflectivity;
+//! };
+//! ```
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use super::{
+    environment::Environment,
+    light::{LightUniforms, PointLight},
+};
+use bytemuck::{Pod, Zeroable};
+use vulkano::{
+    buffer::CpuBufferPool,
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator,
+        layout::{
+            DescriptorSetLayout, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo,
+            DescriptorType,
+        },
+        PersistentDescriptorSet, WriteDescriptorSet,
+    },
+    device::Device,
+    memory::allocator::StandardMemoryAllocator,
+    shader::ShaderStages,
+};
+
+/// Descriptor set index reserved for the frame uniforms, after the material set.
+pub const FRAME_SET: u32 = 1;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameUniforms {
+    /// Extent of the main pass target, in pixels. See `Graphicengine::render_extent`.
+    pub resolution: [f32; 2],
+    /// Frame clock time in seconds, see `Graphicengine::set_fixed_frame_time`.
+    pub time: f32,
+    /// `DebugMode` shaders should output, as its `u32` value.
+    pub debug_mode: u32,
+    /// How much the environment map shows in reflections, between 0 and 1. See
+    /// `Graphicengine::set_reflectivity`.
+    pub reflectivity: f32,
+}
+
+// plain 4-byte fields with no implicit padding
+unsafe impl Zeroable for FrameUniforms {}
+unsafe impl Pod for FrameUniforms {}
+
+impl FrameUniforms {
+    pub fn new(
+        resolution: [u32; 2],
+        time: f32,
+        debug_mode: DebugMode,
+        reflectivity: f32,
+    ) -> FrameUniforms {
+        FrameUniforms {
+            resolution: [resolution[0] as f32, resolution[1] as f32],
+            time,
+            debug_mode: debug_mode as u32,
+            reflectivity,
+        }
+    }
+}
+
+/// Debug visualization fragment shaders switch to, from `debug_mode` in the frame uniforms.
+///
+/// The engine only forwards the mode, each shader implements the ones it can, e.g.
+///
+/// ```glsl
+/// if (debug_mode == 1) {
+///     // MipLevels: red at level 0 to blue at level 8 and above
+///     float lod = textureQueryLod(sampler2D(tex, samp), uv).x;
+///     color = vec4(mix(vec3(1.0, 0.0, 0.0), vec3(0.0, 0.0, 1.0), clamp(lod / 8.0, 0.0, 1.0)), 1.0);
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DebugMode {
+    /// Regular shading.
+    #[default]
+    None = 0,
+    /// A color per sampled mip level.
+    MipLevels = 1,
+    /// World-space normals mapped from `-1.0..1.0` to colors.
+    Normals = 2,
+    /// Texture coordinates as red and green.
+    Uvs = 3,
+    /// A constant dim color per fragment, added up with additive blending to show overdraw.
+    /// Pipelines have to enable the blending themselves.
+    Overdraw = 4,
+}
+
+/// What passes get to record a frame.
+pub struct Frame {
+    pub uniforms: FrameUniforms,
+    /// The frame uniforms, to bind at `FRAME_SET`.
+    pub descriptor_set: Arc<PersistentDescriptorSet>,
+}
+
+pub(super) struct FrameUniformPool {
+    layout: Arc<DescriptorSetLayout>,
+    pool: CpuBufferPool<FrameUniforms>,
+    light_pool: CpuBufferPool<LightUniforms>,
+}
+
+impl FrameUniformPool {
+    pub fn new(
+        device: &Arc<Device>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+    ) -> FrameUniformPool {
+        let layout = DescriptorSetLayout::new(
+            device.clone(),
+            DescriptorSetLayoutCreateInfo {
+                bindings: BTreeMap::from([
+                    (
+                        0,
+                        DescriptorSetLayoutBinding {
+                            stages: ShaderStages {
+                                vertex: true,
+                                fragment: true,
+                                ..ShaderStages::empty()
+                            },
+                            ..DescriptorSetLayoutBinding::descriptor_type(
+                                DescriptorType::UniformBuffer,
+                            )
+                        },
+                    ),
+                    (
+                        1,
+                        DescriptorSetLayoutBinding {
+                            stages: ShaderStages {
+                                fragment: true,
+                                ..ShaderStages::empty()
+                            },
+                            ..DescriptorSetLayoutBinding::descriptor_type(
+                                DescriptorType::UniformBuffer,
+                            )
+                        },
+                    ),
+                    (
+                        2,
+                        DescriptorSetLayoutBinding {
+                            stages: ShaderStages {
+                                fragment: true,
+                                ..ShaderStages::empty()
+                            },
+                            ..DescriptorSetLayoutBinding::descriptor_type(
+                                DescriptorType::CombinedImageSampler,
+                            )
+                        },
+                    ),
+                ]),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        FrameUniformPool {
+            layout,
+            pool: CpuBufferPool::uniform_buffer(memory_allocator.clone()),
+            light_pool: CpuBufferPool::uniform_buffer(memory_allocator),
+        }
+    }
+
+    pub fn layout(&self) -> Arc<DescriptorSetLayout> {
+        self.layout.clone()
+    }
+
+    // the pool hands out a fresh subbuffer each frame, reclaimed once the GPU is done with it.
+    // Still panics when out of memory: it runs after acquire, and a frame bailing out there
+    // would leave the image acquired, which vulkano can't release without presenting it.
+    pub fn next(
+        &self,
+        allocator: &StandardDescriptorSetAllocator,
+        uniforms: FrameUniforms,
+        lights: &[PointLight],
+        environment: &Environment,
+    ) -> Frame {
+        let buffer = self.pool.from_data(uniforms).unwrap();
+        let light_buffer = self
+            .light_pool
+            .from_data(LightUniforms::new(lights))
+            .unwrap();
+        let descriptor_set = PersistentDescriptorSet::new(
+            allocator,
+            self.layout.clone(),
+            [
+                WriteDescriptorSet::buffer(0, buffer),
+                WriteDescriptorSet::buffer(1, light_buffer),
+                environment.write(2),
+            ],
+        )
+        .unwrap();
+
+        Frame {
+            uniforms,
+            descriptor_set,
+        }
+    }
+}
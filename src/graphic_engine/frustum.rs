@@ -0,0 +1,121 @@
+//! View frustum tests on the CPU, to skip drawing what the camera can't see.
+
+use super::projection::Matrix4;
+
+/// Axis-aligned bounding box, in the space of the matrix the frustum is built from, usually world
+/// space.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Aabb {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+/// The six planes bounding what a view projection matrix maps into Vulkan clip space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    // (normal, distance) pointing inwards: inside where dot(normal, p) + distance >= 0
+    planes: [[f32; 4]; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from `view_proj`, e.g. `Graphicengine::view_proj`. Works with reverse
+    /// Z, which swaps the near and far planes.
+    pub fn from_matrix(view_proj: &Matrix4) -> Frustum {
+        let row = |i: usize| {
+            [
+                view_proj[0][i],
+                view_proj[1][i],
+                view_proj[2][i],
+                view_proj[3][i],
+            ]
+        };
+        let [x, y, z, w] = [row(0), row(1), row(2), row(3)];
+        let add = |a: [f32; 4], b: [f32; 4]| [a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3]];
+        let sub = |a: [f32; 4], b: [f32; 4]| [a[0] - b[0], a[1] - b[1], a[2] - b[2], a[3] - b[3]];
+
+        // -w <= x, y <= w and, with Vulkan depth, 0 <= z <= w
+        let planes = [add(w, x), sub(w, x), add(w, y), sub(w, y), z, sub(w, z)].map(|plane| {
+            let length = (plane[0] * plane[0] + plane[1] * plane[1] + plane[2] * plane[2]).sqrt();
+            if length > 0.0 {
+                plane.map(|c| c / length)
+            } else {
+                plane
+            }
+        });
+        Frustum { planes }
+    }
+
+    /// Whether some of `aabb` may be inside the frustum. Conservative: boxes near a frustum
+    /// corner can pass while being outside.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // the corner furthest along the plane normal
+            let corner = [0, 1, 2].map(|i| {
+                if plane[i] >= 0.0 {
+                    aabb.max[i]
+                } else {
+                    aabb.min[i]
+                }
+            });
+            plane[0] * corner[0] + plane[1] * corner[1] + plane[2] * corner[2] + plane[3] >= 0.0
+        })
+    }
+
+    /// Whether `point` is inside the frustum or on its boundary.
+    pub fn contains_point(&self, point: [f32; 3]) -> bool {
+        self.intersects_aabb(&Aabb {
+            min: point,
+            max: point,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::projection::Projection;
+    use super::*;
+
+    // a unit cube centered at `center`
+    fn cube(center: [f32; 3]) -> Aabb {
+        Aabb {
+            min: center.map(|c| c - 0.5),
+            max: center.map(|c| c + 0.5),
+        }
+    }
+
+    fn frustums() -> [Frustum; 2] {
+        let projection = Projection::default();
+        [false, true].map(|reverse_z| {
+            Frustum::from_matrix(
+                &Projection {
+                    reverse_z,
+                    ..projection
+                }
+                .matrix(1.0),
+            )
+        })
+    }
+
+    #[test]
+    fn boxes_in_view_intersect() {
+        // the camera looks down -z from the origin
+        for frustum in frustums() {
+            assert!(frustum.intersects_aabb(&cube([0.0, 0.0, -5.0])));
+            // across the near and far planes
+            assert!(frustum.intersects_aabb(&cube([0.0, 0.0, 0.0])));
+            assert!(frustum.intersects_aabb(&cube([0.0, 0.0, -100.0])));
+            // partly inside the left side
+            assert!(frustum.intersects_aabb(&cube([-2.5, 0.0, -5.0])));
+        }
+    }
+
+    #[test]
+    fn boxes_out_of_view_are_culled() {
+        for frustum in frustums() {
+            assert!(!frustum.intersects_aabb(&cube([0.0, 0.0, 5.0])));
+            assert!(!frustum.intersects_aabb(&cube([0.0, 0.0, -101.0])));
+            assert!(!frustum.intersects_aabb(&cube([10.0, 0.0, -5.0])));
+            assert!(!frustum.intersects_aabb(&cube([0.0, -10.0, -5.0])));
+        }
+    }
+}
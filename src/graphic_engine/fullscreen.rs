@@ -0,0 +1,217 @@
+//! Passes drawing a single fullscreen triangle into one color target: the post-processes and the
+//! deferred lighting. Each one only brings its fragment shader and descriptor writes, the vertex
+//! shader is `shaders/fullscreen.vert`.
+
+use std::sync::Arc;
+
+use vulkano::{
+    command_buffer::{
+        AutoCommandBufferBuilder, PrimaryAutoCommandBuffer, RenderPassBeginInfo, SubpassContents,
+    },
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator,
+        layout::{DescriptorSetLayout, DescriptorSetLayoutCreateInfo},
+        PersistentDescriptorSet, WriteDescriptorSet,
+    },
+    device::Device,
+    format::Format,
+    image::{ImageLayout, ImageViewAbstract},
+    pipeline::{
+        cache::PipelineCache,
+        graphics::{
+            color_blend::ColorBlendState,
+            input_assembly::InputAssemblyState,
+            vertex_input::VertexInputState,
+            viewport::{Viewport, ViewportState},
+        },
+        GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+    },
+    render_pass::{
+        AttachmentDescription, AttachmentReference, Framebuffer, FramebufferCreateInfo, LoadOp,
+        RenderPass, RenderPassCreateInfo, StoreOp, Subpass, SubpassDescription,
+    },
+    sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo},
+    shader::EntryPoint,
+};
+
+use super::{frame::FRAME_SET, shaders};
+
+pub(super) struct FullscreenPass {
+    render_pass: Arc<RenderPass>,
+    pipeline: Arc<GraphicsPipeline>,
+    sampler: Arc<Sampler>,
+    descriptor_set: Option<Arc<PersistentDescriptorSet>>,
+    framebuffers: Vec<Arc<Framebuffer>>,
+}
+
+impl FullscreenPass {
+    /// A pass running `fs` over targets of `format`, which overwrites every pixel, or adds to
+    /// them when `additive`. With `frame_set_layout`, the whole frame set can be bound at
+    /// `FRAME_SET` even if `fs` only reads part of it.
+    pub fn new(
+        device: &Arc<Device>,
+        format: Format,
+        fs: EntryPoint,
+        additive: bool,
+        frame_set_layout: Option<&DescriptorSetLayout>,
+        pipeline_cache: &Arc<PipelineCache>,
+    ) -> FullscreenPass {
+        // overwritten pixels don't need the previous contents
+        let render_pass = RenderPass::new(
+            device.clone(),
+            RenderPassCreateInfo {
+                attachments: vec![AttachmentDescription {
+                    format: Some(format),
+                    load_op: if additive {
+                        LoadOp::Load
+                    } else {
+                        LoadOp::DontCare
+                    },
+                    store_op: StoreOp::Store,
+                    initial_layout: ImageLayout::ColorAttachmentOptimal,
+                    final_layout: ImageLayout::ColorAttachmentOptimal,
+                    ..Default::default()
+                }],
+                subpasses: vec![SubpassDescription {
+                    color_attachments: vec![Some(AttachmentReference {
+                        attachment: 0,
+                        layout: ImageLayout::ColorAttachmentOptimal,
+                        ..Default::default()
+                    })],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // embedded from shaders/ by build.rs
+        let vs = shaders::load(device, "fullscreen.vert").unwrap();
+        let color_blend_state = if additive {
+            ColorBlendState::new(1).blend_additive()
+        } else {
+            ColorBlendState::new(1)
+        };
+        let pipeline = GraphicsPipeline::start()
+            .vertex_input_state(VertexInputState::new())
+            .vertex_shader(vs.entry_point("main").unwrap(), ())
+            .input_assembly_state(InputAssemblyState::new())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .fragment_shader(fs, ())
+            .color_blend_state(color_blend_state)
+            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .build_with_cache(pipeline_cache.clone())
+            .with_auto_layout(device.clone(), |set_layouts| {
+                if let Some(frame_set_layout) = frame_set_layout {
+                    set_layouts[FRAME_SET as usize] = DescriptorSetLayoutCreateInfo {
+                        bindings: frame_set_layout.bindings().clone(),
+                        ..Default::default()
+                    };
+                }
+            })
+            .unwrap();
+
+        let sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        FullscreenPass {
+            render_pass,
+            pipeline,
+            sampler,
+            descriptor_set: None,
+            framebuffers: Vec::new(),
+        }
+    }
+
+    pub fn format(&self) -> Format {
+        self.render_pass.attachments()[0].format.unwrap()
+    }
+
+    /// Linear, clamped to the edges, for the writes of `set_targets`.
+    pub fn sampler(&self) -> &Arc<Sampler> {
+        &self.sampler
+    }
+
+    // called whenever the window size dependent targets are rebuilt, with the writes of set 0
+    // and a target per swapchain image
+    pub fn set_targets(
+        &mut self,
+        allocator: &StandardDescriptorSetAllocator,
+        writes: impl IntoIterator<Item = WriteDescriptorSet>,
+        targets: Vec<Arc<dyn ImageViewAbstract>>,
+    ) {
+        self.descriptor_set = Some(
+            PersistentDescriptorSet::new(
+                allocator,
+                self.pipeline.layout().set_layouts()[0].clone(),
+                writes,
+            )
+            .unwrap(),
+        );
+
+        self.framebuffers = targets
+            .into_iter()
+            .map(|view| {
+                Framebuffer::new(
+                    self.render_pass.clone(),
+                    FramebufferCreateInfo {
+                        attachments: vec![view],
+                        ..Default::default()
+                    },
+                )
+                .unwrap()
+            })
+            .collect();
+    }
+
+    /// Records the pass into the target of `image_index`. `bind` records what changes every
+    /// frame, e.g. push constants, after the pipeline and set 0 are bound.
+    pub fn draw(
+        &self,
+        cmd_buffer_builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        image_index: usize,
+        bind: impl FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, &Arc<PipelineLayout>),
+    ) {
+        let framebuffer = self.framebuffers[image_index].clone();
+        let [width, height] = framebuffer.extent();
+
+        cmd_buffer_builder
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    clear_values: vec![None],
+                    ..RenderPassBeginInfo::framebuffer(framebuffer)
+                },
+                SubpassContents::Inline,
+            )
+            .unwrap()
+            .set_viewport(
+                0,
+                [Viewport {
+                    origin: [0.0, 0.0],
+                    dimensions: [width as f32, height as f32],
+                    depth_range: 0.0..1.0,
+                }],
+            )
+            .bind_pipeline_graphics(self.pipeline.clone())
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                self.pipeline.layout().clone(),
+                0,
+                self.descriptor_set.clone().unwrap(),
+            );
+        bind(cmd_buffer_builder, self.pipeline.layout());
+        cmd_buffer_builder
+            .draw(3, 1, 0, 0)
+            .unwrap()
+            .end_render_pass()
+            .unwrap();
+    }
+}
@@ -0,0 +1,57 @@
+//! FXAA post-process, drawn from the offscreen main pass target into the swapchain image.
+//!
+//! It smooths edges found from luma contrast in the final image, so unlike MSAA it costs a single
+//! fullscreen pass and no extra samples, and also catches aliasing from shading and alpha
+//! testing. It blurs some fine detail and text in return, and doesn't recover sub-pixel geometry.
+//!
+//! It also applies the tone mapping, see `tonemap`.
+
+use std::sync::Arc;
+
+use vulkano::{
+    device::Device,
+    format::{Format, NumericType},
+    pipeline::cache::PipelineCache,
+};
+
+use super::fullscreen::FullscreenPass;
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/graphic_engine/post.frag",
+        define: [("FXAA", "")],
+    }
+}
+
+mod fs_linear {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/graphic_engine/post.frag",
+        define: [("FXAA", ""), ("LINEAR_INPUT", "")],
+    }
+}
+
+/// The FXAA pass into swapchain images of `format`, reading the offscreen target and the
+/// tonemap parameters like `tonemap::pass`.
+pub(super) fn pass(
+    device: &Arc<Device>,
+    format: Format,
+    pipeline_cache: &Arc<PipelineCache>,
+) -> FullscreenPass {
+    // shaders write linear values when the swapchain encodes to sRGB, whatever the format of
+    // the offscreen source
+    let fs = if format.type_color() == Some(NumericType::SRGB) {
+        fs_linear::load(device.clone()).unwrap()
+    } else {
+        fs::load(device.clone()).unwrap()
+    };
+    FullscreenPass::new(
+        device,
+        format,
+        fs.entry_point("main").unwrap(),
+        false,
+        None,
+        pipeline_cache,
+    )
+}
@@ -0,0 +1,195 @@
+//! GPU time spent on each part of a frame, measured with timestamp queries.
+//!
+//! Timestamps are written into the frame's command buffer, and copied at its end into a
+//! host-visible buffer of the frame's in-flight slot without waiting for them. That buffer is
+//! read back when the slot comes up again, `frames_in_flight` frames later, and skipped if the GPU
+//! still holds it, so measuring never stalls the CPU.
+
+use std::{sync::Arc, time::Duration};
+
+use vulkano::{
+    buffer::{BufferUsage, CpuAccessibleBuffer},
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    device::Device,
+    memory::allocator::StandardMemoryAllocator,
+    query::{QueryPool, QueryPoolCreateInfo, QueryPoolCreationError, QueryResultFlags, QueryType},
+    sync::PipelineStage,
+};
+
+use super::EngineError;
+
+pub(super) const FRAME_START: u32 = 0;
+pub(super) const PASSES_END: u32 = 1;
+pub(super) const MAIN_PASS_END: u32 = 2;
+const FRAME_END: u32 = 3;
+const TIMESTAMPS: u32 = 4;
+
+/// GPU durations of the parts of a frame, see `Graphicengine::gpu_timings`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GpuTimings {
+    /// The passes added with `add_pass`.
+    pub passes: Duration,
+    pub main_pass: Duration,
+    /// FXAA or the blit to the swapchain image, and the screenshot copy.
+    pub post_process: Duration,
+    pub total: Duration,
+}
+
+pub(super) struct GpuTimer {
+    query_pool: Arc<QueryPool>,
+    // per slot, a value and an availability word per timestamp
+    results: Vec<Arc<CpuAccessibleBuffer<[u64]>>>,
+    nanoseconds_per_tick: f64,
+    valid_mask: u64,
+    latest: Option<GpuTimings>,
+}
+
+impl GpuTimer {
+    /// Fails with `TimestampsUnsupported` if the queue family doesn't support timestamps.
+    pub fn new(
+        device: &Arc<Device>,
+        memory_allocator: &StandardMemoryAllocator,
+        queue_family_index: u32,
+        slots: usize,
+    ) -> Result<GpuTimer, EngineError> {
+        let physical_device = device.physical_device();
+        let valid_bits = physical_device.queue_family_properties()[queue_family_index as usize]
+            .timestamp_valid_bits
+            .ok_or(EngineError::TimestampsUnsupported)?;
+
+        let query_pool = QueryPool::new(
+            device.clone(),
+            QueryPoolCreateInfo {
+                query_count: TIMESTAMPS * slots as u32,
+                ..QueryPoolCreateInfo::query_type(QueryType::Timestamp)
+            },
+        )
+        .map_err(|e| match e {
+            QueryPoolCreationError::OomError(e) => EngineError::OutOfMemory(e),
+            e => EngineError::QueryPoolCreation(e),
+        })?;
+        let results = (0..slots)
+            .map(|_| {
+                // zeroed, so slots that never ran read as unavailable
+                CpuAccessibleBuffer::from_iter(
+                    memory_allocator,
+                    BufferUsage {
+                        transfer_dst: true,
+                        ..BufferUsage::empty()
+                    },
+                    true,
+                    (0..TIMESTAMPS * 2).map(|_| 0u64),
+                )
+                .map_err(EngineError::allocation(TIMESTAMPS as u64 * 2 * 8))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(GpuTimer {
+            query_pool,
+            results,
+            nanoseconds_per_tick: physical_device.properties().timestamp_period as f64,
+            valid_mask: u64::MAX >> (64 - valid_bits),
+            latest: None,
+        })
+    }
+
+    pub fn slots(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn latest(&self) -> Option<GpuTimings> {
+        self.latest
+    }
+
+    /// Reads what the slot measured last time, and starts measuring the frame in it.
+    pub fn begin(
+        &mut self,
+        cmd_buffer_builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        slot: usize,
+    ) {
+        if let Some(timings) = self.read(slot) {
+            self.latest = Some(timings);
+        }
+
+        let queries = self.queries(slot);
+        // query commands on one queue execute in submission order, so this can't overtake the
+        // copy of the previous frame in the slot
+        unsafe {
+            cmd_buffer_builder
+                .reset_query_pool(self.query_pool.clone(), queries)
+                .unwrap();
+        }
+        self.write(cmd_buffer_builder, slot, FRAME_START);
+    }
+
+    pub fn write(
+        &self,
+        cmd_buffer_builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        slot: usize,
+        timestamp: u32,
+    ) {
+        let stage = if timestamp == FRAME_START {
+            PipelineStage::TopOfPipe
+        } else {
+            PipelineStage::BottomOfPipe
+        };
+        // reset in begin, and each timestamp is written once per frame
+        unsafe {
+            cmd_buffer_builder
+                .write_timestamp(
+                    self.query_pool.clone(),
+                    self.queries(slot).start + timestamp,
+                    stage,
+                )
+                .unwrap();
+        }
+    }
+
+    /// Ends the measure of the frame and queues the copy of its results.
+    pub fn end(
+        &self,
+        cmd_buffer_builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        slot: usize,
+    ) {
+        self.write(cmd_buffer_builder, slot, FRAME_END);
+        cmd_buffer_builder
+            .copy_query_pool_results(
+                self.query_pool.clone(),
+                self.queries(slot),
+                self.results[slot].clone(),
+                QueryResultFlags {
+                    with_availability: true,
+                    ..QueryResultFlags::empty()
+                },
+            )
+            .unwrap();
+    }
+
+    fn queries(&self, slot: usize) -> std::ops::Range<u32> {
+        let start = TIMESTAMPS * slot as u32;
+        start..start + TIMESTAMPS
+    }
+
+    fn read(&self, slot: usize) -> Option<GpuTimings> {
+        // fails while the GPU still has the frame that last used the slot
+        let results = self.results[slot].read().ok()?;
+        let mut ticks = [0; TIMESTAMPS as usize];
+        for (ticks, result) in ticks.iter_mut().zip(results.chunks_exact(2)) {
+            if result[1] == 0 {
+                return None;
+            }
+            *ticks = result[0] & self.valid_mask;
+        }
+
+        let between = |start: u32, end: u32| {
+            let elapsed = ticks[end as usize].wrapping_sub(ticks[start as usize]) & self.valid_mask;
+            Duration::from_nanos((elapsed as f64 * self.nanoseconds_per_tick) as u64)
+        };
+        Some(GpuTimings {
+            passes: between(FRAME_START, PASSES_END),
+            main_pass: between(PASSES_END, MAIN_PASS_END),
+            post_process: between(MAIN_PASS_END, FRAME_END),
+            total: between(FRAME_START, FRAME_END),
+        })
+    }
+}
@@ -0,0 +1,63 @@
+//! HDR mastering metadata sent to the display with `VK_EXT_hdr_metadata`, describing the colors
+//! and luminance the content was graded for so the display can tone map it to its own range.
+//!
+//! vulkano doesn't wrap the extension, so it is called through the raw device functions.
+
+use ash::vk;
+use vulkano::{device::Device, swapchain::Swapchain, VulkanObject};
+
+/// Chromaticities are CIE 1931 xy coordinates, luminances in nits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HdrMetadata {
+    pub display_primary_red: [f32; 2],
+    pub display_primary_green: [f32; 2],
+    pub display_primary_blue: [f32; 2],
+    pub white_point: [f32; 2],
+    pub max_luminance: f32,
+    pub min_luminance: f32,
+    /// Luminance of the brightest pixel of the content.
+    pub max_content_light_level: f32,
+    /// Highest average luminance of a frame of the content.
+    pub max_frame_average_light_level: f32,
+}
+
+impl HdrMetadata {
+    /// Rec. 2020 primaries with a D65 white point, for `Hdr10St2084` swapchains, mastered for a
+    /// common 1000 nit display. Adjust the luminances to the content.
+    pub const REC_2020: HdrMetadata = HdrMetadata {
+        display_primary_red: [0.708, 0.292],
+        display_primary_green: [0.170, 0.797],
+        display_primary_blue: [0.131, 0.046],
+        white_point: [0.3127, 0.3290],
+        max_luminance: 1000.0,
+        min_luminance: 0.001,
+        max_content_light_level: 1000.0,
+        max_frame_average_light_level: 400.0,
+    };
+}
+
+// the device must have VK_EXT_hdr_metadata enabled
+pub(super) fn set(device: &Device, swapchain: &Swapchain, metadata: &HdrMetadata) {
+    let xy = |[x, y]: [f32; 2]| vk::XYColorEXT { x, y };
+    let metadata = vk::HdrMetadataEXT {
+        display_primary_red: xy(metadata.display_primary_red),
+        display_primary_green: xy(metadata.display_primary_green),
+        display_primary_blue: xy(metadata.display_primary_blue),
+        white_point: xy(metadata.white_point),
+        max_luminance: metadata.max_luminance,
+        min_luminance: metadata.min_luminance,
+        max_content_light_level: metadata.max_content_light_level,
+        max_frame_average_light_level: metadata.max_frame_average_light_level,
+        ..Default::default()
+    };
+    let swapchain = swapchain.handle();
+
+    unsafe {
+        (device.fns().ext_hdr_metadata.set_hdr_metadata_ext)(
+            device.handle(),
+            1,
+            &swapchain,
+            &metadata,
+        );
+    }
+}
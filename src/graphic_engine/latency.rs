@@ -0,0 +1,95 @@
+//! Frame latency bounded with `VK_KHR_present_wait`: every present gets an id, and a frame only
+//! starts once the present from `max_latency` frames ago has reached the display, so the CPU
+//! can't queue frames further ahead of it than that.
+
+use std::{
+    collections::VecDeque,
+    num::NonZeroU64,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use vulkano::swapchain::{self, PresentWaitError, Swapchain};
+
+// presents of a hidden window may never complete, so waits give up rather than stall
+const PRESENT_WAIT_TIMEOUT: Duration = Duration::from_millis(100);
+
+// ids must keep increasing for the whole life of a swapchain, so the pacer lives as long as the
+// engine
+pub(super) struct PresentPacer {
+    max_latency: Option<u32>,
+    last_id: u64,
+    // ids and submission times of the presents not waited on yet, oldest first
+    pending: VecDeque<(NonZeroU64, Instant)>,
+    latency: Option<Duration>,
+}
+
+impl PresentPacer {
+    pub fn new() -> PresentPacer {
+        PresentPacer {
+            max_latency: None,
+            last_id: 0,
+            pending: VecDeque::new(),
+            latency: None,
+        }
+    }
+
+    /// `None` stops pacing presents.
+    pub fn set_max_latency(&mut self, frames: Option<u32>) {
+        self.max_latency = frames.map(|frames| frames.max(1));
+        if self.max_latency.is_none() {
+            self.pending.clear();
+            self.latency = None;
+        }
+    }
+
+    pub fn max_latency(&self) -> Option<u32> {
+        self.max_latency
+    }
+
+    /// Blocks until fewer than `max_latency` presents are still on their way to the display.
+    /// Returns the latency of each present waited on.
+    pub fn wait(&mut self, swapchain: &Arc<Swapchain>) -> Vec<Duration> {
+        let mut latencies = Vec::new();
+        let Some(max_latency) = self.max_latency else {
+            return latencies;
+        };
+        while self.pending.len() >= max_latency as usize {
+            let (id, submitted) = self.pending.pop_front().unwrap();
+            match swapchain::wait_for_present(
+                swapchain.clone(),
+                id.get(),
+                Some(PRESENT_WAIT_TIMEOUT),
+            ) {
+                Ok(_) => latencies.push(submitted.elapsed()),
+                Err(PresentWaitError::Timeout) => {}
+                // the swapchain is being replaced, its presents can't be waited on anymore
+                Err(_) => self.pending.clear(),
+            }
+        }
+        if let Some(&latency) = latencies.last() {
+            self.latency = Some(latency);
+        }
+        latencies
+    }
+
+    /// Id for the present about to be submitted, while pacing.
+    pub fn next_id(&mut self) -> Option<NonZeroU64> {
+        self.max_latency?;
+        self.last_id += 1;
+        let id = NonZeroU64::new(self.last_id).unwrap();
+        self.pending.push_back((id, Instant::now()));
+        Some(id)
+    }
+
+    /// Forgets the presents in flight, after a failed present or when the swapchain is
+    /// recreated. Ids keep increasing, which a new swapchain accepts too.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Time from submission to display of the last present waited on.
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+}
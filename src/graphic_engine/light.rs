@@ -0,0 +1,78 @@
+//! Point lights uploaded with the frame uniforms, at set `FRAME_SET`, binding 1:
+//!
+//! ```glsl
+//! struct PointLight {
+//!     vec3 position;
+//!     float radius;
+//!     vec3 color;
+//! };
+//!
+//! // specialized with `MAX_LIGHTS_CONSTANT_ID`, up to `MAX_POINT_LIGHTS`
+//! layout(constant_id = 0) const uint MAX_LIGHTS = 16;
+//!
+//! layout(set = 1, binding = 1) uniform Lights {
+//!     uint light_count;
+//!     PointLight lights[MAX_LIGHTS];
+//! };
+//!
+//! // for (uint i = 0; i < min(light_count, MAX_LIGHTS); i++) { ... }
+//! ```
+//!
+//! The count comes first so that the array can be specialized to fewer lights than the engine
+//! uploads: shaders with a lower `MAX_LIGHTS` read the first ones.
+
+use bytemuck::{Pod, Zeroable};
+
+/// Number of point lights a frame can hold, the largest array size of the `Lights` block.
+pub const MAX_POINT_LIGHTS: usize = 16;
+
+/// Id of the `MAX_LIGHTS` specialization constant sizing the `Lights` array, set at pipeline
+/// build, e.g. to `MAX_POINT_LIGHTS`.
+pub const MAX_LIGHTS_CONSTANT_ID: u32 = 0;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PointLight {
+    pub position: [f32; 3],
+    /// Distance at which the light no longer contributes.
+    pub radius: f32,
+    pub color: [f32; 3],
+    // std140 rounds each array element up to 16 bytes
+    _padding: f32,
+}
+
+// plain f32 fields, padded by hand to the std140 layout
+unsafe impl Zeroable for PointLight {}
+unsafe impl Pod for PointLight {}
+
+impl PointLight {
+    pub fn new(position: [f32; 3], color: [f32; 3], radius: f32) -> PointLight {
+        PointLight {
+            position,
+            radius,
+            color,
+            _padding: 0.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(super) struct LightUniforms {
+    count: u32,
+    _padding: [u32; 3],
+    lights: [PointLight; MAX_POINT_LIGHTS],
+}
+
+// as above, the count is padded to the 16 byte alignment of the array
+unsafe impl Zeroable for LightUniforms {}
+unsafe impl Pod for LightUniforms {}
+
+impl LightUniforms {
+    pub fn new(lights: &[PointLight]) -> LightUniforms {
+        let mut uniforms = LightUniforms::zeroed();
+        uniforms.lights[..lights.len()].copy_from_slice(lights);
+        uniforms.count = lights.len() as u32;
+        uniforms
+    }
+}
@@ -0,0 +1,424 @@
+//! Debug lines in world space, drawn in the main pass like text, see `Graphicengine::draw_lines`.
+//!
+//! `LineMode::Hardware` rasterizes line primitives, one pixel wide and aliased. Wider hardware
+//! lines need the `wide_lines` feature and still look different across drivers, so
+//! `LineMode::Quad` expands each line in the vertex shader into a quad facing the camera, of a
+//! width in pixels, whose alpha falls off over its outer pixel for smooth edges. It only needs
+//! blending, and costs six vertices per line instead of two.
+//!
+//! Lines are clipped to the near plane on the CPU, the quads can't be expanded behind the camera.
+
+use std::sync::Arc;
+
+use bytemuck::{Pod, Zeroable};
+use vulkano::{
+    buffer::{BufferUsage, CpuAccessibleBuffer},
+    command_buffer::{AutoCommandBufferBuilder, SecondaryAutoCommandBuffer},
+    device::Device,
+    impl_vertex,
+    memory::allocator::StandardMemoryAllocator,
+    pipeline::{
+        cache::PipelineCache,
+        graphics::{
+            color_blend::{
+                AttachmentBlend, ColorBlendAttachmentState, ColorBlendState, ColorComponents,
+            },
+            input_assembly::{InputAssemblyState, PrimitiveTopology},
+            multisample::MultisampleState,
+            render_pass::PipelineRenderingCreateInfo,
+            vertex_input::BuffersDefinition,
+            viewport::{Viewport, ViewportState},
+        },
+        GraphicsPipeline, StateMode,
+    },
+    render_pass::{RenderPass, Subpass},
+};
+
+use super::{projection::Matrix4, EngineError, RenderPath};
+
+mod hardware_vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+#version 450
+
+layout(location = 0) in vec4 position;
+layout(location = 1) in vec4 color;
+
+layout(location = 0) out vec4 v_color;
+
+void main() {
+    v_color = color;
+    gl_Position = position;
+}
+"
+    }
+}
+
+mod hardware_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+#version 450
+
+layout(location = 0) in vec4 v_color;
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    f_color = v_color;
+}
+"
+    }
+}
+
+mod quad_vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+#version 450
+
+// both ends of the line, in clip space
+layout(location = 0) in vec4 start;
+layout(location = 1) in vec4 end;
+layout(location = 2) in vec4 color;
+// the end of the line this corner is at, 0 or 1, and its side, -1 or 1
+layout(location = 3) in vec2 corner;
+layout(location = 4) in vec2 viewport;
+layout(location = 5) in float half_width;
+
+layout(location = 0) out vec4 v_color;
+// signed distance from the center of the line and half width, in pixels
+layout(location = 1) out vec2 v_distance;
+
+void main() {
+    vec2 start_pixels = start.xy / start.w * viewport * 0.5;
+    vec2 end_pixels = end.xy / end.w * viewport * 0.5;
+    vec2 direction = end_pixels - start_pixels;
+    if (dot(direction, direction) < 1e-8) {
+        direction = vec2(1.0, 0.0);
+    }
+    vec2 normal = normalize(vec2(-direction.y, direction.x));
+
+    // a pixel wider than the line, for the falloff
+    float offset = corner.y * (half_width + 1.0);
+    vec4 position = corner.x == 0.0 ? start : end;
+    position.xy += normal * offset / viewport * 2.0 * position.w;
+
+    v_color = color;
+    v_distance = vec2(offset, half_width);
+    gl_Position = position;
+}
+"
+    }
+}
+
+mod quad_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+#version 450
+
+layout(location = 0) in vec4 v_color;
+layout(location = 1) in vec2 v_distance;
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    // how much of the pixel the line covers across its edge
+    float coverage = clamp(v_distance.y + 0.5 - abs(v_distance.x), 0.0, 1.0);
+    f_color = vec4(v_color.rgb, v_color.a * coverage);
+}
+"
+    }
+}
+
+/// How `Graphicengine::draw_lines` rasterizes lines.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LineMode {
+    /// Line primitives, one pixel wide.
+    #[default]
+    Hardware,
+    /// Quads `width` pixels of `render_extent` wide, with anti-aliased edges.
+    Quad { width: f32 },
+}
+
+/// A line of `Graphicengine::draw_lines`, between two points in world space. Colors are written
+/// to the main pass target as they are, like the clear color, and blended by their alpha.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Line {
+    pub from: [f32; 3],
+    pub to: [f32; 3],
+    pub color: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+struct HardwareVertex {
+    position: [f32; 4],
+    color: [f32; 4],
+}
+
+// plain f32 fields with no implicit padding
+unsafe impl Zeroable for HardwareVertex {}
+unsafe impl Pod for HardwareVertex {}
+
+impl_vertex!(HardwareVertex, position, color);
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+struct QuadVertex {
+    start: [f32; 4],
+    end: [f32; 4],
+    color: [f32; 4],
+    corner: [f32; 2],
+    viewport: [f32; 2],
+    half_width: f32,
+}
+
+// plain f32 fields with no implicit padding
+unsafe impl Zeroable for QuadVertex {}
+unsafe impl Pod for QuadVertex {}
+
+impl_vertex!(QuadVertex, start, end, color, corner, viewport, half_width);
+
+fn transform(matrix: &Matrix4, point: [f32; 3]) -> [f32; 4] {
+    [0, 1, 2, 3].map(|row| {
+        matrix[0][row] * point[0]
+            + matrix[1][row] * point[1]
+            + matrix[2][row] * point[2]
+            + matrix[3][row]
+    })
+}
+
+/// Ends of `lines` seen through `view_proj`, in clip space, with their colors. Lines behind the
+/// camera are left out.
+pub(super) fn clip_space_lines(
+    lines: &[Line],
+    view_proj: &Matrix4,
+    reverse_z: bool,
+) -> Vec<ClipSpaceLine> {
+    lines
+        .iter()
+        .filter_map(|line| {
+            let (start, end) = clip_to_near(
+                transform(view_proj, line.from),
+                transform(view_proj, line.to),
+                reverse_z,
+            )?;
+            Some(ClipSpaceLine {
+                start,
+                end,
+                color: line.color,
+            })
+        })
+        .collect()
+}
+
+pub(super) struct ClipSpaceLine {
+    start: [f32; 4],
+    end: [f32; 4],
+    color: [f32; 4],
+}
+
+// the part of the segment in front of the near plane, at depth 0.0, or 1.0 with reverse Z
+fn clip_to_near(start: [f32; 4], end: [f32; 4], reverse_z: bool) -> Option<([f32; 4], [f32; 4])> {
+    let distance = |p: [f32; 4]| if reverse_z { p[3] - p[2] } else { p[2] };
+    let (start_distance, end_distance) = (distance(start), distance(end));
+    if start_distance < 0.0 && end_distance < 0.0 {
+        return None;
+    }
+
+    let at = |t: f32| [0, 1, 2, 3].map(|i| start[i] + (end[i] - start[i]) * t);
+    let t = start_distance / (start_distance - end_distance);
+    if start_distance < 0.0 {
+        Some((at(t), end))
+    } else if end_distance < 0.0 {
+        Some((start, at(t)))
+    } else {
+        Some((start, end))
+    }
+}
+
+/// Pipeline drawing lines in the main pass for one line mode, rebuilt when the main pass or
+/// the mode changes.
+pub(super) struct LineRenderer {
+    render_pass: Arc<RenderPass>,
+    // whether it draws `LineMode::Quad`, the width is a vertex attribute
+    quads: bool,
+    pipeline: Arc<GraphicsPipeline>,
+}
+
+impl LineRenderer {
+    pub fn new(
+        device: &Arc<Device>,
+        render_pass: &Arc<RenderPass>,
+        render_path: RenderPath,
+        mode: LineMode,
+        pipeline_cache: &Arc<PipelineCache>,
+    ) -> LineRenderer {
+        // the render pass describes the main pass attachments with either render path
+        let attachments = render_pass.attachments();
+        let subpass = Subpass::from(render_pass.clone(), 0).unwrap();
+        let color_attachments = &subpass.subpass_desc().color_attachments;
+
+        // only the main color attachment is written to
+        let mut color_blend_state = ColorBlendState::new(color_attachments.len() as u32);
+        for (i, attachment) in color_blend_state.attachments.iter_mut().enumerate() {
+            *attachment = if i == 0 {
+                ColorBlendAttachmentState {
+                    blend: Some(AttachmentBlend::alpha()),
+                    color_write_mask: ColorComponents::all(),
+                    color_write_enable: StateMode::Fixed(true),
+                }
+            } else {
+                ColorBlendAttachmentState {
+                    blend: None,
+                    color_write_mask: ColorComponents::empty(),
+                    color_write_enable: StateMode::Fixed(true),
+                }
+            };
+        }
+
+        let quads = matches!(mode, LineMode::Quad { .. });
+        let (vs, fs, vertex_input_state, topology) = if quads {
+            (
+                quad_vs::load(device.clone()).unwrap(),
+                quad_fs::load(device.clone()).unwrap(),
+                BuffersDefinition::new().vertex::<QuadVertex>(),
+                PrimitiveTopology::TriangleList,
+            )
+        } else {
+            (
+                hardware_vs::load(device.clone()).unwrap(),
+                hardware_fs::load(device.clone()).unwrap(),
+                BuffersDefinition::new().vertex::<HardwareVertex>(),
+                PrimitiveTopology::LineList,
+            )
+        };
+        let builder = GraphicsPipeline::start()
+            .vertex_input_state(vertex_input_state)
+            .vertex_shader(vs.entry_point("main").unwrap(), ())
+            .input_assembly_state(InputAssemblyState::new().topology(topology))
+            .fragment_shader(fs.entry_point("main").unwrap(), ())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .color_blend_state(color_blend_state)
+            .multisample_state(MultisampleState {
+                rasterization_samples: attachments[0].samples,
+                ..Default::default()
+            });
+        let builder = match render_path {
+            RenderPath::RenderPass => builder.render_pass(subpass.clone()),
+            RenderPath::DynamicRendering => {
+                let format = |attachment: u32| attachments[attachment as usize].format;
+                builder.render_pass(PipelineRenderingCreateInfo {
+                    color_attachment_formats: color_attachments
+                        .iter()
+                        .map(|reference| reference.as_ref().and_then(|r| format(r.attachment)))
+                        .collect(),
+                    depth_attachment_format: subpass
+                        .subpass_desc()
+                        .depth_stencil_attachment
+                        .as_ref()
+                        .and_then(|r| format(r.attachment)),
+                    ..Default::default()
+                })
+            }
+        };
+        let pipeline = builder
+            .build_with_cache(pipeline_cache.clone())
+            .build(device.clone())
+            .unwrap();
+
+        LineRenderer {
+            render_pass: render_pass.clone(),
+            quads,
+            pipeline,
+        }
+    }
+
+    pub fn is_for(&self, render_pass: &Arc<RenderPass>, mode: LineMode) -> bool {
+        Arc::ptr_eq(&self.render_pass, render_pass)
+            && self.quads == matches!(mode, LineMode::Quad { .. })
+    }
+
+    /// Records a draw of the lines from `clip_space_lines`, which mustn't be empty, in `mode`,
+    /// which the renderer must be for.
+    pub fn draw(
+        &self,
+        cmd_buffer_builder: &mut AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>,
+        memory_allocator: &StandardMemoryAllocator,
+        viewport: &Viewport,
+        mode: LineMode,
+        lines: &[ClipSpaceLine],
+    ) -> Result<(), EngineError> {
+        match mode {
+            LineMode::Hardware => {
+                let vertices = lines
+                    .iter()
+                    .flat_map(|line| {
+                        [line.start, line.end].map(|position| HardwareVertex {
+                            position,
+                            color: line.color,
+                        })
+                    })
+                    .collect();
+                self.record(cmd_buffer_builder, memory_allocator, viewport, vertices)
+            }
+            LineMode::Quad { width } => {
+                // two triangles between the sides of both ends
+                let corners = [
+                    [0.0, -1.0],
+                    [0.0, 1.0],
+                    [1.0, -1.0],
+                    [1.0, -1.0],
+                    [0.0, 1.0],
+                    [1.0, 1.0],
+                ];
+                let vertices = lines
+                    .iter()
+                    .flat_map(|line| {
+                        corners.map(|corner| QuadVertex {
+                            start: line.start,
+                            end: line.end,
+                            color: line.color,
+                            corner,
+                            viewport: viewport.dimensions,
+                            half_width: width.max(0.0) * 0.5,
+                        })
+                    })
+                    .collect();
+                self.record(cmd_buffer_builder, memory_allocator, viewport, vertices)
+            }
+        }
+    }
+
+    fn record<V: Pod + Send + Sync>(
+        &self,
+        cmd_buffer_builder: &mut AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>,
+        memory_allocator: &StandardMemoryAllocator,
+        viewport: &Viewport,
+        vertices: Vec<V>,
+    ) -> Result<(), EngineError> {
+        let vertex_count = vertices.len() as u32;
+        let size = std::mem::size_of_val(vertices.as_slice()) as u64;
+        let vertex_buffer = CpuAccessibleBuffer::from_iter(
+            memory_allocator,
+            BufferUsage {
+                vertex_buffer: true,
+                ..BufferUsage::empty()
+            },
+            false,
+            vertices,
+        )
+        .map_err(EngineError::allocation(size))?;
+
+        cmd_buffer_builder
+            .set_viewport(0, [viewport.clone()])
+            .bind_pipeline_graphics(self.pipeline.clone())
+            .bind_vertex_buffers(0, vertex_buffer)
+            .draw(vertex_count, 1, 0, 0)
+            .unwrap();
+        Ok(())
+    }
+}
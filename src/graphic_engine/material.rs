@@ -0,0 +1,139 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use vulkano::{
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator,
+        layout::{
+            DescriptorSetLayout, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo,
+            DescriptorType,
+        },
+        PersistentDescriptorSet, WriteDescriptorSet,
+    },
+    device::Device,
+    image::ImageViewAbstract,
+    sampler::Sampler,
+    shader::ShaderStages,
+};
+
+use super::EngineError;
+
+// size of the texture array when descriptor indexing is available
+const MAX_BINDLESS_MATERIALS: u32 = 1024;
+
+/// Textures registered as materials, sampled by fragment shaders at set binding 0.
+///
+/// With descriptor indexing, every texture lives in one variable-sized array bound once, and
+/// shaders index it with the material id (e.g. from a push constant). Otherwise each material
+/// gets its own single-texture descriptor set.
+pub(super) struct MaterialRegistry {
+    layout: Arc<DescriptorSetLayout>,
+    bindless: bool,
+    textures: Vec<Arc<dyn ImageViewAbstract>>,
+    descriptor_sets: Vec<Arc<PersistentDescriptorSet>>,
+}
+
+impl MaterialRegistry {
+    pub fn new(device: &Arc<Device>, bindless: bool) -> MaterialRegistry {
+        let binding = if bindless {
+            let properties = device.physical_device().properties();
+            DescriptorSetLayoutBinding {
+                descriptor_count: MAX_BINDLESS_MATERIALS
+                    .min(properties.max_per_stage_descriptor_sampled_images)
+                    .min(properties.max_per_stage_descriptor_samplers),
+                variable_descriptor_count: true,
+                stages: ShaderStages {
+                    fragment: true,
+                    ..ShaderStages::empty()
+                },
+                ..DescriptorSetLayoutBinding::descriptor_type(DescriptorType::CombinedImageSampler)
+            }
+        } else {
+            DescriptorSetLayoutBinding {
+                stages: ShaderStages {
+                    fragment: true,
+                    ..ShaderStages::empty()
+                },
+                ..DescriptorSetLayoutBinding::descriptor_type(DescriptorType::CombinedImageSampler)
+            }
+        };
+
+        let layout = DescriptorSetLayout::new(
+            device.clone(),
+            DescriptorSetLayoutCreateInfo {
+                bindings: BTreeMap::from([(0, binding)]),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        MaterialRegistry {
+            layout,
+            bindless,
+            textures: Vec::new(),
+            descriptor_sets: Vec::new(),
+        }
+    }
+
+    pub fn register(
+        &mut self,
+        allocator: &StandardDescriptorSetAllocator,
+        texture: Arc<dyn ImageViewAbstract>,
+        sampler: Arc<Sampler>,
+    ) -> Result<u32, EngineError> {
+        let material = self.textures.len() as u32;
+        if self.bindless {
+            let capacity = self.layout.variable_descriptor_count();
+            if material == capacity {
+                return Err(EngineError::TooManyMaterials(capacity));
+            }
+        }
+        self.textures.push(texture.clone());
+
+        if self.bindless {
+            // the array set is immutable, so it is rebuilt with every texture
+            let descriptor_set = PersistentDescriptorSet::new_variable(
+                allocator,
+                self.layout.clone(),
+                self.textures.len() as u32,
+                [WriteDescriptorSet::image_view_sampler_array(
+                    0,
+                    0,
+                    self.textures
+                        .iter()
+                        .map(|texture| (texture.clone(), sampler.clone())),
+                )],
+            )
+            .unwrap();
+            self.descriptor_sets = vec![descriptor_set];
+        } else {
+            let descriptor_set = PersistentDescriptorSet::new(
+                allocator,
+                self.layout.clone(),
+                [WriteDescriptorSet::image_view_sampler(0, texture, sampler)],
+            )
+            .unwrap();
+            self.descriptor_sets.push(descriptor_set);
+        }
+
+        Ok(material)
+    }
+
+    pub fn layout(&self) -> Arc<DescriptorSetLayout> {
+        self.layout.clone()
+    }
+
+    pub fn is_bindless(&self) -> bool {
+        self.bindless
+    }
+
+    pub fn descriptor_set(&self, material: u32) -> Option<Arc<PersistentDescriptorSet>> {
+        if self.bindless {
+            self.descriptor_sets
+                .first()
+                .filter(|_| material < self.textures.len() as u32)
+                .cloned()
+        } else {
+            self.descriptor_sets.get(material as usize).cloned()
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! Morph target animation: keyframe position sets of one mesh, blended linearly on the GPU.
+//!
+//! Each keyframe is its own vertex buffer. A frame binds the two keyframes around the current
+//! time, at bindings 0 and 1 of `MorphTargets::vertex_input_state`, and the vertex shader mixes
+//! them with the weight of the second one, e.g. from a push constant:
+//!
+//! ```glsl
+//! layout(location = 0) in vec3 position_from;
+//! layout(location = 1) in vec3 position_to;
+//! layout(push_constant) uniform Morph { float weight; };
+//!
+//! vec3 position = mix(position_from, position_to, weight);
+//! ```
+//!
+//! Other attributes, which don't change between keyframes, go in further bindings.
+
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::BufferAccess,
+    format::Format,
+    pipeline::graphics::vertex_input::{
+        VertexInputAttributeDescription, VertexInputBindingDescription, VertexInputRate,
+        VertexInputState,
+    },
+};
+
+/// Positions of a mesh at each keyframe, see `Graphicengine::create_morph_targets`, played back
+/// at a fixed rate.
+pub struct MorphTargets {
+    keyframes: Vec<Arc<dyn BufferAccess>>,
+    vertex_count: usize,
+    keyframe_duration: f32,
+    looping: bool,
+}
+
+/// The keyframes to blend at some time, and how far along from the first to the second.
+#[derive(Clone)]
+pub struct MorphSample {
+    pub from: Arc<dyn BufferAccess>,
+    pub to: Arc<dyn BufferAccess>,
+    /// Weight of `to`, between 0 and 1.
+    pub weight: f32,
+}
+
+impl MorphTargets {
+    pub(super) fn new(keyframes: Vec<Arc<dyn BufferAccess>>, vertex_count: usize) -> MorphTargets {
+        MorphTargets {
+            keyframes,
+            vertex_count,
+            keyframe_duration: 1.0,
+            looping: true,
+        }
+    }
+
+    /// Vertex input with the positions of the blended keyframes at bindings 0 and 1, read at
+    /// `from_location` and `to_location` as `vec3`.
+    pub fn vertex_input_state(from_location: u32, to_location: u32) -> VertexInputState {
+        let binding = || VertexInputBindingDescription {
+            stride: std::mem::size_of::<[f32; 3]>() as u32,
+            input_rate: VertexInputRate::Vertex,
+        };
+        let attribute = |index| VertexInputAttributeDescription {
+            binding: index,
+            format: Format::R32G32B32_SFLOAT,
+            offset: 0,
+        };
+        VertexInputState::new()
+            .bindings([(0, binding()), (1, binding())])
+            .attributes([(from_location, attribute(0)), (to_location, attribute(1))])
+    }
+
+    pub fn keyframe_count(&self) -> usize {
+        self.keyframes.len()
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+
+    pub fn keyframe(&self, index: usize) -> Option<&Arc<dyn BufferAccess>> {
+        self.keyframes.get(index)
+    }
+
+    /// Seconds from one keyframe to the next, 1 by default.
+    pub fn set_keyframe_duration(&mut self, seconds: f32) {
+        self.keyframe_duration = seconds.max(f32::EPSILON);
+    }
+
+    pub fn keyframe_duration(&self) -> f32 {
+        self.keyframe_duration
+    }
+
+    /// Whether playback blends from the last keyframe back to the first, the default, rather
+    /// than stopping on the last one.
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    pub fn looping(&self) -> bool {
+        self.looping
+    }
+
+    /// Seconds to play every keyframe once, back to the first one when looping.
+    pub fn duration(&self) -> f32 {
+        let segments = if self.looping {
+            self.keyframes.len()
+        } else {
+            self.keyframes.len() - 1
+        };
+        segments as f32 * self.keyframe_duration
+    }
+
+    /// The keyframes to bind `time` seconds into the animation, e.g.
+    /// `Graphicengine::elapsed_seconds`. Negative times play from the start.
+    pub fn sample(&self, time: f32) -> MorphSample {
+        let count = self.keyframes.len();
+        let position = time.max(0.0) / self.keyframe_duration;
+        let (from, weight) = if count == 1 {
+            (0, 0.0)
+        } else if self.looping {
+            let position = position % count as f32;
+            (position as usize, position.fract())
+        } else {
+            // the last keyframe is the end of the last segment
+            let from = (position as usize).min(count - 2);
+            (from, (position - from as f32).min(1.0))
+        };
+
+        MorphSample {
+            from: self.keyframes[from].clone(),
+            to: self.keyframes[(from + 1) % count].clone(),
+            weight,
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Rendering over images owned by someone else, e.g. a compositor embedding the engine, whose
+//! contents are kept: the attachments load what is already there instead of clearing it.
+//!
+//! The images are tracked by vulkano like the engine's own, so they must be vulkano images on
+//! the engine's device, with their contents in the layout their `ImageAccess` reports.
+//! `AutoCommandBufferBuilder` transitions them from it to `ColorAttachmentOptimal` for the render
+//! pass and back afterwards, which keeps the contents.
+
+use std::{collections::HashMap, sync::Arc};
+
+use vulkano::{
+    command_buffer::{
+        AutoCommandBufferBuilder, PrimaryAutoCommandBuffer, RenderPassBeginInfo, SubpassContents,
+    },
+    device::Device,
+    format::Format,
+    image::{ImageLayout, ImageViewAbstract},
+    pipeline::graphics::viewport::Viewport,
+    render_pass::{
+        AttachmentDescription, AttachmentReference, Framebuffer, FramebufferCreateInfo, LoadOp,
+        RenderPass, RenderPassCreateInfo, StoreOp, SubpassDescription,
+    },
+};
+
+// one render pass per list of attachment formats, so pipelines built for it stay valid
+#[derive(Default)]
+pub(super) struct OverlayPasses {
+    render_passes: HashMap<Vec<Format>, Arc<RenderPass>>,
+}
+
+impl OverlayPasses {
+    pub fn get(&mut self, device: &Arc<Device>, formats: &[Format]) -> Arc<RenderPass> {
+        self.render_passes
+            .entry(formats.to_vec())
+            .or_insert_with(|| {
+                let attachments = formats
+                    .iter()
+                    .map(|&format| AttachmentDescription {
+                        format: Some(format),
+                        load_op: LoadOp::Load,
+                        store_op: StoreOp::Store,
+                        initial_layout: ImageLayout::ColorAttachmentOptimal,
+                        final_layout: ImageLayout::ColorAttachmentOptimal,
+                        ..Default::default()
+                    })
+                    .collect();
+                let color_attachments = (0..formats.len() as u32)
+                    .map(|attachment| {
+                        Some(AttachmentReference {
+                            attachment,
+                            layout: ImageLayout::ColorAttachmentOptimal,
+                            ..Default::default()
+                        })
+                    })
+                    .collect();
+                RenderPass::new(
+                    device.clone(),
+                    RenderPassCreateInfo {
+                        attachments,
+                        subpasses: vec![SubpassDescription {
+                            color_attachments,
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    },
+                )
+                .unwrap()
+            })
+            .clone()
+    }
+}
+
+/// Records the render pass over `views`, already checked against it, with `record` drawing
+/// inside it.
+pub(super) fn record(
+    cmd_buffer_builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    render_pass: Arc<RenderPass>,
+    views: &[Arc<dyn ImageViewAbstract>],
+    record: impl FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, &Viewport),
+) {
+    let framebuffer = Framebuffer::new(
+        render_pass,
+        FramebufferCreateInfo {
+            attachments: views.to_vec(),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let [width, height] = framebuffer.extent();
+    let viewport = Viewport {
+        origin: [0.0, 0.0],
+        dimensions: [width as f32, height as f32],
+        depth_range: 0.0..1.0,
+    };
+
+    cmd_buffer_builder
+        .begin_render_pass(
+            RenderPassBeginInfo {
+                clear_values: vec![None; views.len()],
+                ..RenderPassBeginInfo::framebuffer(framebuffer)
+            },
+            SubpassContents::Inline,
+        )
+        .unwrap()
+        .set_viewport(0, [viewport.clone()]);
+    record(cmd_buffer_builder, &viewport);
+    cmd_buffer_builder.end_render_pass().unwrap();
+}
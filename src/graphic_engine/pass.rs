@@ -0,0 +1,197 @@
+//! Lightweight ordering of the passes recorded each frame.
+//!
+//! Every pass declares the images it reads and writes by name, and passes are recorded in an
+//! order where writers come before their readers. Image layout transitions and barriers between
+//! passes are inserted by vulkano's `AutoCommandBufferBuilder`, which tracks every resource used
+//! by the recorded commands.
+//!
+//! Limitations: images are never aliased, the schedule is linear (one command buffer, no async
+//! compute), and passes writing the same image keep the order they were added in.
+
+use std::{error::Error, fmt};
+
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+
+use super::frame::Frame;
+
+pub type PassRecordFn =
+    Box<dyn FnMut(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, &Frame)>;
+
+pub struct Pass {
+    name: String,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    record: PassRecordFn,
+}
+
+impl Pass {
+    pub fn new(
+        name: impl Into<String>,
+        record: impl FnMut(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, &Frame) + 'static,
+    ) -> Pass {
+        Pass {
+            name: name.into(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            record: Box::new(record),
+        }
+    }
+
+    pub fn reads(mut self, image: impl Into<String>) -> Pass {
+        self.inputs.push(image.into());
+        self
+    }
+
+    pub fn writes(mut self, image: impl Into<String>) -> Pass {
+        self.outputs.push(image.into());
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(super) fn record(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        frame: &Frame,
+    ) {
+        (self.record)(builder, frame)
+    }
+}
+
+/// Returned when the declared inputs and outputs form a cycle.
+#[derive(Debug)]
+pub struct PassCycleError {
+    pub passes: Vec<String>,
+}
+
+impl fmt::Display for PassCycleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Pass dependency cycle between: {}",
+            self.passes.join(", ")
+        )
+    }
+}
+
+impl Error for PassCycleError {}
+
+/// Returns pass indices in dependency order, keeping insertion order between independent passes.
+pub(super) fn sort_passes(passes: &[Pass]) -> Result<Vec<usize>, PassCycleError> {
+    let count = passes.len();
+    let mut dependencies = vec![Vec::new(); count];
+
+    for (later, pass) in passes.iter().enumerate() {
+        for (earlier, other) in passes.iter().enumerate() {
+            if earlier == later {
+                continue;
+            }
+            // reader after writer, unless both update the image in place
+            let reads_output = pass.inputs.iter().any(|image| {
+                other.outputs.contains(image) && (earlier < later || !pass.outputs.contains(image))
+            });
+            // writers of the same image keep their insertion order
+            let same_output = earlier < later
+                && pass
+                    .outputs
+                    .iter()
+                    .any(|image| other.outputs.contains(image));
+
+            if reads_output || same_output {
+                dependencies[later].push(earlier);
+            }
+        }
+    }
+
+    let mut order = Vec::with_capacity(count);
+    let mut scheduled = vec![false; count];
+
+    while order.len() < count {
+        // pick the first pass whose dependencies are all scheduled
+        let next = (0..count).find(|&i| {
+            !scheduled[i]
+                && dependencies[i]
+                    .iter()
+                    .all(|&dependency| scheduled[dependency])
+        });
+
+        match next {
+            Some(i) => {
+                scheduled[i] = true;
+                order.push(i);
+            }
+            None => {
+                return Err(PassCycleError {
+                    passes: (0..count)
+                        .filter(|&i| !scheduled[i])
+                        .map(|i| passes[i].name.clone())
+                        .collect(),
+                })
+            }
+        }
+    }
+
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pass(name: &str) -> Pass {
+        Pass::new(name, |_, _| {})
+    }
+
+    fn names(passes: &[Pass], order: &[usize]) -> Vec<String> {
+        order.iter().map(|&i| passes[i].name.clone()).collect()
+    }
+
+    #[test]
+    fn chain_is_sorted_from_writers_to_readers() {
+        let passes = [
+            pass("blur").reads("shadow").writes("blurred"),
+            pass("composite").reads("blurred"),
+            pass("shadow").writes("shadow"),
+        ];
+        let order = sort_passes(&passes).unwrap();
+        assert_eq!(names(&passes, &order), ["shadow", "blur", "composite"]);
+    }
+
+    #[test]
+    fn diamond_keeps_insertion_order_between_branches() {
+        let passes = [
+            pass("combine").reads("ao").reads("bloom"),
+            pass("bloom").reads("gbuffer").writes("bloom"),
+            pass("ao").reads("gbuffer").writes("ao"),
+            pass("gbuffer").writes("gbuffer"),
+        ];
+        let order = sort_passes(&passes).unwrap();
+        assert_eq!(
+            names(&passes, &order),
+            ["gbuffer", "bloom", "ao", "combine"]
+        );
+    }
+
+    #[test]
+    fn pass_updating_an_image_in_place_is_not_a_cycle() {
+        let passes = [
+            pass("sharpen").reads("color").writes("color"),
+            pass("grade").reads("color").writes("color"),
+        ];
+        let order = sort_passes(&passes).unwrap();
+        assert_eq!(names(&passes, &order), ["sharpen", "grade"]);
+    }
+
+    #[test]
+    fn two_pass_cycle_is_an_error() {
+        let passes = [
+            pass("independent").writes("other"),
+            pass("a").reads("b").writes("a"),
+            pass("b").reads("a").writes("b"),
+        ];
+        let error = sort_passes(&passes).unwrap_err();
+        assert_eq!(error.passes, ["a", "b"]);
+    }
+}
@@ -0,0 +1,59 @@
+//! Pipeline cache persisted to disk, so that pipelines built in a previous run don't compile
+//! their shaders again.
+
+use std::{fs, path::Path, sync::Arc};
+
+use vulkano::{device::Device, pipeline::cache::PipelineCache};
+
+use super::EngineError;
+
+// VkPipelineCacheHeaderVersionOne: header length, header version, vendor id, device id and
+// pipeline cache UUID
+const HEADER_SIZE: usize = 32;
+const HEADER_VERSION_ONE: u32 = 1;
+
+/// Loads the cache saved at `path`, or starts an empty one if there is none or it was written by
+/// another GPU or driver.
+pub(super) fn load(device: &Arc<Device>, path: &Path) -> Arc<PipelineCache> {
+    match fs::read(path) {
+        Ok(data) if is_compatible(device, &data) => {
+            // the header matches this device and driver, which validate the rest themselves
+            match unsafe { PipelineCache::with_data(device.clone(), &data) } {
+                Ok(cache) => return cache,
+                Err(e) => println!("Failed to load pipeline cache {}: {}", path.display(), e),
+            }
+        }
+        Ok(_) => println!(
+            "Pipeline cache {} is from another device or driver, starting a new one",
+            path.display()
+        ),
+        // no cache yet
+        Err(_) => {}
+    }
+
+    PipelineCache::empty(device.clone()).unwrap()
+}
+
+fn is_compatible(device: &Arc<Device>, data: &[u8]) -> bool {
+    if data.len() < HEADER_SIZE {
+        return false;
+    }
+    let word = |i: usize| u32::from_le_bytes(data[i * 4..i * 4 + 4].try_into().unwrap());
+
+    let properties = device.physical_device().properties();
+    word(0) as usize >= HEADER_SIZE
+        && word(1) == HEADER_VERSION_ONE
+        && word(2) == properties.vendor_id
+        && word(3) == properties.device_id
+        && data[16..32] == properties.pipeline_cache_uuid
+}
+
+pub(super) fn save(cache: &PipelineCache, path: &Path) -> Result<(), EngineError> {
+    let data = cache.get_data().map_err(EngineError::OutOfMemory)?;
+
+    // written next to the cache and renamed over it, so an interrupted save can't corrupt it
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, data)
+        .and_then(|()| fs::rename(&temp_path, path))
+        .map_err(EngineError::PipelineCacheWrite)
+}
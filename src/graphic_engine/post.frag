@@ -0,0 +1,111 @@
+#version 450
+
+// Post-processes drawing the main pass output to the swapchain image: tone mapping, followed by
+// FXAA when FXAA is defined. LINEAR_INPUT is defined for sRGB swapchains, which encode the output
+// themselves.
+
+layout(location = 0) in vec2 uv;
+layout(location = 0) out vec4 color;
+
+layout(set = 0, binding = 0) uniform texture2D source;
+layout(set = 0, binding = 1) uniform sampler source_sampler;
+
+// the operator, 0 passing colors through, see tonemap.rs
+layout(push_constant) uniform TonemapParams {
+    uint operator;
+    float exposure;
+    float curve;
+} params;
+
+vec3 tonemap(vec3 c) {
+    c *= params.exposure;
+    switch (params.operator) {
+    case 1:
+        // Reinhard
+        c = c / (1.0 + c);
+        break;
+    case 2:
+        // ACES filmic curve fitted by Krzysztof Narkowicz
+        c = clamp((c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14), 0.0, 1.0);
+        break;
+    case 3:
+        // exponential
+        c = 1.0 - exp(-c * params.curve);
+        break;
+    default:
+        // already in the encoding of the source
+        return c;
+    }
+#ifndef LINEAR_INPUT
+    // tone mapped colors are linear, and nothing else encodes them
+    c = max(c, vec3(0.0));
+    c = mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, step(vec3(0.0031308), c));
+#endif
+    return c;
+}
+
+#ifdef FXAA
+
+const float SPAN_MAX = 8.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float REDUCE_MIN = 1.0 / 128.0;
+
+// tone mapped before filtering, so edges are found and blended in display range
+vec3 fetch(vec2 at) {
+    return tonemap(texture(sampler2D(source, source_sampler), at).rgb);
+}
+
+// edges are found on perceptual luma, so the linear colors sRGB swapchains encode are re-encoded
+float luma(vec3 rgb) {
+    float l = dot(rgb, vec3(0.299, 0.587, 0.114));
+#ifdef LINEAR_INPUT
+    return sqrt(l);
+#else
+    return l;
+#endif
+}
+
+void main() {
+    vec2 t = 1.0 / vec2(textureSize(sampler2D(source, source_sampler), 0));
+    vec3 rgb_nw = fetch(uv + vec2(-1.0, -1.0) * t);
+    vec3 rgb_ne = fetch(uv + vec2(1.0, -1.0) * t);
+    vec3 rgb_sw = fetch(uv + vec2(-1.0, 1.0) * t);
+    vec3 rgb_se = fetch(uv + vec2(1.0, 1.0) * t);
+    vec3 rgb_m = fetch(uv);
+
+    float luma_nw = luma(rgb_nw);
+    float luma_ne = luma(rgb_ne);
+    float luma_sw = luma(rgb_sw);
+    float luma_se = luma(rgb_se);
+    float luma_m = luma(rgb_m);
+    float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // blur along the edge, perpendicular to the luma gradient
+    vec2 dir = vec2(
+        (luma_sw + luma_se) - (luma_nw + luma_ne),
+        (luma_nw + luma_sw) - (luma_ne + luma_se));
+    float dir_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float rcp_dir_min = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+    dir = clamp(dir * rcp_dir_min, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * t;
+
+    vec3 rgb_a = 0.5 * (
+        fetch(uv + dir * (1.0 / 3.0 - 0.5)) +
+        fetch(uv + dir * (2.0 / 3.0 - 0.5)));
+    vec3 rgb_b = rgb_a * 0.5 + 0.25 * (
+        fetch(uv + dir * -0.5) +
+        fetch(uv + dir * 0.5));
+
+    // the wider blur overshot the local contrast, keep the narrow one
+    float luma_b = luma(rgb_b);
+    color = vec4(luma_b < luma_min || luma_b > luma_max ? rgb_a : rgb_b, 1.0);
+}
+
+#else
+
+void main() {
+    vec4 hdr = texture(sampler2D(source, source_sampler), uv);
+    color = vec4(tonemap(hdr.rgb), hdr.a);
+}
+
+#endif
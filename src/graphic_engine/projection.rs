@@ -0,0 +1,149 @@
+//! Perspective projection kept in sync with the swapchain aspect ratio.
+//!
+//! Matrices are column-major (`m[column][row]`), matching GLSL `mat4` uniforms, and target Vulkan
+//! clip space: y pointing down and depth in `0.0..=1.0`.
+
+pub type Matrix4 = [[f32; 4]; 4];
+
+pub(super) const IDENTITY: Matrix4 = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Projection {
+    /// Vertical field of view, in radians.
+    pub fov_y: f32,
+    pub near: f32,
+    /// May be `f32::INFINITY`, which suits reverse Z best as floating point depth keeps its
+    /// precision towards 0.0.
+    pub far: f32,
+    /// Maps `near` to depth 1.0 and `far` to 0.0, for better precision far away. Use with a
+    /// `Greater` depth test, as `Graphicengine::depth_stencil_state` sets, and a depth cleared to
+    /// 0.0.
+    pub reverse_z: bool,
+}
+
+impl Default for Projection {
+    fn default() -> Projection {
+        Projection {
+            fov_y: std::f32::consts::FRAC_PI_4,
+            near: 0.1,
+            far: 100.0,
+            reverse_z: false,
+        }
+    }
+}
+
+impl Projection {
+    pub fn matrix(&self, aspect: f32) -> Matrix4 {
+        let f = 1.0 / (self.fov_y / 2.0).tan();
+        // the finite terms tend to these as `far` grows, but evaluate to NaN at infinity
+        let (depth, offset) = match (self.reverse_z, self.far.is_infinite()) {
+            (true, true) => (0.0, self.near),
+            (true, false) => {
+                let depth = self.near / (self.far - self.near);
+                (depth, depth * self.far)
+            }
+            (false, true) => (-1.0, -self.near),
+            (false, false) => {
+                let depth = self.far / (self.near - self.far);
+                (depth, depth * self.near)
+            }
+        };
+
+        [
+            [f / aspect, 0.0, 0.0, 0.0],
+            [0.0, -f, 0.0, 0.0],
+            [0.0, 0.0, depth, -1.0],
+            [0.0, 0.0, offset, 0.0],
+        ]
+    }
+}
+
+pub(super) fn aspect_ratio(extent: [u32; 2]) -> f32 {
+    // a minimized window has a zero height
+    extent[0] as f32 / extent[1].max(1) as f32
+}
+
+pub(super) fn multiply(a: &Matrix4, b: &Matrix4) -> Matrix4 {
+    let mut result = [[0.0; 4]; 4];
+    for (column, result_column) in result.iter_mut().enumerate() {
+        for (row, value) in result_column.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b[column][k]).sum();
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // depth of a point `distance` in front of the camera, which looks down -z
+    fn depth_at(m: &Matrix4, distance: f32) -> f32 {
+        let z = -distance;
+        (m[2][2] * z + m[3][2]) / (m[2][3] * z)
+    }
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn aspect_ratio_follows_extent() {
+        assert_near(aspect_ratio([1920, 1080]), 16.0 / 9.0);
+        assert_near(aspect_ratio([600, 800]), 0.75);
+        // minimized
+        assert_near(aspect_ratio([640, 0]), 640.0);
+
+        let projection = Projection::default();
+        let wide = projection.matrix(aspect_ratio([1920, 1080]));
+        let tall = projection.matrix(aspect_ratio([600, 800]));
+        assert_near(wide[1][1], tall[1][1]);
+        assert_near(wide[0][0] * 16.0 / 9.0, tall[0][0] * 0.75);
+    }
+
+    #[test]
+    fn depth_range() {
+        let projection = Projection::default();
+        let m = projection.matrix(1.0);
+        assert_near(depth_at(&m, projection.near), 0.0);
+        assert_near(depth_at(&m, projection.far), 1.0);
+
+        let m = Projection {
+            reverse_z: true,
+            ..projection
+        }
+        .matrix(1.0);
+        assert_near(depth_at(&m, projection.near), 1.0);
+        assert_near(depth_at(&m, projection.far), 0.0);
+    }
+
+    #[test]
+    fn infinite_far() {
+        let projection = Projection {
+            far: f32::INFINITY,
+            reverse_z: true,
+            ..Projection::default()
+        };
+        let m = projection.matrix(1.0);
+        assert_eq!(m[2][2], 0.0);
+        assert_eq!(m[2][3], -1.0);
+        assert_eq!(m[3][2], projection.near);
+        assert_near(depth_at(&m, projection.near), 1.0);
+        assert_near(depth_at(&m, 1e9), 0.0);
+
+        let m = Projection {
+            reverse_z: false,
+            ..projection
+        }
+        .matrix(1.0);
+        assert_eq!(m[2][2], -1.0);
+        assert_eq!(m[3][2], -projection.near);
+        assert_near(depth_at(&m, projection.near), 0.0);
+        assert_near(depth_at(&m, 1e9), 1.0);
+    }
+}
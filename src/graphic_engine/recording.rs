@@ -0,0 +1,125 @@
+//! Capture of presented frames into a numbered PNG sequence, e.g. to assemble a clip with ffmpeg.
+//!
+//! Each captured frame is copied from its swapchain image into its own host-visible buffer by the
+//! frame's command buffer, like a screenshot. The buffer is read once the GPU has released it,
+//! checked at the start of each later frame so rendering never waits for it, and its pixels are
+//! converted and encoded on a writer thread.
+
+use std::{
+    collections::VecDeque,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use vulkano::{buffer::CpuAccessibleBuffer, format::Format, swapchain::ColorSpace};
+
+use super::screenshot::{self, ScreenshotError};
+
+/// How a captured swapchain image is stored, to convert it like a screenshot.
+#[derive(Clone, Copy)]
+pub(super) struct FrameLayout {
+    pub extent: [u32; 2],
+    pub format: Format,
+    pub color_space: ColorSpace,
+    pub opaque: bool,
+}
+
+struct CapturedFrame {
+    layout: FrameLayout,
+    data: Vec<u8>,
+}
+
+pub(super) struct Recording {
+    frame_interval: Duration,
+    // in frame clock time
+    next_capture: Duration,
+    // copies the GPU may still be writing, oldest first
+    pending: VecDeque<(FrameLayout, Arc<CpuAccessibleBuffer<[u8]>>)>,
+    frames: Sender<CapturedFrame>,
+    // returns the number of written frames, or the first error, which stops it
+    writer: JoinHandle<Result<u32, ScreenshotError>>,
+}
+
+impl Recording {
+    pub fn new(directory: &Path, fps: f32, time: Duration) -> Result<Recording, ScreenshotError> {
+        fs::create_dir_all(directory)?;
+
+        let directory = directory.to_path_buf();
+        let (frames, received) = mpsc::channel::<CapturedFrame>();
+        let writer = thread::spawn(move || {
+            let mut count = 0;
+            for frame in received {
+                screenshot::save_png(
+                    &frame_path(&directory, count),
+                    frame.layout.extent,
+                    frame.layout.format,
+                    frame.layout.color_space,
+                    frame.layout.opaque,
+                    &frame.data,
+                )?;
+                count += 1;
+            }
+            Ok(count)
+        });
+
+        Ok(Recording {
+            frame_interval: Duration::from_secs_f32(1.0 / fps.max(f32::EPSILON)),
+            next_capture: time,
+            pending: VecDeque::new(),
+            frames,
+            writer,
+        })
+    }
+
+    /// Whether a frame presented at `time` of the frame clock is due, at most one per frame
+    /// interval.
+    pub fn capture_due(&mut self, time: Duration) -> bool {
+        // a fixed frame time of one interval captures every frame despite rounding
+        if time + self.frame_interval / 100 < self.next_capture {
+            return false;
+        }
+        // rendering slower than the recording rate captures every frame, without catching up
+        self.next_capture = (self.next_capture + self.frame_interval).max(time);
+        true
+    }
+
+    /// Takes the copy of a frame that was submitted.
+    pub fn push(&mut self, layout: FrameLayout, buffer: Arc<CpuAccessibleBuffer<[u8]>>) {
+        self.pending.push_back((layout, buffer));
+    }
+
+    /// Hands the copies the GPU is done with to the writer, in capture order.
+    pub fn poll(&mut self) {
+        while let Some((layout, buffer)) = self.pending.front() {
+            let Ok(data) = buffer.read() else {
+                break;
+            };
+            let frame = CapturedFrame {
+                layout: *layout,
+                data: data.to_vec(),
+            };
+            drop(data);
+            self.pending.pop_front();
+            // a failed writer reports its error in finish
+            let _ = self.frames.send(frame);
+        }
+    }
+
+    /// Writes the remaining frames, which must all have been executed, and returns how many were
+    /// written in total.
+    pub fn finish(mut self) -> Result<u32, ScreenshotError> {
+        self.poll();
+        drop(self.frames);
+        self.writer.join().unwrap()
+    }
+}
+
+fn frame_path(directory: &Path, index: u32) -> PathBuf {
+    directory.join(format!("frame_{:05}.png", index))
+}
@@ -0,0 +1,175 @@
+//! Offscreen color targets, with an optional depth attachment, rendered into before the frame and
+//! sampled by it, e.g. for mirrors or picture-in-picture.
+//!
+//! Attachments stay in their attachment layout outside the render pass, and vulkano's
+//! `AutoCommandBufferBuilder` transitions the color image to a shader readable layout when a
+//! descriptor set samples it, and back when it is rendered into again.
+
+use std::sync::Arc;
+
+use vulkano::{
+    command_buffer::{
+        AutoCommandBufferBuilder, PrimaryAutoCommandBuffer, RenderPassBeginInfo, SubpassContents,
+    },
+    device::Device,
+    format::{ClearValue, Format},
+    image::{view::ImageView, AttachmentImage, ImageLayout, ImageUsage},
+    memory::allocator::StandardMemoryAllocator,
+    pipeline::graphics::viewport::Viewport,
+    render_pass::{
+        AttachmentDescription, AttachmentReference, Framebuffer, FramebufferCreateInfo, LoadOp,
+        RenderPass, RenderPassCreateInfo, StoreOp, Subpass, SubpassDescription,
+    },
+};
+
+use super::EngineError;
+
+/// Identifies a render texture of the engine that created it, see
+/// `Graphicengine::create_render_texture`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RenderTextureHandle(pub(super) usize);
+
+pub(super) struct RenderTexture {
+    render_pass: Arc<RenderPass>,
+    framebuffer: Arc<Framebuffer>,
+    color_view: Arc<ImageView<AttachmentImage>>,
+}
+
+impl RenderTexture {
+    pub fn new(
+        device: &Arc<Device>,
+        memory_allocator: &StandardMemoryAllocator,
+        extent: [u32; 2],
+        format: Format,
+        depth_format: Option<Format>,
+    ) -> Result<RenderTexture, EngineError> {
+        let image_size =
+            |format: Format| extent[0] as u64 * extent[1] as u64 * format.block_size().unwrap_or(0);
+
+        let mut attachments = vec![AttachmentDescription {
+            format: Some(format),
+            load_op: LoadOp::Clear,
+            store_op: StoreOp::Store,
+            initial_layout: ImageLayout::ColorAttachmentOptimal,
+            final_layout: ImageLayout::ColorAttachmentOptimal,
+            ..Default::default()
+        }];
+        // only used while rendering, so never stored
+        attachments.extend(depth_format.map(|format| AttachmentDescription {
+            format: Some(format),
+            load_op: LoadOp::Clear,
+            store_op: StoreOp::DontCare,
+            initial_layout: ImageLayout::DepthStencilAttachmentOptimal,
+            final_layout: ImageLayout::DepthStencilAttachmentOptimal,
+            ..Default::default()
+        }));
+        let render_pass = RenderPass::new(
+            device.clone(),
+            RenderPassCreateInfo {
+                attachments,
+                subpasses: vec![SubpassDescription {
+                    color_attachments: vec![Some(AttachmentReference {
+                        attachment: 0,
+                        layout: ImageLayout::ColorAttachmentOptimal,
+                        ..Default::default()
+                    })],
+                    depth_stencil_attachment: depth_format.map(|_| AttachmentReference {
+                        attachment: 1,
+                        layout: ImageLayout::DepthStencilAttachmentOptimal,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let color_view = ImageView::new_default(
+            AttachmentImage::with_usage(
+                memory_allocator,
+                extent,
+                format,
+                ImageUsage {
+                    color_attachment: true,
+                    sampled: true,
+                    ..ImageUsage::empty()
+                },
+            )
+            .map_err(EngineError::image_allocation(image_size(format)))?,
+        )
+        .unwrap();
+        let mut views = vec![color_view.clone()];
+        if let Some(format) = depth_format {
+            let depth_view = ImageView::new_default(
+                AttachmentImage::with_usage(
+                    memory_allocator,
+                    extent,
+                    format,
+                    ImageUsage {
+                        depth_stencil_attachment: true,
+                        transient_attachment: true,
+                        ..ImageUsage::empty()
+                    },
+                )
+                .map_err(EngineError::image_allocation(image_size(format)))?,
+            )
+            .unwrap();
+            views.push(depth_view);
+        }
+        let framebuffer = Framebuffer::new(
+            render_pass.clone(),
+            FramebufferCreateInfo {
+                attachments: views.into_iter().map(|view| view as _).collect(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        Ok(RenderTexture {
+            render_pass,
+            framebuffer,
+            color_view,
+        })
+    }
+
+    pub fn subpass(&self) -> Subpass {
+        Subpass::from(self.render_pass.clone(), 0).unwrap()
+    }
+
+    pub fn color_view(&self) -> &Arc<ImageView<AttachmentImage>> {
+        &self.color_view
+    }
+
+    /// Records the render pass, with `record` drawing inside it.
+    pub fn record(
+        &self,
+        cmd_buffer_builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        clear_values: Vec<Option<ClearValue>>,
+        record: impl FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, &Viewport),
+    ) {
+        let [width, height] = self.framebuffer.extent();
+        let viewport = Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [width as f32, height as f32],
+            depth_range: 0.0..1.0,
+        };
+
+        cmd_buffer_builder
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    // the depth clear value is dropped without a depth attachment
+                    clear_values: clear_values
+                        .into_iter()
+                        .take(self.render_pass.attachments().len())
+                        .collect(),
+                    ..RenderPassBeginInfo::framebuffer(self.framebuffer.clone())
+                },
+                SubpassContents::Inline,
+            )
+            .unwrap()
+            .set_viewport(0, [viewport.clone()]);
+        record(cmd_buffer_builder, &viewport);
+        cmd_buffer_builder.end_render_pass().unwrap();
+    }
+}
@@ -0,0 +1,131 @@
+use std::{str::FromStr, sync::Arc};
+
+use vulkano::{
+    device::Device,
+    pipeline::graphics::depth_stencil::CompareOp,
+    sampler::{
+        BorderColor, Filter, Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerMipmapMode,
+        LOD_CLAMP_NONE,
+    },
+};
+
+// upper bound for the anisotropic preset, even on devices allowing more
+const MAX_ANISOTROPY: f32 = 16.0;
+
+/// Named sampler configurations, built once and shared by every texture using them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SamplerPreset {
+    NearestClamp,
+    LinearRepeat,
+    #[default]
+    LinearMipmapRepeatAniso,
+    /// Depth comparison for `sampler2DShadow` lookups, texels outside the map count as lit.
+    NearestCompareLess,
+    /// Like `NearestCompareLess` with hardware PCF, only for depth formats supporting linear
+    /// filtering.
+    LinearCompareLess,
+}
+
+impl FromStr for SamplerPreset {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<SamplerPreset, String> {
+        match name {
+            "nearest_clamp" => Ok(SamplerPreset::NearestClamp),
+            "linear_repeat" => Ok(SamplerPreset::LinearRepeat),
+            "linear_mipmap_repeat_aniso" => Ok(SamplerPreset::LinearMipmapRepeatAniso),
+            "nearest_compare_less" => Ok(SamplerPreset::NearestCompareLess),
+            "linear_compare_less" => Ok(SamplerPreset::LinearCompareLess),
+            _ => Err(format!("Unknown sampler preset: {}", name)),
+        }
+    }
+}
+
+pub(super) struct SamplerCache {
+    nearest_clamp: Arc<Sampler>,
+    linear_repeat: Arc<Sampler>,
+    linear_mipmap_repeat_aniso: Arc<Sampler>,
+    nearest_compare_less: Arc<Sampler>,
+    linear_compare_less: Arc<Sampler>,
+}
+
+impl SamplerCache {
+    pub fn new(device: &Arc<Device>) -> SamplerCache {
+        // anisotropy is only used when the feature was enabled on the device
+        let anisotropy = if device.enabled_features().sampler_anisotropy {
+            Some(
+                device
+                    .physical_device()
+                    .properties()
+                    .max_sampler_anisotropy
+                    .min(MAX_ANISOTROPY),
+            )
+        } else {
+            None
+        };
+
+        let nearest_clamp = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Nearest,
+                min_filter: Filter::Nearest,
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let linear_repeat = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo::simple_repeat_linear_no_mipmap(),
+        )
+        .unwrap();
+
+        let linear_mipmap_repeat_aniso = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                mipmap_mode: SamplerMipmapMode::Linear,
+                address_mode: [SamplerAddressMode::Repeat; 3],
+                anisotropy,
+                lod: 0.0..=LOD_CLAMP_NONE,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let compare_less = |filter| {
+            Sampler::new(
+                device.clone(),
+                SamplerCreateInfo {
+                    mag_filter: filter,
+                    min_filter: filter,
+                    address_mode: [SamplerAddressMode::ClampToBorder; 3],
+                    border_color: BorderColor::FloatOpaqueWhite,
+                    compare: Some(CompareOp::Less),
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+
+        SamplerCache {
+            nearest_clamp,
+            linear_repeat,
+            linear_mipmap_repeat_aniso,
+            nearest_compare_less: compare_less(Filter::Nearest),
+            linear_compare_less: compare_less(Filter::Linear),
+        }
+    }
+
+    pub fn get(&self, preset: SamplerPreset) -> Arc<Sampler> {
+        match preset {
+            SamplerPreset::NearestClamp => self.nearest_clamp.clone(),
+            SamplerPreset::LinearRepeat => self.linear_repeat.clone(),
+            SamplerPreset::LinearMipmapRepeatAniso => self.linear_mipmap_repeat_aniso.clone(),
+            SamplerPreset::NearestCompareLess => self.nearest_compare_less.clone(),
+            SamplerPreset::LinearCompareLess => self.linear_compare_less.clone(),
+        }
+    }
+}
@@ -0,0 +1,292 @@
+//! Conversion of captured swapchain images to 8-bit sRGB PNG files.
+//!
+//! The presentation engine interprets the stored values in the swapchain color space, so they are
+//! converted according to that color space rather than according to the format alone: `SRGB` and
+//! `UNORM` formats in a non-linear color space already hold display-encoded values and are
+//! written unchanged, while linear color spaces are encoded to sRGB and HDR10 (PQ) is tonemapped.
+
+use std::{error::Error, fmt, fs::File, io, io::BufWriter, path::Path};
+
+use vulkano::{format::Format, swapchain::ColorSpace};
+
+// luminance mapped to 1.0 when tonemapping HDR10 (BT.2408 reference white)
+const HDR_REFERENCE_WHITE_NITS: f32 = 203.0;
+
+#[derive(Debug)]
+pub enum ScreenshotError {
+    UnsupportedFormat(Format),
+    /// The swapchain images weren't created with the `transfer_src` usage.
+    SwapchainNotCopyable,
+    Io(io::Error),
+    Encoding(png::EncodingError),
+}
+
+impl fmt::Display for ScreenshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScreenshotError::UnsupportedFormat(format) => {
+                write!(
+                    f,
+                    "Unsupported swapchain format for screenshots: {:?}",
+                    format
+                )
+            }
+            ScreenshotError::SwapchainNotCopyable => write!(f, "Swapchain images can't be copied"),
+            ScreenshotError::Io(e) => write!(f, "Failed to write screenshot: {}", e),
+            ScreenshotError::Encoding(e) => write!(f, "Failed to encode screenshot: {}", e),
+        }
+    }
+}
+
+impl Error for ScreenshotError {}
+
+impl From<io::Error> for ScreenshotError {
+    fn from(e: io::Error) -> ScreenshotError {
+        ScreenshotError::Io(e)
+    }
+}
+
+impl From<png::EncodingError> for ScreenshotError {
+    fn from(e: png::EncodingError) -> ScreenshotError {
+        ScreenshotError::Encoding(e)
+    }
+}
+
+pub(super) fn save_png(
+    path: &Path,
+    extent: [u32; 2],
+    format: Format,
+    color_space: ColorSpace,
+    opaque: bool,
+    data: &[u8],
+) -> Result<(), ScreenshotError> {
+    let pixels = to_rgba8(format, color_space, opaque, data)?;
+
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), extent[0], extent[1]);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&pixels)?;
+
+    Ok(())
+}
+
+/// Converts tightly packed pixels of a swapchain image to 8-bit sRGB RGBA.
+fn to_rgba8(
+    format: Format,
+    color_space: ColorSpace,
+    opaque: bool,
+    data: &[u8],
+) -> Result<Vec<u8>, ScreenshotError> {
+    let block_size = match format {
+        Format::R8G8B8A8_UNORM
+        | Format::R8G8B8A8_SRGB
+        | Format::B8G8R8A8_UNORM
+        | Format::B8G8R8A8_SRGB
+        | Format::A2B10G10R10_UNORM_PACK32
+        | Format::A2R10G10B10_UNORM_PACK32 => 4,
+        Format::R16G16B16A16_SFLOAT => 8,
+        _ => return Err(ScreenshotError::UnsupportedFormat(format)),
+    };
+
+    let mut pixels = Vec::with_capacity(data.len() / block_size * 4);
+    for texel in data.chunks_exact(block_size) {
+        let [r, g, b, a] = decode_texel(format, texel);
+        let [r, g, b] = encode_color_space(color_space, [r, g, b]);
+        let a = if opaque { 1.0 } else { a };
+        pixels.extend([r, g, b, a].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8));
+    }
+
+    Ok(pixels)
+}
+
+// normalized channel values, in RGBA order
+fn decode_texel(format: Format, texel: &[u8]) -> [f32; 4] {
+    let unorm8 = |i: usize| texel[i] as f32 / 255.0;
+    let packed = || u32::from_le_bytes([texel[0], texel[1], texel[2], texel[3]]);
+    let unorm10 = |value: u32, shift: u32| ((value >> shift) & 0x3ff) as f32 / 1023.0;
+    let alpha2 = |value: u32| (value >> 30) as f32 / 3.0;
+
+    match format {
+        Format::B8G8R8A8_UNORM | Format::B8G8R8A8_SRGB => {
+            [unorm8(2), unorm8(1), unorm8(0), unorm8(3)]
+        }
+        Format::A2B10G10R10_UNORM_PACK32 => {
+            let value = packed();
+            [
+                unorm10(value, 0),
+                unorm10(value, 10),
+                unorm10(value, 20),
+                alpha2(value),
+            ]
+        }
+        Format::A2R10G10B10_UNORM_PACK32 => {
+            let value = packed();
+            [
+                unorm10(value, 20),
+                unorm10(value, 10),
+                unorm10(value, 0),
+                alpha2(value),
+            ]
+        }
+        Format::R16G16B16A16_SFLOAT => {
+            let half = |i: usize| f16_to_f32(u16::from_le_bytes([texel[i], texel[i + 1]]));
+            [half(0), half(2), half(4), half(6)]
+        }
+        _ => [unorm8(0), unorm8(1), unorm8(2), unorm8(3)],
+    }
+}
+
+fn encode_color_space(color_space: ColorSpace, rgb: [f32; 3]) -> [f32; 3] {
+    match color_space {
+        ColorSpace::Bt709Linear
+        | ColorSpace::DisplayP3Linear
+        | ColorSpace::Bt2020Linear
+        | ColorSpace::AdobeRgbLinear => rgb.map(linear_to_srgb),
+        // scRGB values may go above 1.0
+        ColorSpace::ExtendedSrgbLinear => rgb.map(|c| linear_to_srgb(tonemap(c))),
+        ColorSpace::Hdr10St2084 => {
+            let linear = rgb.map(|c| pq_to_nits(c) / HDR_REFERENCE_WHITE_NITS);
+            bt2020_to_bt709(linear).map(|c| linear_to_srgb(tonemap(c)))
+        }
+        // values are already encoded for the display
+        _ => rgb,
+    }
+}
+
+// identity below the knee, then compresses everything above it into the remaining range
+fn tonemap(c: f32) -> f32 {
+    const KNEE: f32 = 0.8;
+
+    if c <= KNEE {
+        c
+    } else {
+        let x = (c - KNEE) / (1.0 - KNEE);
+        KNEE + (1.0 - KNEE) * x / (1.0 + x)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// SMPTE ST 2084 EOTF
+fn pq_to_nits(c: f32) -> f32 {
+    const M1: f32 = 2610.0 / 16384.0;
+    const M2: f32 = 2523.0 / 4096.0 * 128.0;
+    const C1: f32 = 3424.0 / 4096.0;
+    const C2: f32 = 2413.0 / 4096.0 * 32.0;
+    const C3: f32 = 2392.0 / 4096.0 * 32.0;
+
+    let p = c.max(0.0).powf(1.0 / M2);
+    10000.0 * ((p - C1).max(0.0) / (C2 - C3 * p)).powf(1.0 / M1)
+}
+
+fn bt2020_to_bt709([r, g, b]: [f32; 3]) -> [f32; 3] {
+    [
+        1.6605 * r - 0.5876 * g - 0.0728 * b,
+        -0.1246 * r + 1.1329 * g - 0.0083 * b,
+        -0.0182 * r - 0.1006 * g + 1.1187 * b,
+    ]
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bgra8_is_swizzled_to_rgba() {
+        let data = [10, 20, 30, 40, 50, 60, 70, 80];
+        let pixels = to_rgba8(
+            Format::B8G8R8A8_UNORM,
+            ColorSpace::SrgbNonLinear,
+            false,
+            &data,
+        )
+        .unwrap();
+        assert_eq!(pixels, [30, 20, 10, 40, 70, 60, 50, 80]);
+
+        let pixels = to_rgba8(
+            Format::B8G8R8A8_UNORM,
+            ColorSpace::SrgbNonLinear,
+            true,
+            &data,
+        )
+        .unwrap();
+        assert_eq!(pixels, [30, 20, 10, 255, 70, 60, 50, 255]);
+    }
+
+    #[test]
+    fn srgb_is_kept_and_linear_encoded() {
+        let data = [0, 128, 255, 255];
+        let pixels = to_rgba8(
+            Format::R8G8B8A8_SRGB,
+            ColorSpace::SrgbNonLinear,
+            false,
+            &data,
+        )
+        .unwrap();
+        assert_eq!(pixels, data);
+
+        let pixels = to_rgba8(
+            Format::R8G8B8A8_UNORM,
+            ColorSpace::Bt709Linear,
+            false,
+            &data,
+        )
+        .unwrap();
+        assert_eq!(pixels, [0, 188, 255, 255]);
+    }
+
+    #[test]
+    fn float16_is_tonemapped_and_encoded() {
+        // 0.25, 1.0, 2.0 and an alpha of 0.5
+        let data = [0x3400u16, 0x3c00, 0x4000, 0x3800]
+            .iter()
+            .flat_map(|half| half.to_le_bytes())
+            .collect::<Vec<_>>();
+        let pixels = to_rgba8(
+            Format::R16G16B16A16_SFLOAT,
+            ColorSpace::ExtendedSrgbLinear,
+            false,
+            &data,
+        )
+        .unwrap();
+        assert_eq!(pixels, [137, 243, 252, 128]);
+    }
+
+    #[test]
+    fn display_encoded_color_space_is_unchanged() {
+        let rgb = [0.1, 0.5, 0.9];
+        assert_eq!(encode_color_space(ColorSpace::SrgbNonLinear, rgb), rgb);
+        assert_eq!(
+            encode_color_space(ColorSpace::Bt709Linear, [0.0, 1.0, 2.0]),
+            [0.0, linear_to_srgb(1.0), linear_to_srgb(1.0)]
+        );
+    }
+
+    #[test]
+    fn unsupported_format_is_rejected() {
+        assert!(matches!(
+            to_rgba8(Format::R8_UNORM, ColorSpace::SrgbNonLinear, false, &[0]),
+            Err(ScreenshotError::UnsupportedFormat(Format::R8_UNORM))
+        ));
+    }
+}
@@ -0,0 +1,50 @@
+//! Shaders compiled from files at build time.
+//!
+//! `build.rs` compiles every `.vert`, `.frag` and `.comp` GLSL file of the `shaders/` directory
+//! at the crate root to SPIR-V and embeds it in the binary, so shaders can be edited as files but
+//! ship inside the executable. Load them by file name, e.g. `engine.embedded_shader("sky.frag")`.
+//! Compilation errors fail the build, and cargo rebuilds whenever the directory changes.
+//!
+//! Shaders given inline with `vulkano_shaders::shader!` are unaffected.
+
+use std::sync::Arc;
+
+use vulkano::{device::Device, shader::ShaderModule};
+
+use super::EngineError;
+
+include!(concat!(env!("OUT_DIR"), "/embedded_shaders.rs"));
+
+pub(super) fn names() -> impl Iterator<Item = &'static str> {
+    EMBEDDED_SHADERS.iter().map(|&(name, _)| name)
+}
+
+pub(super) fn load(device: &Arc<Device>, name: &str) -> Result<Arc<ShaderModule>, EngineError> {
+    let &(_, bytes) = EMBEDDED_SHADERS
+        .iter()
+        .find(|&&(shader_name, _)| shader_name == name)
+        .ok_or_else(|| EngineError::UnknownShader(name.to_owned()))?;
+
+    // embedded bytes have no alignment guarantee, so the words are copied out
+    let words: Vec<u32> = bytes
+        .chunks_exact(4)
+        .map(|word| u32::from_ne_bytes([word[0], word[1], word[2], word[3]]))
+        .collect();
+
+    // the SPIR-V comes from shaderc and is validated by vulkano when parsed
+    unsafe { ShaderModule::from_words(device.clone(), &words) }.map_err(EngineError::Shader)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embeds_the_shaders_directory() {
+        assert!(names().any(|name| name == "fullscreen.vert"));
+        for &(_, bytes) in EMBEDDED_SHADERS {
+            // SPIR-V magic number
+            assert_eq!(bytes[..4], 0x0723_0203u32.to_ne_bytes());
+        }
+    }
+}
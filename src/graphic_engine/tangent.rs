@@ -0,0 +1,112 @@
+//! Per-vertex tangents for normal mapping, for meshes that don't come with them.
+//!
+//! The tangent and the sign in `w` give the tangent space basis a fragment shader perturbs the
+//! normal in, with a normal map sampled in `[0, 1]`:
+//!
+//! ```glsl
+//! vec3 n = normalize(normal);
+//! vec3 t = normalize(tangent.xyz - n * dot(n, tangent.xyz));
+//! vec3 b = cross(n, t) * tangent.w;
+//! vec3 mapped = texture(normal_map, uv).xyz * 2.0 - 1.0;
+//! vec3 shading_normal = normalize(mat3(t, b, n) * mapped);
+//! ```
+
+type Vec3 = [f32; 3];
+
+// triangles with a smaller UV area have no usable tangent direction
+const MIN_UV_AREA: f32 = 1e-12;
+
+/// Computes a tangent per vertex from indexed triangles, `xyz` along increasing U and `w` the
+/// handedness of the bitangent (`1.0` or `-1.0`).
+///
+/// Tangents are averaged over the triangles sharing the vertex and made orthogonal to its
+/// normal. Triangles with collinear UVs or an out of range index are skipped, and vertices left
+/// without a direction get an arbitrary tangent orthogonal to their normal.
+pub fn generate_tangents(
+    positions: &[Vec3],
+    normals: &[Vec3],
+    uvs: &[[f32; 2]],
+    indices: &[u32],
+) -> Vec<[f32; 4]> {
+    let vertex_count = positions.len().min(normals.len()).min(uvs.len());
+    let mut tangents = vec![[0.0; 3]; vertex_count];
+    let mut bitangents = vec![[0.0; 3]; vertex_count];
+
+    for triangle in indices.chunks_exact(3) {
+        let [i0, i1, i2] = [0, 1, 2].map(|i| triangle[i] as usize);
+        if i0.max(i1).max(i2) >= vertex_count {
+            continue;
+        }
+
+        let e1 = sub(positions[i1], positions[i0]);
+        let e2 = sub(positions[i2], positions[i0]);
+        let [du1, dv1] = [uvs[i1][0] - uvs[i0][0], uvs[i1][1] - uvs[i0][1]];
+        let [du2, dv2] = [uvs[i2][0] - uvs[i0][0], uvs[i2][1] - uvs[i0][1]];
+        let determinant = du1 * dv2 - du2 * dv1;
+        if determinant.abs() < MIN_UV_AREA {
+            continue;
+        }
+
+        let r = 1.0 / determinant;
+        let tangent = scale(sub(scale(e1, dv2), scale(e2, dv1)), r);
+        let bitangent = scale(sub(scale(e2, du1), scale(e1, du2)), r);
+        for i in [i0, i1, i2] {
+            tangents[i] = add(tangents[i], tangent);
+            bitangents[i] = add(bitangents[i], bitangent);
+        }
+    }
+
+    (0..vertex_count)
+        .map(|i| {
+            let normal = normalize(normals[i]).unwrap_or([0.0, 0.0, 1.0]);
+            // Gram-Schmidt, falling back to any direction orthogonal to the normal
+            let tangent = normalize(sub(tangents[i], scale(normal, dot(normal, tangents[i]))))
+                .unwrap_or_else(|| orthogonal(normal));
+            let handedness = if dot(cross(normal, tangent), bitangents[i]) < 0.0 {
+                -1.0
+            } else {
+                1.0
+            };
+            [tangent[0], tangent[1], tangent[2], handedness]
+        })
+        .collect()
+}
+
+fn add(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: Vec3, s: f32) -> Vec3 {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+fn dot(a: Vec3, b: Vec3) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(a: Vec3) -> Option<Vec3> {
+    let length = dot(a, a).sqrt();
+    (length > f32::EPSILON).then(|| scale(a, 1.0 / length))
+}
+
+// a unit vector orthogonal to the unit vector `n`
+fn orthogonal(n: Vec3) -> Vec3 {
+    let axis = if n[0].abs() < 0.9 {
+        [1.0, 0.0, 0.0]
+    } else {
+        [0.0, 1.0, 0.0]
+    };
+    normalize(cross(n, axis)).unwrap()
+}
@@ -0,0 +1,527 @@
+//! Text drawn from a signed distance field glyph atlas, which stays sharp at any scale.
+//!
+//! The atlas is a PNG holding the distance to the glyph edge in its first channel, 0.5 on the
+//! edge and above inside, as generated by e.g. Hiero or msdf-bmfont in single-channel mode. The
+//! metrics are the matching BMFont text file (`.fnt`). Edges are smoothed over about one screen
+//! pixel whatever the scale, and the same distance gives outlines and glows for free.
+
+use std::{collections::HashMap, error::Error, fmt, fs::File, io, path::Path, sync::Arc};
+
+use bytemuck::{Pod, Zeroable};
+use vulkano::{
+    buffer::{BufferUsage, CpuAccessibleBuffer},
+    command_buffer::{AutoCommandBufferBuilder, SecondaryAutoCommandBuffer},
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet,
+    },
+    device::Device,
+    image::{view::ImageView, ImmutableImage},
+    impl_vertex,
+    memory::allocator::StandardMemoryAllocator,
+    pipeline::{
+        cache::PipelineCache,
+        graphics::{
+            color_blend::{
+                AttachmentBlend, ColorBlendAttachmentState, ColorBlendState, ColorComponents,
+            },
+            input_assembly::InputAssemblyState,
+            multisample::MultisampleState,
+            render_pass::PipelineRenderingCreateInfo,
+            vertex_input::BuffersDefinition,
+            viewport::{Viewport, ViewportState},
+        },
+        GraphicsPipeline, Pipeline, PipelineBindPoint, StateMode,
+    },
+    render_pass::{RenderPass, Subpass},
+    sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo},
+};
+
+use super::{EngineError, RenderPath};
+
+mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+#version 450
+
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec2 uv;
+layout(location = 2) in vec4 color;
+layout(location = 3) in vec4 outline_color;
+layout(location = 4) in vec2 outline;
+
+layout(location = 0) out vec2 v_uv;
+layout(location = 1) out vec4 v_color;
+layout(location = 2) out vec4 v_outline_color;
+layout(location = 3) out vec2 v_outline;
+
+void main() {
+    v_uv = uv;
+    v_color = color;
+    v_outline_color = outline_color;
+    v_outline = outline;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+layout(location = 1) in vec4 v_color;
+layout(location = 2) in vec4 v_outline_color;
+// width and softness, in distance units
+layout(location = 3) in vec2 v_outline;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform texture2D atlas;
+layout(set = 0, binding = 1) uniform sampler atlas_sampler;
+
+void main() {
+    float distance = texture(sampler2D(atlas, atlas_sampler), v_uv).r;
+    // how much the distance changes over a screen pixel, for the same smoothing at any scale
+    float smoothing = fwidth(distance) * 0.5;
+
+    float fill = smoothstep(0.5 - smoothing, 0.5 + smoothing, distance);
+    float edge = 0.5 - v_outline.x;
+    float outline = smoothstep(edge - smoothing - v_outline.y, edge + smoothing, distance);
+
+    // the fill over its outline
+    float fill_alpha = v_color.a * fill;
+    float outline_alpha = v_outline_color.a * outline * (1.0 - fill_alpha);
+    float alpha = fill_alpha + outline_alpha;
+    vec3 color = (v_color.rgb * fill_alpha + v_outline_color.rgb * outline_alpha) / max(alpha, 1e-5);
+    f_color = vec4(color, alpha);
+}
+"
+    }
+}
+
+#[derive(Debug)]
+pub enum FontError {
+    Io(io::Error),
+    Decoding(png::DecodingError),
+    /// The atlas isn't an 8-bit PNG.
+    UnsupportedAtlas(png::BitDepth),
+    Metrics(String),
+    /// The atlas is wider or taller than `Graphicengine::max_texture_size`.
+    AtlasTooLarge {
+        dimensions: [u32; 2],
+        max: u32,
+    },
+    /// The atlas didn't fit in memory, see `EngineError::AllocationFailed`.
+    Allocation(EngineError),
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FontError::Io(e) => write!(f, "Failed to read font: {}", e),
+            FontError::Decoding(e) => write!(f, "Failed to decode font atlas: {}", e),
+            FontError::UnsupportedAtlas(bit_depth) => {
+                write!(f, "Unsupported font atlas bit depth: {:?}", bit_depth)
+            }
+            FontError::Metrics(message) => write!(f, "Invalid font metrics: {}", message),
+            FontError::AtlasTooLarge { dimensions, max } => write!(
+                f,
+                "Font atlas of {}x{} exceeds the maximum texture size of {}",
+                dimensions[0], dimensions[1], max
+            ),
+            FontError::Allocation(e) => write!(f, "Failed to create font atlas: {}", e),
+        }
+    }
+}
+
+impl Error for FontError {}
+
+impl From<io::Error> for FontError {
+    fn from(e: io::Error) -> FontError {
+        FontError::Io(e)
+    }
+}
+
+impl From<png::DecodingError> for FontError {
+    fn from(e: png::DecodingError) -> FontError {
+        FontError::Decoding(e)
+    }
+}
+
+impl From<EngineError> for FontError {
+    fn from(e: EngineError) -> FontError {
+        FontError::Allocation(e)
+    }
+}
+
+/// How `Graphicengine::draw_text_sdf` fills text. Colors are written to the main pass target as
+/// they are, like the clear color, and blended by their alpha.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextStyle {
+    pub color: [f32; 4],
+    pub outline_color: [f32; 4],
+    /// How far the outline extends outside the glyph edges, in distance units: `0.5` reaches the
+    /// spread the atlas was generated with.
+    pub outline_width: f32,
+    /// Extra fade of the outline on its outer side, also in distance units, turning it into a
+    /// glow.
+    pub outline_softness: f32,
+}
+
+impl Default for TextStyle {
+    fn default() -> TextStyle {
+        TextStyle {
+            color: [1.0; 4],
+            outline_color: [0.0; 4],
+            outline_width: 0.0,
+            outline_softness: 0.0,
+        }
+    }
+}
+
+/// One text of `Graphicengine::draw_text_sdf_batch`, with the arguments of `draw_text_sdf`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextRun<'a> {
+    pub text: &'a str,
+    pub position: [f32; 2],
+    pub size: f32,
+    pub style: TextStyle,
+}
+
+// in atlas pixels, as in the BMFont file
+#[derive(Clone, Copy, Debug, Default)]
+struct Glyph {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    x_offset: f32,
+    y_offset: f32,
+    x_advance: f32,
+}
+
+/// Glyph atlas and metrics loaded by `Graphicengine::load_sdf_font`.
+pub struct SdfFont {
+    atlas: Arc<ImageView<ImmutableImage>>,
+    glyphs: HashMap<char, Glyph>,
+    size: f32,
+    line_height: f32,
+    atlas_size: [f32; 2],
+}
+
+impl SdfFont {
+    /// Size in pixels the metrics are given for.
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    /// Distance between baselines, in pixels at `size`.
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    /// Quads of `text` at `position`, the top left corner of its first line in pixels of a
+    /// target of `extent`.
+    pub(super) fn layout(
+        &self,
+        text: &str,
+        position: [f32; 2],
+        size: f32,
+        style: &TextStyle,
+        extent: [f32; 2],
+    ) -> Vec<TextVertex> {
+        let scale = size / self.size;
+        let [width, height] = extent;
+        let to_ndc = |x: f32, y: f32| [x / width * 2.0 - 1.0, y / height * 2.0 - 1.0];
+        let to_uv = |x: f32, y: f32| [x / self.atlas_size[0], y / self.atlas_size[1]];
+
+        let mut vertices = Vec::with_capacity(text.len() * 6);
+        let mut pen = position;
+        for character in text.chars() {
+            if character == '\n' {
+                pen = [position[0], pen[1] + self.line_height * scale];
+                continue;
+            }
+            let Some(glyph) = self
+                .glyphs
+                .get(&character)
+                .or_else(|| self.glyphs.get(&'?'))
+            else {
+                continue;
+            };
+
+            if glyph.width > 0.0 && glyph.height > 0.0 {
+                let x0 = pen[0] + glyph.x_offset * scale;
+                let y0 = pen[1] + glyph.y_offset * scale;
+                let (x1, y1) = (x0 + glyph.width * scale, y0 + glyph.height * scale);
+                let (u0, v0) = (glyph.x, glyph.y);
+                let (u1, v1) = (glyph.x + glyph.width, glyph.y + glyph.height);
+
+                let vertex = |x, y, u, v| TextVertex {
+                    position: to_ndc(x, y),
+                    uv: to_uv(u, v),
+                    color: style.color,
+                    outline_color: style.outline_color,
+                    outline: [style.outline_width, style.outline_softness],
+                };
+                vertices.extend([
+                    vertex(x0, y0, u0, v0),
+                    vertex(x1, y0, u1, v0),
+                    vertex(x0, y1, u0, v1),
+                    vertex(x0, y1, u0, v1),
+                    vertex(x1, y0, u1, v0),
+                    vertex(x1, y1, u1, v1),
+                ]);
+            }
+            pen[0] += glyph.x_advance * scale;
+        }
+        vertices
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct TextVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+    color: [f32; 4],
+    outline_color: [f32; 4],
+    outline: [f32; 2],
+}
+
+// plain f32 fields with no implicit padding
+unsafe impl Zeroable for TextVertex {}
+unsafe impl Pod for TextVertex {}
+
+impl_vertex!(TextVertex, position, uv, color, outline_color, outline);
+
+/// Reads the first channel of an 8-bit PNG atlas, returning its extent and texels.
+pub(super) fn read_atlas(path: &Path) -> Result<([u32; 2], Vec<u8>), FontError> {
+    let mut decoder = png::Decoder::new(File::open(path)?);
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info()?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data)?;
+    if info.bit_depth != png::BitDepth::Eight {
+        return Err(FontError::UnsupportedAtlas(info.bit_depth));
+    }
+
+    let samples = info.color_type.samples();
+    let texels = data[..info.buffer_size()]
+        .chunks_exact(samples)
+        .map(|pixel| pixel[0])
+        .collect();
+    Ok(([info.width, info.height], texels))
+}
+
+pub(super) fn new_font(
+    atlas: Arc<ImageView<ImmutableImage>>,
+    metrics: &Path,
+) -> Result<SdfFont, FontError> {
+    let metrics = std::fs::read_to_string(metrics)?;
+    let mut font = SdfFont {
+        atlas,
+        glyphs: HashMap::new(),
+        size: 0.0,
+        line_height: 0.0,
+        atlas_size: [0.0; 2],
+    };
+
+    for (index, line) in metrics.lines().enumerate() {
+        let mut tokens = line.split_whitespace();
+        let tag = tokens.next().unwrap_or_default();
+        // string values may contain spaces, only numbers are read
+        let values: HashMap<&str, f32> = tokens
+            .filter_map(|token| token.split_once('='))
+            .filter_map(|(key, value)| Some((key, value.parse().ok()?)))
+            .collect();
+        let value = |key: &str| {
+            values
+                .get(key)
+                .copied()
+                .ok_or_else(|| FontError::Metrics(format!("line {}: missing {}", index + 1, key)))
+        };
+
+        match tag {
+            // negative sizes are in points rather than pixels, only the magnitude matters
+            "info" => font.size = value("size")?.abs(),
+            "common" => {
+                font.line_height = value("lineHeight")?;
+                font.atlas_size = [value("scaleW")?, value("scaleH")?];
+            }
+            "char" => {
+                let id = value("id")? as u32;
+                let Some(character) = char::from_u32(id) else {
+                    continue;
+                };
+                font.glyphs.insert(
+                    character,
+                    Glyph {
+                        x: value("x")?,
+                        y: value("y")?,
+                        width: value("width")?,
+                        height: value("height")?,
+                        x_offset: value("xoffset")?,
+                        y_offset: value("yoffset")?,
+                        x_advance: value("xadvance")?,
+                    },
+                );
+            }
+            _ => {}
+        }
+    }
+
+    if font.size == 0.0 || font.atlas_size.contains(&0.0) {
+        return Err(FontError::Metrics(
+            "missing info or common line".to_string(),
+        ));
+    }
+    Ok(font)
+}
+
+/// Pipeline drawing the glyph quads in the main pass, rebuilt when the main pass changes.
+pub(super) struct TextRenderer {
+    render_pass: Arc<RenderPass>,
+    pipeline: Arc<GraphicsPipeline>,
+    sampler: Arc<Sampler>,
+}
+
+impl TextRenderer {
+    pub fn new(
+        device: &Arc<Device>,
+        render_pass: &Arc<RenderPass>,
+        render_path: RenderPath,
+        pipeline_cache: &Arc<PipelineCache>,
+    ) -> TextRenderer {
+        // the render pass describes the main pass attachments with either render path
+        let attachments = render_pass.attachments();
+        let subpass = Subpass::from(render_pass.clone(), 0).unwrap();
+        let color_attachments = &subpass.subpass_desc().color_attachments;
+
+        // only the main color attachment is written to
+        let mut color_blend_state = ColorBlendState::new(color_attachments.len() as u32);
+        for (i, attachment) in color_blend_state.attachments.iter_mut().enumerate() {
+            *attachment = if i == 0 {
+                ColorBlendAttachmentState {
+                    blend: Some(AttachmentBlend::alpha()),
+                    color_write_mask: ColorComponents::all(),
+                    color_write_enable: StateMode::Fixed(true),
+                }
+            } else {
+                ColorBlendAttachmentState {
+                    blend: None,
+                    color_write_mask: ColorComponents::empty(),
+                    color_write_enable: StateMode::Fixed(true),
+                }
+            };
+        }
+
+        let vs = vs::load(device.clone()).unwrap();
+        let fs = fs::load(device.clone()).unwrap();
+        let builder = GraphicsPipeline::start()
+            .vertex_input_state(BuffersDefinition::new().vertex::<TextVertex>())
+            .vertex_shader(vs.entry_point("main").unwrap(), ())
+            .input_assembly_state(InputAssemblyState::new())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .fragment_shader(fs.entry_point("main").unwrap(), ())
+            .color_blend_state(color_blend_state)
+            .multisample_state(MultisampleState {
+                rasterization_samples: attachments[0].samples,
+                ..Default::default()
+            });
+        let builder = match render_path {
+            RenderPath::RenderPass => builder.render_pass(subpass.clone()),
+            RenderPath::DynamicRendering => {
+                let format = |attachment: u32| attachments[attachment as usize].format;
+                builder.render_pass(PipelineRenderingCreateInfo {
+                    color_attachment_formats: color_attachments
+                        .iter()
+                        .map(|reference| reference.as_ref().and_then(|r| format(r.attachment)))
+                        .collect(),
+                    depth_attachment_format: subpass
+                        .subpass_desc()
+                        .depth_stencil_attachment
+                        .as_ref()
+                        .and_then(|r| format(r.attachment)),
+                    ..Default::default()
+                })
+            }
+        };
+        let pipeline = builder
+            .build_with_cache(pipeline_cache.clone())
+            .build(device.clone())
+            .unwrap();
+
+        let sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        TextRenderer {
+            render_pass: render_pass.clone(),
+            pipeline,
+            sampler,
+        }
+    }
+
+    pub fn is_for(&self, render_pass: &Arc<RenderPass>) -> bool {
+        Arc::ptr_eq(&self.render_pass, render_pass)
+    }
+
+    /// Records a draw of the quads from `SdfFont::layout`, which mustn't be empty.
+    pub fn draw(
+        &self,
+        cmd_buffer_builder: &mut AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>,
+        memory_allocator: &StandardMemoryAllocator,
+        descriptor_set_allocator: &StandardDescriptorSetAllocator,
+        viewport: &Viewport,
+        font: &SdfFont,
+        vertices: Vec<TextVertex>,
+    ) -> Result<(), EngineError> {
+        let vertex_count = vertices.len() as u32;
+        let size = std::mem::size_of_val(vertices.as_slice()) as u64;
+        let vertex_buffer = CpuAccessibleBuffer::from_iter(
+            memory_allocator,
+            BufferUsage {
+                vertex_buffer: true,
+                ..BufferUsage::empty()
+            },
+            false,
+            vertices,
+        )
+        .map_err(EngineError::allocation(size))?;
+        let descriptor_set = PersistentDescriptorSet::new(
+            descriptor_set_allocator,
+            self.pipeline.layout().set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::image_view(0, font.atlas.clone()),
+                WriteDescriptorSet::sampler(1, self.sampler.clone()),
+            ],
+        )
+        .unwrap();
+
+        cmd_buffer_builder
+            .set_viewport(0, [viewport.clone()])
+            .bind_pipeline_graphics(self.pipeline.clone())
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                self.pipeline.layout().clone(),
+                0,
+                descriptor_set,
+            )
+            .bind_vertex_buffers(0, vertex_buffer)
+            .draw(vertex_count, 1, 0, 0)
+            .unwrap();
+        Ok(())
+    }
+}
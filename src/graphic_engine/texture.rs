@@ -0,0 +1,264 @@
+//! PNG textures loaded with the sampler they are meant to be read with.
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt, fs, io,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use vulkano::{
+    device::Device,
+    sampler::{
+        Filter, Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerCreationError,
+        SamplerMipmapMode, LOD_CLAMP_NONE,
+    },
+};
+
+use super::EngineError;
+
+/// How `Graphicengine::load_texture` uploads a texture and samples it. The default suits world
+/// textures; UI textures usually want `TextureOptions::ui`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureOptions {
+    /// Whether the texels are sRGB encoded colors, rather than linear data like normals.
+    pub srgb: bool,
+    /// Blits a full mip chain, when the format supports linear blits.
+    pub generate_mips: bool,
+    /// Maximum anisotropy, clamped to what the device supports. Ignored when the
+    /// `sampler_anisotropy` feature isn't enabled.
+    pub anisotropy: Option<f32>,
+    pub address_mode: SamplerAddressMode,
+    /// Magnification, minification and, with mips, mipmap filter.
+    pub filter: Filter,
+}
+
+impl TextureOptions {
+    /// Clamped, nearest filtered and without mips, for pixel exact UI.
+    pub fn ui() -> TextureOptions {
+        TextureOptions {
+            srgb: true,
+            generate_mips: false,
+            anisotropy: None,
+            address_mode: SamplerAddressMode::ClampToEdge,
+            filter: Filter::Nearest,
+        }
+    }
+}
+
+impl Default for TextureOptions {
+    fn default() -> TextureOptions {
+        TextureOptions {
+            srgb: true,
+            generate_mips: true,
+            anisotropy: Some(16.0),
+            address_mode: SamplerAddressMode::Repeat,
+            filter: Filter::Linear,
+        }
+    }
+}
+
+/// Why loading a texture failed, with the file it failed on.
+#[derive(Debug)]
+pub struct TextureError {
+    /// For cubemaps, the face the error is about, or the first one for the whole cubemap.
+    pub path: PathBuf,
+    pub kind: TextureErrorKind,
+}
+
+impl TextureError {
+    pub(super) fn at<E: Into<TextureErrorKind>>(path: &Path) -> impl FnOnce(E) -> TextureError {
+        let path = path.to_path_buf();
+        move |kind| TextureError {
+            path,
+            kind: kind.into(),
+        }
+    }
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.kind)
+    }
+}
+
+impl Error for TextureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.kind)
+    }
+}
+
+#[derive(Debug)]
+pub enum TextureErrorKind {
+    Io(io::Error),
+    /// The file is a corrupt PNG.
+    Decode(png::DecodingError),
+    /// The file isn't a PNG, the only format loaded, and has this extension.
+    UnsupportedFormat(String),
+    Sampler(SamplerCreationError),
+    /// The image is wider or taller than `Graphicengine::max_texture_size`.
+    TooLarge {
+        dimensions: [u32; 2],
+        max: u32,
+    },
+    /// A face of a cubemap isn't square, or not the size of the first face.
+    CubemapFaceSize {
+        face: usize,
+        dimensions: [u32; 2],
+        size: u32,
+    },
+    /// Uploading the image failed, e.g. with `EngineError::AllocationFailed`.
+    Gpu(EngineError),
+}
+
+impl fmt::Display for TextureErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextureErrorKind::Io(e) => write!(f, "Failed to read texture: {}", e),
+            TextureErrorKind::Decode(e) => write!(f, "Failed to decode texture: {}", e),
+            TextureErrorKind::UnsupportedFormat(extension) => write!(
+                f,
+                "Texture is not a PNG, {:?} textures aren't supported",
+                extension
+            ),
+            TextureErrorKind::Sampler(e) => write!(f, "Failed to create texture sampler: {}", e),
+            TextureErrorKind::TooLarge { dimensions, max } => write!(
+                f,
+                "Texture of {}x{} exceeds the maximum size of {}",
+                dimensions[0], dimensions[1], max
+            ),
+            TextureErrorKind::CubemapFaceSize {
+                face,
+                dimensions,
+                size,
+            } => write!(
+                f,
+                "Cubemap face {} of {}x{} is not a {}x{} square like the first face",
+                face, dimensions[0], dimensions[1], size, size
+            ),
+            TextureErrorKind::Gpu(e) => write!(f, "Failed to create texture: {}", e),
+        }
+    }
+}
+
+impl Error for TextureErrorKind {}
+
+impl From<io::Error> for TextureErrorKind {
+    fn from(e: io::Error) -> TextureErrorKind {
+        TextureErrorKind::Io(e)
+    }
+}
+
+impl From<png::DecodingError> for TextureErrorKind {
+    fn from(e: png::DecodingError) -> TextureErrorKind {
+        TextureErrorKind::Decode(e)
+    }
+}
+
+impl From<SamplerCreationError> for TextureErrorKind {
+    fn from(e: SamplerCreationError) -> TextureErrorKind {
+        TextureErrorKind::Sampler(e)
+    }
+}
+
+impl From<EngineError> for TextureErrorKind {
+    fn from(e: EngineError) -> TextureErrorKind {
+        TextureErrorKind::Gpu(e)
+    }
+}
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Decodes a PNG of any color type and bit depth to 8-bit RGBA.
+pub(super) fn read_rgba(path: &Path) -> Result<([u32; 2], Vec<u8>), TextureErrorKind> {
+    let file = fs::read(path)?;
+    if !file.starts_with(&PNG_SIGNATURE) {
+        let extension = path.extension().unwrap_or_default();
+        return Err(TextureErrorKind::UnsupportedFormat(
+            extension.to_string_lossy().into_owned(),
+        ));
+    }
+    let mut decoder = png::Decoder::new(file.as_slice());
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data)?;
+
+    let data = &data[..info.buffer_size()];
+    let texels = match info.color_type {
+        png::ColorType::Rgba => data.to_vec(),
+        png::ColorType::Rgb => data
+            .chunks_exact(3)
+            .flat_map(|texel| [texel[0], texel[1], texel[2], u8::MAX])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => data
+            .chunks_exact(2)
+            .flat_map(|texel| [texel[0], texel[0], texel[0], texel[1]])
+            .collect(),
+        // expanded to RGB(A) when indexed
+        png::ColorType::Grayscale | png::ColorType::Indexed => data
+            .iter()
+            .flat_map(|&gray| [gray, gray, gray, u8::MAX])
+            .collect(),
+    };
+    Ok(([info.width, info.height], texels))
+}
+
+// sampler options that differ between textures, the anisotropy by its bits
+type SamplerKey = (Filter, SamplerAddressMode, bool, Option<u32>);
+
+/// Samplers of the loaded textures, shared by the textures with the same options.
+#[derive(Default)]
+pub(super) struct TextureSamplers {
+    samplers: HashMap<SamplerKey, Arc<Sampler>>,
+}
+
+impl TextureSamplers {
+    pub fn get(
+        &mut self,
+        device: &Arc<Device>,
+        options: &TextureOptions,
+        mipmapped: bool,
+    ) -> Result<Arc<Sampler>, SamplerCreationError> {
+        let anisotropy = options
+            .anisotropy
+            .filter(|_| device.enabled_features().sampler_anisotropy)
+            .map(|anisotropy| {
+                anisotropy.clamp(
+                    1.0,
+                    device.physical_device().properties().max_sampler_anisotropy,
+                )
+            });
+        let key = (
+            options.filter,
+            options.address_mode,
+            mipmapped,
+            anisotropy.map(f32::to_bits),
+        );
+        if let Some(sampler) = self.samplers.get(&key) {
+            return Ok(sampler.clone());
+        }
+
+        let (mipmap_mode, lod): (_, RangeInclusive<f32>) = match (mipmapped, options.filter) {
+            (false, _) => (SamplerMipmapMode::Nearest, 0.0..=0.0),
+            (true, Filter::Nearest) => (SamplerMipmapMode::Nearest, 0.0..=LOD_CLAMP_NONE),
+            (true, _) => (SamplerMipmapMode::Linear, 0.0..=LOD_CLAMP_NONE),
+        };
+        let sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                mag_filter: options.filter,
+                min_filter: options.filter,
+                mipmap_mode,
+                address_mode: [options.address_mode; 3],
+                anisotropy,
+                lod,
+                ..Default::default()
+            },
+        )?;
+        self.samplers.insert(key, sampler.clone());
+        Ok(sampler)
+    }
+}
@@ -0,0 +1,117 @@
+//! CPU timestamps taken along the render path, for latency analysis, see
+//! `Graphicengine::enable_frame_timeline`.
+
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use crate::benchmark::FrameStats;
+
+/// How long the last frames spent in each stage of the render path, with percentiles from
+/// `FrameStats::percentile`:
+/// - `acquire`: blocked in acquiring the swapchain image,
+/// - `record`: from the acquired image to the frame submitted with its present,
+/// - `present`: from the submission to the present reaching the display.
+#[derive(Clone, Debug)]
+pub struct FrameTimeline {
+    acquire: FrameStats,
+    record: FrameStats,
+    present: FrameStats,
+    // timestamps of the frame being rendered
+    acquiring: Option<Instant>,
+    acquired: Option<Instant>,
+    // of the last submitted frame whose present wasn't measured yet
+    submitted: Option<Instant>,
+}
+
+impl FrameTimeline {
+    pub(super) fn new(window: usize) -> FrameTimeline {
+        FrameTimeline {
+            acquire: FrameStats::new(window),
+            record: FrameStats::new(window),
+            present: FrameStats::new(window),
+            acquiring: None,
+            acquired: None,
+            submitted: None,
+        }
+    }
+
+    pub fn acquire(&self) -> &FrameStats {
+        &self.acquire
+    }
+
+    pub fn record(&self) -> &FrameStats {
+        &self.record
+    }
+
+    pub fn present(&self) -> &FrameStats {
+        &self.present
+    }
+
+    pub(super) fn begin_acquire(&mut self) {
+        self.acquiring = Some(Instant::now());
+        self.acquired = None;
+    }
+
+    /// Without present wait, the present of the previous frame is taken to have completed when
+    /// the next image is acquired, which is only an approximation: acquire returns once the
+    /// presentation engine no longer needs one of the images, not when that present was shown.
+    pub(super) fn acquired(&mut self, approximate_present: bool) {
+        let now = Instant::now();
+        if let Some(acquiring) = self.acquiring.take() {
+            self.acquire.record(now - acquiring);
+        }
+        if approximate_present {
+            if let Some(submitted) = self.submitted.take() {
+                self.present.record(now - submitted);
+            }
+        }
+        self.acquired = Some(now);
+    }
+
+    pub(super) fn submitted(&mut self) {
+        let now = Instant::now();
+        if let Some(acquired) = self.acquired.take() {
+            self.record.record(now - acquired);
+        }
+        self.submitted = Some(now);
+    }
+
+    /// A present measured with present wait, `latency` after its submission.
+    pub(super) fn presented(&mut self, latency: Duration) {
+        self.present.record(latency);
+    }
+
+    // after a skipped frame, nothing was presented
+    pub(super) fn cancel(&mut self) {
+        self.acquiring = None;
+        self.acquired = None;
+    }
+}
+
+impl fmt::Display for FrameTimeline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (stage, stats)) in [
+            ("acquire", &self.acquire),
+            ("record", &self.record),
+            ("present", &self.present),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(
+                f,
+                "{} p50 {:.2?} p95 {:.2?} p99 {:.2?}",
+                stage,
+                stats.percentile(50.0),
+                stats.percentile(95.0),
+                stats.percentile(99.0)
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+//! Tone mapping post-process, drawn from a floating point main pass target into the swapchain
+//! image, compressing values above 1.0 from lighting into the display range instead of clipping
+//! them.
+//!
+//! With FXAA enabled, FXAA tone maps the samples it reads instead, see `post.frag`, so the image
+//! is still drawn to the swapchain in a single pass.
+//!
+//! Both get the parameters as push constants, so exposure changes don't rebuild anything.
+
+use std::sync::Arc;
+
+use bytemuck::{Pod, Zeroable};
+use vulkano::{
+    device::Device,
+    format::{Format, NumericType},
+    pipeline::cache::PipelineCache,
+};
+
+use super::fullscreen::FullscreenPass;
+
+mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/graphic_engine/post.frag",
+    }
+}
+
+mod fs_linear {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/graphic_engine/post.frag",
+        define: [("LINEAR_INPUT", "")],
+    }
+}
+
+/// Curve mapping the linear colors of a floating point main target to the display range, see
+/// `Graphicengine::set_tonemap`. Colors are multiplied by the exposure first.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Tonemap {
+    /// Colors are written as they are, values above 1.0 clip.
+    #[default]
+    None,
+    /// `c / (1 + c)`, which never quite reaches white and desaturates highlights little.
+    Reinhard,
+    /// An approximation of the ACES filmic curve, with more contrast and a toe in the shadows.
+    Aces,
+    /// `1 - exp(-k * c)` with this `k`, a soft shoulder that saturates faster as `k` grows.
+    Exposure(f32),
+}
+
+// the `TonemapParams` push constants of post.frag
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct TonemapParams {
+    operator: u32,
+    exposure: f32,
+    curve: f32,
+}
+
+// plain 4-byte fields with no implicit padding
+unsafe impl Zeroable for TonemapParams {}
+unsafe impl Pod for TonemapParams {}
+
+impl TonemapParams {
+    pub fn new(tonemap: Tonemap, exposure: f32) -> TonemapParams {
+        let (operator, curve) = match tonemap {
+            Tonemap::None => (0, 1.0),
+            Tonemap::Reinhard => (1, 1.0),
+            Tonemap::Aces => (2, 1.0),
+            Tonemap::Exposure(curve) => (3, curve),
+        };
+        TonemapParams {
+            operator,
+            exposure,
+            curve,
+        }
+    }
+
+    pub fn passthrough() -> TonemapParams {
+        TonemapParams::new(Tonemap::None, 1.0)
+    }
+}
+
+/// The tone mapping pass into swapchain images of `format`. Set 0 holds the offscreen target at
+/// binding 0 and the sampler at binding 1, and `TonemapParams` are pushed at each draw.
+pub(super) fn pass(
+    device: &Arc<Device>,
+    format: Format,
+    pipeline_cache: &Arc<PipelineCache>,
+) -> FullscreenPass {
+    // as with FXAA, sRGB swapchains encode the linear values written
+    let fs = if format.type_color() == Some(NumericType::SRGB) {
+        fs_linear::load(device.clone()).unwrap()
+    } else {
+        fs::load(device.clone()).unwrap()
+    };
+    FullscreenPass::new(
+        device,
+        format,
+        fs.entry_point("main").unwrap(),
+        false,
+        None,
+        pipeline_cache,
+    )
+}
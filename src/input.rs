@@ -0,0 +1,90 @@
+//! Polled view of the keyboard and mouse, kept up to date by the event loop.
+
+use std::collections::HashSet;
+
+use winit::event::{
+    DeviceEvent, ElementState, Event, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent,
+};
+
+// pixels per scrolled line, to report pixel and line deltas in the same unit
+const PIXELS_PER_LINE: f32 = 20.0;
+
+#[derive(Clone, Debug, Default)]
+pub struct InputState {
+    keys_down: HashSet<VirtualKeyCode>,
+    mouse_buttons_down: HashSet<MouseButton>,
+    mouse_delta: [f32; 2],
+    scroll_delta: [f32; 2],
+}
+
+impl InputState {
+    pub fn is_key_down(&self, key: VirtualKeyCode) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    pub fn is_mouse_button_down(&self, button: MouseButton) -> bool {
+        self.mouse_buttons_down.contains(&button)
+    }
+
+    /// Raw mouse motion since the last frame, unaffected by the cursor reaching the screen edge.
+    pub fn mouse_delta(&self) -> [f32; 2] {
+        self.mouse_delta
+    }
+
+    /// Scrolling since the last frame, in lines.
+    pub fn scroll_delta(&self) -> [f32; 2] {
+        self.scroll_delta
+    }
+
+    pub(crate) fn handle_event<T>(&mut self, event: &Event<T>) {
+        match event {
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::KeyboardInput { input, .. } => {
+                    if let Some(key) = input.virtual_keycode {
+                        match input.state {
+                            ElementState::Pressed => self.keys_down.insert(key),
+                            ElementState::Released => self.keys_down.remove(&key),
+                        };
+                    }
+                }
+                WindowEvent::MouseInput { state, button, .. } => {
+                    match state {
+                        ElementState::Pressed => self.mouse_buttons_down.insert(*button),
+                        ElementState::Released => self.mouse_buttons_down.remove(button),
+                    };
+                }
+                WindowEvent::MouseWheel { delta, .. } => {
+                    let [x, y] = match *delta {
+                        MouseScrollDelta::LineDelta(x, y) => [x, y],
+                        MouseScrollDelta::PixelDelta(position) => [
+                            position.x as f32 / PIXELS_PER_LINE,
+                            position.y as f32 / PIXELS_PER_LINE,
+                        ],
+                    };
+                    self.scroll_delta[0] += x;
+                    self.scroll_delta[1] += y;
+                }
+                // releases happening while unfocused are never received
+                WindowEvent::Focused(false) => {
+                    self.keys_down.clear();
+                    self.mouse_buttons_down.clear();
+                }
+                _ => {}
+            },
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } => {
+                self.mouse_delta[0] += delta.0 as f32;
+                self.mouse_delta[1] += delta.1 as f32;
+            }
+            _ => {}
+        }
+    }
+
+    // deltas accumulate between rendered frames
+    pub(crate) fn end_frame(&mut self) {
+        self.mouse_delta = [0.0; 2];
+        self.scroll_delta = [0.0; 2];
+    }
+}
@@ -1,27 +1,155 @@
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
 use vulkano::{
-    instance::{Instance, InstanceCreateInfo},
+    instance::{Instance, InstanceCreateInfo, InstanceExtensions},
+    swapchain::{CompositeAlpha, PresentMode},
     Version, VulkanLibrary,
 };
 use vulkano_win::VkSurfaceBuild;
 use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
     event::{Event, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    event_loop::{ControlFlow, EventLoop, EventLoopBuilder, EventLoopProxy},
+    monitor::{MonitorHandle, VideoMode},
+    window::{Fullscreen, WindowBuilder},
 };
 
+pub mod benchmark;
+#[cfg(feature = "capi")]
+pub mod capi;
 pub mod graphic_engine;
+pub mod input;
+
+use benchmark::FrameStats;
+use graphic_engine::{DeviceRetry, EngineError, PowerPreference};
+use input::InputState;
+
+// frames the benchmark statistics are computed over
+const BENCHMARK_WINDOW: usize = 1000;
+const BENCHMARK_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// When the event loop wakes up to render.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControlFlowPolicy {
+    /// Render as often as possible (`ControlFlow::Poll`).
+    Continuous,
+    /// Render only on redraw requests and window changes (`ControlFlow::Wait`).
+    OnDemand,
+    /// Render at most once per target frame time (`ControlFlow::WaitUntil`).
+    EnergySaving(Duration),
+}
+
+/// Events other threads send to the event loop through `App::proxy`.
+///
+/// An event wakes the loop whatever the control flow policy, and is handled before the
+/// `RedrawRequested` events of the same loop iteration, so a redraw it requests draws with
+/// whatever the sending thread finished before sending it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UserEvent {
+    /// Only wakes the loop.
+    Wake,
+    /// Same as `App::request_redraw`, which renders a frame with the `OnDemand` policy and is
+    /// redundant with the others.
+    RequestRedraw,
+}
+
+/// How the window covers the screen, see `App::set_fullscreen`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FullscreenMode {
+    Windowed,
+    /// A borderless window covering the monitor, the current one with `None`. The desktop video
+    /// mode is kept, so switching is fast.
+    Borderless(Option<MonitorHandle>),
+    /// Takes over the monitor of the video mode and switches it to that resolution and refresh
+    /// rate, which may lower latency. Video modes come from `App::video_modes`.
+    Exclusive(VideoMode),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AppConfig {
+    /// Enabled on top of the extensions the window surface requires.
+    pub extra_instance_extensions: InstanceExtensions,
+    /// Initial position of the window's top left corner in physical pixels, relative to
+    /// `monitor` when it is set. The OS decides when neither is set.
+    pub position: Option<(i32, i32)>,
+    /// Index in the event loop's `available_monitors` of the monitor to open the window on. The
+    /// primary monitor is used if there is no monitor at that index.
+    pub monitor: Option<usize>,
+    /// File the pipeline cache is loaded from at startup and saved to on exit.
+    pub pipeline_cache_path: Option<PathBuf>,
+    /// Device creation is tried once by default.
+    pub device_retry: DeviceRetry,
+    /// Discrete GPUs are preferred by default.
+    pub power_preference: PowerPreference,
+    /// Lets the desktop show through where the clear color and rendered pixels are transparent.
+    /// Colors must be premultiplied by their alpha when the surface blends premultiplied, see
+    /// `Graphicengine::composite_alpha`. Fails if the surface can't blend with alpha at all.
+    pub transparent: bool,
+    /// Smallest inner size in physical pixels the window can be resized to, which the swapchain
+    /// also keeps to where the surface allows it, see `Graphicengine::set_min_extent`.
+    pub min_size: Option<(u32, u32)>,
+    /// Largest inner size in physical pixels the window can be resized to. Fails when smaller
+    /// than `min_size`.
+    pub max_size: Option<(u32, u32)>,
+}
 
 pub struct App {
-    event_loop: EventLoop<()>,
+    // taken by `run`
+    event_loop: Option<EventLoop<UserEvent>>,
+    proxy: EventLoopProxy<UserEvent>,
     graphic_engine: graphic_engine::Graphicengine,
+    control_flow_policy: ControlFlowPolicy,
+    input: InputState,
+    benchmark: Option<Benchmark>,
+    // outer position, when the platform has one, and inner size before going fullscreen
+    windowed_rect: Option<(Option<PhysicalPosition<i32>>, PhysicalSize<u32>)>,
+}
+
+// settings replaced by benchmark mode, restored when it is turned off
+struct Benchmark {
+    present_mode: PresentMode,
+    control_flow_policy: ControlFlowPolicy,
+    stats: FrameStats,
+    last_frame: Option<Instant>,
+    last_report: Instant,
+}
+
+impl Benchmark {
+    fn frame_rendered(&mut self) {
+        let now = Instant::now();
+        if let Some(last_frame) = self.last_frame {
+            self.stats.record(now - last_frame);
+        }
+        self.last_frame = Some(now);
+
+        if now - self.last_report >= BENCHMARK_REPORT_INTERVAL {
+            println!("{}", self.stats);
+            self.last_report = now;
+        }
+    }
 }
 
 impl App {
     pub fn new() -> App {
+        App::with_config(AppConfig::default()).unwrap()
+    }
+
+    pub fn with_config(config: AppConfig) -> Result<App, EngineError> {
         // Vulkan instance
         let instance = {
-            let library = VulkanLibrary::new().unwrap();
-            let extensions = vulkano_win::required_extensions(&library);
+            let library = VulkanLibrary::new().map_err(EngineError::Loading)?;
+            let extensions =
+                vulkano_win::required_extensions(&library) | config.extra_instance_extensions;
+
+            let missing = extensions - *library.supported_extensions();
+            if missing != InstanceExtensions::empty() {
+                return Err(EngineError::UnsupportedInstanceExtensions(missing));
+            }
 
             Instance::new(
                 library,
@@ -32,25 +160,255 @@ impl App {
                     ..Default::default()
                 },
             )
-            .unwrap()
+            .map_err(EngineError::InstanceCreation)?
         };
+        App::with_instance(instance, config)
+    }
+
+    /// Like `with_config`, on an instance created by the caller, e.g. with its own application
+    /// info, layers or validation features, or shared with other Vulkan code.
+    ///
+    /// The instance must enable the surface extensions of `vulkano_win::required_extensions`,
+    /// otherwise this fails with `UnsupportedInstanceExtensions`, and `enumerate_portability`
+    /// to find MoltenVK devices on macOS. Dynamic rendering, bindless materials and push
+    /// descriptors need a `max_api_version` of at least 1.1, and are turned off below.
+    /// `config.extra_instance_extensions` is ignored.
+    pub fn with_instance(instance: Arc<Instance>, config: AppConfig) -> Result<App, EngineError> {
+        let missing =
+            vulkano_win::required_extensions(instance.library()) - *instance.enabled_extensions();
+        if missing != InstanceExtensions::empty() {
+            return Err(EngineError::UnsupportedInstanceExtensions(missing));
+        }
+        check_size_limits(config.min_size, config.max_size)?;
+
         // Vulkan surface on a window
-        let event_loop = EventLoop::new();
-        let surface = WindowBuilder::new()
+        let event_loop = EventLoopBuilder::with_user_event().build();
+        let monitor_position = config.monitor.map(|index| {
+            let monitor = event_loop.available_monitors().nth(index).or_else(|| {
+                println!(
+                    "No monitor {}, opening the window on the primary one",
+                    index
+                );
+                event_loop.primary_monitor()
+            });
+            monitor.map_or(PhysicalPosition::new(0, 0), |monitor| monitor.position())
+        });
+
+        let mut window_builder = WindowBuilder::new().with_transparent(config.transparent);
+        if let Some((width, height)) = config.min_size {
+            window_builder = window_builder.with_min_inner_size(PhysicalSize::new(width, height));
+        }
+        if let Some((width, height)) = config.max_size {
+            window_builder = window_builder.with_max_inner_size(PhysicalSize::new(width, height));
+        }
+        if config.position.is_some() || monitor_position.is_some() {
+            let origin = monitor_position.unwrap_or(PhysicalPosition::new(0, 0));
+            let (x, y) = config.position.unwrap_or((0, 0));
+            window_builder =
+                window_builder.with_position(PhysicalPosition::new(origin.x + x, origin.y + y));
+        }
+        let surface = window_builder
             .build_vk_surface(&event_loop, instance.clone())
-            .unwrap();
+            .map_err(EngineError::WindowCreation)?;
 
-        let graphic_engine = graphic_engine::Graphicengine::new(instance, surface);
+        let mut graphic_engine = graphic_engine::Graphicengine::with_device_options(
+            instance,
+            surface,
+            config.device_retry,
+            config.power_preference,
+        )?;
+        if let Some((width, height)) = config.min_size {
+            graphic_engine.set_min_extent([width, height]);
+        }
+        if let Some(path) = &config.pipeline_cache_path {
+            graphic_engine.set_pipeline_cache_path(path);
+        }
+        if config.transparent {
+            let supported = graphic_engine.surface_composite_alphas();
+            let composite_alpha = [
+                CompositeAlpha::PreMultiplied,
+                CompositeAlpha::PostMultiplied,
+            ]
+            .into_iter()
+            .find(|composite_alpha| supported.contains(composite_alpha))
+            .ok_or(EngineError::UnsupportedCompositeAlpha(
+                CompositeAlpha::PreMultiplied,
+            ))?;
+            graphic_engine.set_composite_alpha(composite_alpha)?;
+            // transparent black is the same premultiplied or not
+            graphic_engine.set_clear_color([0.0; 4]);
+        }
 
-        return App {
-            event_loop,
+        Ok(App {
+            proxy: event_loop.create_proxy(),
+            event_loop: Some(event_loop),
             graphic_engine,
+            control_flow_policy: ControlFlowPolicy::Continuous,
+            input: InputState::default(),
+            benchmark: None,
+            windowed_rect: None,
+        })
+    }
+
+    pub fn set_control_flow_policy(&mut self, policy: ControlFlowPolicy) {
+        self.control_flow_policy = policy;
+    }
+
+    pub fn request_redraw(&self) {
+        self.graphic_engine.request_redraw();
+    }
+
+    /// Advances the frame clock, the `time` uniform, by exactly `step` every frame instead of
+    /// real time, see `Graphicengine::set_fixed_frame_time`. Frame pacing is unaffected, combine
+    /// it with a control flow policy for that.
+    pub fn set_fixed_frame_time(&mut self, step: Option<Duration>) {
+        self.graphic_engine.set_fixed_frame_time(step);
+    }
+
+    /// Changes the limits of `AppConfig::min_size` and `max_size`. The window is resized into
+    /// them asynchronously if needed.
+    pub fn set_window_size_limits(
+        &mut self,
+        min: Option<(u32, u32)>,
+        max: Option<(u32, u32)>,
+    ) -> Result<(), EngineError> {
+        check_size_limits(min, max)?;
+        let window = self.graphic_engine.window();
+        window.set_min_inner_size(min.map(|(width, height)| PhysicalSize::new(width, height)));
+        window.set_max_inner_size(max.map(|(width, height)| PhysicalSize::new(width, height)));
+        self.graphic_engine
+            .set_min_extent(min.map_or([0, 0], |(width, height)| [width, height]));
+        Ok(())
+    }
+
+    /// Sends `UserEvent`s to the event loop from other threads. Sending fails once the loop has
+    /// exited.
+    pub fn proxy(&self) -> EventLoopProxy<UserEvent> {
+        self.proxy.clone()
+    }
+
+    /// Switches the window to `mode`. Leaving windowed mode remembers the window's position and
+    /// size, which switching back to `Windowed` restores.
+    ///
+    /// The window is resized asynchronously, and the swapchain recreated when the resize event
+    /// arrives. Call it from the `run_with` callback to switch while running; a window still
+    /// fullscreen when the loop exits is switched back first.
+    pub fn set_fullscreen(&mut self, mode: FullscreenMode) {
+        let window = self.graphic_engine.window();
+        if window.fullscreen().is_none() && mode != FullscreenMode::Windowed {
+            self.windowed_rect = Some((window.outer_position().ok(), window.inner_size()));
+        }
+
+        match mode {
+            FullscreenMode::Windowed => {
+                window.set_fullscreen(None);
+                if let Some((position, size)) = self.windowed_rect.take() {
+                    window.set_inner_size(size);
+                    if let Some(position) = position {
+                        window.set_outer_position(position);
+                    }
+                }
+            }
+            FullscreenMode::Borderless(monitor) => {
+                window.set_fullscreen(Some(Fullscreen::Borderless(monitor)));
+            }
+            FullscreenMode::Exclusive(video_mode) => {
+                window.set_fullscreen(Some(Fullscreen::Exclusive(video_mode)));
+            }
+        }
+    }
+
+    pub fn fullscreen(&self) -> FullscreenMode {
+        match self.graphic_engine.window().fullscreen() {
+            None => FullscreenMode::Windowed,
+            Some(Fullscreen::Borderless(monitor)) => FullscreenMode::Borderless(monitor),
+            Some(Fullscreen::Exclusive(video_mode)) => FullscreenMode::Exclusive(video_mode),
+        }
+    }
+
+    /// Video modes of the monitor the window is on, for `FullscreenMode::Exclusive`, largest and
+    /// fastest first. Other monitors list theirs with `MonitorHandle::video_modes`.
+    pub fn video_modes(&self) -> Vec<VideoMode> {
+        let Some(monitor) = self.graphic_engine.window().current_monitor() else {
+            return Vec::new();
         };
+        let mut video_modes: Vec<_> = monitor.video_modes().collect();
+        video_modes.sort_by_key(|video_mode| {
+            let size = video_mode.size();
+            std::cmp::Reverse((
+                size.width * size.height,
+                video_mode.refresh_rate_millihertz(),
+                video_mode.bit_depth(),
+            ))
+        });
+        video_modes
     }
 
-    pub fn run(mut self) {
+    /// Keys and mouse as of the frame being rendered, for the `run_with` callback. Deltas cover
+    /// the events since the previous frame.
+    pub fn input(&self) -> &InputState {
+        &self.input
+    }
+
+    /// Renders uncapped, with `PresentMode::Immediate` when the surface supports it and
+    /// continuously, and prints the frame rate and frame time percentiles over the last frames
+    /// every second. Turning it off restores the previous present mode and control flow policy.
+    ///
+    /// Call it before `run` to benchmark from the start, or from the `run_with` callback to turn
+    /// it on and off while running, e.g. on a key press.
+    pub fn benchmark_mode(&mut self, enabled: bool) {
+        match (enabled, self.benchmark.take()) {
+            (true, None) => {
+                let present_mode = self.graphic_engine.present_mode();
+                if let Err(e) = self.graphic_engine.set_present_mode(PresentMode::Immediate) {
+                    println!("{}, benchmarking in {:?} mode", e, present_mode);
+                }
+                self.benchmark = Some(Benchmark {
+                    present_mode,
+                    control_flow_policy: self.control_flow_policy,
+                    stats: FrameStats::new(BENCHMARK_WINDOW),
+                    last_frame: None,
+                    last_report: Instant::now(),
+                });
+                self.control_flow_policy = ControlFlowPolicy::Continuous;
+            }
+            (false, Some(benchmark)) => {
+                println!("{}", benchmark.stats);
+                // it was in use, so it is supported
+                self.graphic_engine
+                    .set_present_mode(benchmark.present_mode)
+                    .unwrap();
+                self.control_flow_policy = benchmark.control_flow_policy;
+            }
+            (_, benchmark) => self.benchmark = benchmark,
+        }
+    }
+
+    /// Frame times measured in benchmark mode, `None` when it is off. Read from the `run_with`
+    /// callback while running.
+    pub fn benchmark_stats(&self) -> Option<&FrameStats> {
+        self.benchmark.as_ref().map(|benchmark| &benchmark.stats)
+    }
+
+    pub fn run(self) {
+        self.run_with(|_| {});
+    }
+
+    /// Like `run`, calling `on_frame` with the app before each frame is rendered, to read `input`
+    /// and change settings while the app runs, e.g. `set_fullscreen` or `benchmark_mode`.
+    pub fn run_with(mut self, mut on_frame: impl FnMut(&mut App) + 'static) {
         let mut recreate_swapchain = false;
-        self.event_loop.run(move |event, _, control_flow| {
+        let mut next_frame = Instant::now();
+        let event_loop = self.event_loop.take().unwrap();
+        event_loop.run(move |event, _, control_flow| {
+            *control_flow = match self.control_flow_policy {
+                ControlFlowPolicy::Continuous => ControlFlow::Poll,
+                ControlFlowPolicy::OnDemand => ControlFlow::Wait,
+                ControlFlowPolicy::EnergySaving(_) => ControlFlow::WaitUntil(next_frame),
+            };
+
+            self.input.handle_event(&event);
+
             match event {
                 Event::WindowEvent {
                     event: WindowEvent::CloseRequested,
@@ -58,22 +416,73 @@ impl App {
                 } => {
                     *control_flow = ControlFlow::Exit;
                 }
+                Event::LoopDestroyed => {
+                    if self.fullscreen() != FullscreenMode::Windowed {
+                        self.set_fullscreen(FullscreenMode::Windowed);
+                    }
+                    if let Err(e) = self.graphic_engine.save_pipeline_cache() {
+                        println!("{}", e);
+                    }
+                    if let Err(e) = self.graphic_engine.stop_recording() {
+                        println!("{}", e);
+                    }
+                }
                 Event::WindowEvent {
                     event: WindowEvent::Resized(_),
                     ..
                 } => {
                     recreate_swapchain = true;
+                    if self.control_flow_policy == ControlFlowPolicy::OnDemand {
+                        self.graphic_engine.request_redraw();
+                    }
+                }
+                Event::UserEvent(UserEvent::Wake) => {}
+                Event::UserEvent(UserEvent::RequestRedraw) => {
+                    self.graphic_engine.request_redraw();
                 }
-                Event::RedrawEventsCleared => {
-                    self.graphic_engine.render(&mut recreate_swapchain);
+                Event::RedrawRequested(_)
+                    if self.control_flow_policy == ControlFlowPolicy::OnDemand =>
+                {
+                    self.frame(&mut on_frame, &mut recreate_swapchain);
                 }
+                Event::RedrawEventsCleared => match self.control_flow_policy {
+                    ControlFlowPolicy::Continuous => {
+                        self.frame(&mut on_frame, &mut recreate_swapchain);
+                    }
+                    ControlFlowPolicy::OnDemand => {}
+                    ControlFlowPolicy::EnergySaving(target_frame_time) => {
+                        let now = Instant::now();
+                        if now >= next_frame {
+                            self.frame(&mut on_frame, &mut recreate_swapchain);
+                            // skip missed frames rather than rendering them back to back
+                            next_frame = (next_frame + target_frame_time).max(now);
+                            *control_flow = ControlFlow::WaitUntil(next_frame);
+                        }
+                    }
+                },
                 _ => {}
             }
-
-            if recreate_swapchain {
-                self.graphic_engine
-                    .recreate_swapchain(&mut recreate_swapchain);
-            }
         });
     }
+
+    fn frame(&mut self, on_frame: &mut impl FnMut(&mut App), recreate_swapchain: &mut bool) {
+        on_frame(self);
+        self.graphic_engine.render(recreate_swapchain);
+        self.input.end_frame();
+        if let Some(benchmark) = &mut self.benchmark {
+            benchmark.frame_rendered();
+        }
+    }
+}
+
+fn check_size_limits(min: Option<(u32, u32)>, max: Option<(u32, u32)>) -> Result<(), EngineError> {
+    match (min, max) {
+        (Some(min), Some(max)) if min.0 > max.0 || min.1 > max.1 => {
+            Err(EngineError::WindowSizeLimits {
+                min: [min.0, min.1],
+                max: [max.0, max.1],
+            })
+        }
+        _ => Ok(()),
+    }
 }